@@ -0,0 +1,1109 @@
+//! Conversion between models and And-Inverter Graphs in the AIGER format.
+//!
+//! [`Aig::from_model`] turns a flat model into an [`Aig`], which
+//! [`write_aag`] and [`write_aig`] write in the ASCII and the binary AIGER
+//! format. [`parse_aiger`] reads both formats, and [`Aig::to_model`] turns
+//! the result back into a model:
+//!
+//! ```rust
+//! use turbo_blif::aiger::{Aig, parse_aiger, write_aag};
+//! use turbo_blif::ast::parse_str_blif_to_ast;
+//!
+//! let blif = parse_str_blif_to_ast(
+//!     "top.blif",
+//!     ".model top\n.inputs a b\n.outputs y\n.names a q d\n11 1\n\
+//!      .latch d q 1\n.names q b y\n0- 1\n-0 1\n.end\n",
+//! )
+//! .unwrap();
+//! let aig = Aig::from_model(blif.model("top").unwrap()).unwrap();
+//!
+//! let mut out = String::new();
+//! write_aag(&aig, &mut out).unwrap();
+//! assert_eq!(
+//!     out,
+//!     "aag 5 2 1 1 2\n2\n4\n6 8 1\n11\n8 6 2\n10 6 4\ni0 a\ni1 b\nl0 q\no0 y\n"
+//! );
+//!
+//! let back = parse_aiger(out.as_bytes()).unwrap();
+//! assert_eq!(back, aig);
+//! assert_eq!(back.to_model("top").commands.len(), 4);
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
+
+use smallvec::SmallVec;
+
+use crate::ast::{Blif, BlifEntry, Gate, LUT, Model, ModelAttr, ModelCmd, ModelCmdKind};
+use crate::netlist::{NetId, Netlist};
+use crate::topo::{TopoError, topo_order_cells};
+use crate::{FlipFlop, FlipFlopInit, GateMeta, ModelMeta, Str, Tristate};
+
+/// A literal: twice the variable index, plus one if it is negated. `0` is
+/// false and `1` is true.
+pub type Lit = u32;
+
+/// An input or output of an [`Aig`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Port {
+    /// The literal of the port. For inputs, it is never negated.
+    pub lit: Lit,
+    /// Name from the symbol table.
+    pub name: Option<String>,
+}
+
+/// A latch of an [`Aig`].
+#[derive(Debug, Clone, PartialEq, Hash)]
+pub struct Latch {
+    /// The current state, never negated.
+    pub lit: Lit,
+    /// The next state.
+    pub next: Lit,
+    /// The reset value. AIGER can't tell apart [`FlipFlopInit::DontCare`]
+    /// and [`FlipFlopInit::Unknown`]; both are written as uninitialized.
+    pub init: FlipFlopInit,
+    /// Name from the symbol table.
+    pub name: Option<String>,
+}
+
+/// A justice property of an [`Aig`]: some path visits states where all of
+/// `lits` hold infinitely often.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Justice {
+    /// The literals that must hold infinitely often.
+    pub lits: Vec<Lit>,
+    /// Name from the symbol table.
+    pub name: Option<String>,
+}
+
+/// An AND gate, `lhs = rhs0 & rhs1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct And {
+    /// The output, never negated.
+    pub lhs: Lit,
+    /// The first input.
+    pub rhs0: Lit,
+    /// The second input.
+    pub rhs1: Lit,
+}
+
+/// An And-Inverter Graph with latches, as described by the AIGER format.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Aig {
+    /// The largest variable index.
+    pub max_var: u32,
+    /// Primary inputs.
+    pub inputs: Vec<Port>,
+    /// Latches, which are all clocked by the same implicit clock.
+    pub latches: Vec<Latch>,
+    /// Primary outputs.
+    pub outputs: Vec<Port>,
+    /// Bad-state properties of AIGER 1.9.
+    pub bad: Vec<Port>,
+    /// Invariant constraints of AIGER 1.9.
+    pub constraints: Vec<Port>,
+    /// Justice properties of AIGER 1.9.
+    pub justice: Vec<Justice>,
+    /// Fairness constraints of AIGER 1.9.
+    pub fairness: Vec<Port>,
+    /// AND gates.
+    pub ands: Vec<And>,
+    /// Lines of the comment section.
+    pub comments: Vec<String>,
+}
+
+/// Why a model can not be converted to an [`Aig`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AigerError {
+    /// The model contains a `.gate` or `.mlatch`, whose function is unknown.
+    LibraryCell {
+        /// Name of the model.
+        model: String,
+        /// Name of the library cell.
+        cell: String,
+    },
+    /// The model contains a `.subckt`; flatten it first.
+    SubModel {
+        /// Name of the model.
+        model: String,
+        /// Name of the instantiated model.
+        instance_of: String,
+    },
+    /// The model contains a `.start_kiss` FSM.
+    Fsm {
+        /// Name of the model.
+        model: String,
+    },
+    /// A net is read, but nothing drives it.
+    Undriven {
+        /// Name of the model.
+        model: String,
+        /// Name of the net.
+        net: String,
+    },
+    /// A net has more than one driver.
+    MultipleDrivers {
+        /// Name of the model.
+        model: String,
+        /// Name of the net.
+        net: String,
+    },
+    /// The gates form a combinational loop.
+    Loop(TopoError),
+}
+
+impl fmt::Display for AigerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AigerError::LibraryCell { model, cell } => write!(
+                f,
+                "model `{model}` uses the library cell `{cell}`, which has to be mapped to `.names` first"
+            ),
+            AigerError::SubModel { model, instance_of } => write!(
+                f,
+                "model `{model}` instantiates model `{instance_of}` and has to be flattened first"
+            ),
+            AigerError::Fsm { model } => write!(
+                f,
+                "model `{model}` contains an FSM, which can not be converted to an AIG"
+            ),
+            AigerError::Undriven { model, net } => {
+                write!(f, "model `{model}`: net `{net}` is read, but never driven")
+            }
+            AigerError::MultipleDrivers { model, net } => {
+                write!(f, "model `{model}`: net `{net}` has more than one driver")
+            }
+            AigerError::Loop(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for AigerError {}
+
+impl From<TopoError> for AigerError {
+    fn from(err: TopoError) -> Self {
+        AigerError::Loop(err)
+    }
+}
+
+/// Creates AND gates, reusing existing ones for the same inputs.
+struct Builder {
+    next_var: u32,
+    ands: Vec<And>,
+    hashed: HashMap<(Lit, Lit), Lit>,
+}
+
+impl Builder {
+    fn and(&mut self, a: Lit, b: Lit) -> Lit {
+        let (rhs0, rhs1) = if a >= b { (a, b) } else { (b, a) };
+        if rhs1 == 0 || rhs0 == rhs1 ^ 1 {
+            return 0;
+        }
+        if rhs1 == 1 || rhs0 == rhs1 {
+            return rhs0;
+        }
+        *self.hashed.entry((rhs0, rhs1)).or_insert_with(|| {
+            let lhs = 2 * self.next_var;
+            self.next_var += 1;
+            self.ands.push(And { lhs, rhs0, rhs1 });
+            lhs
+        })
+    }
+
+    /// The conjunction of `lits`, as a balanced tree.
+    fn and_all(&mut self, mut lits: Vec<Lit>) -> Lit {
+        while lits.len() > 1 {
+            lits = lits
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => self.and(*a, *b),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+        }
+        lits.pop().unwrap_or(1)
+    }
+
+    /// The disjunction of `lits`, as a balanced tree.
+    fn or_all(&mut self, lits: Vec<Lit>) -> Lit {
+        self.and_all(lits.into_iter().map(|x| x ^ 1).collect()) ^ 1
+    }
+}
+
+impl Aig {
+    /// Convert `model` to an AIG.
+    ///
+    /// The cover of every `.names` is decomposed into a sum of products of
+    /// AND gates and inverters, where equal AND gates are shared. `.conn` are
+    /// wires, and `.latch` become AIGER latches with the same reset value.
+    /// The inputs are the nets of `.inputs` and `.clock`, and the clocks and
+    /// types of latches are ignored, because AIGER has a single implicit
+    /// clock. Timing constraints and `.exdc` gates are ignored too.
+    ///
+    /// Inputs, latches and outputs get the names of their nets in the symbol
+    /// table.
+    ///
+    /// # Errors
+    ///
+    /// Fails for models that contain library cells, sub-circuits or FSMs,
+    /// have undriven or multiply driven nets, or combinational loops.
+    pub fn from_model(model: &Model) -> Result<Aig, AigerError> {
+        let model_name = || model.meta.name.to_string();
+        for cmd in &model.commands {
+            match &cmd.kind {
+                ModelCmdKind::LibGate(lg) => {
+                    return Err(AigerError::LibraryCell {
+                        model: model_name(),
+                        cell: lg.name.to_string(),
+                    });
+                }
+                ModelCmdKind::LibFF(lf) => {
+                    return Err(AigerError::LibraryCell {
+                        model: model_name(),
+                        cell: lf.name.to_string(),
+                    });
+                }
+                ModelCmdKind::SubModel { name, .. } => {
+                    return Err(AigerError::SubModel {
+                        model: model_name(),
+                        instance_of: name.to_string(),
+                    });
+                }
+                ModelCmdKind::FSM(_) => {
+                    return Err(AigerError::Fsm {
+                        model: model_name(),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        let netlist = Netlist::new(model);
+        if let Some((_, net)) = netlist.nets().find(|(_, net)| net.drivers.len() > 1) {
+            return Err(AigerError::MultipleDrivers {
+                model: model_name(),
+                net: net.name().to_string(),
+            });
+        }
+        let order = topo_order_cells(&netlist)?;
+
+        let mut lits: Vec<Option<Lit>> = vec![None; netlist.net_count()];
+        let lit_of = |lits: &[Option<Lit>], net: NetId| {
+            lits[net.0].ok_or_else(|| AigerError::Undriven {
+                model: model_name(),
+                net: netlist.net(net).name().to_string(),
+            })
+        };
+
+        let mut aig = Aig::default();
+        let mut var = 0;
+        for &net in netlist.inputs() {
+            var += 1;
+            lits[net.0] = Some(2 * var);
+            aig.inputs.push(Port {
+                lit: 2 * var,
+                name: Some(netlist.net(net).name().to_string()),
+            });
+        }
+
+        let latches: Vec<_> = order
+            .iter()
+            .map(|&id| netlist.cell(id))
+            .filter(|cell| matches!(cell.cmd.kind, ModelCmdKind::FF(_)))
+            .collect();
+        for cell in &latches {
+            let ModelCmdKind::FF(ff) = &cell.cmd.kind else {
+                unreachable!()
+            };
+            var += 1;
+            lits[cell.pins[1].net.0] = Some(2 * var);
+            aig.latches.push(Latch {
+                lit: 2 * var,
+                next: 0,
+                init: ff.init.clone(),
+                name: Some(netlist.net(cell.pins[1].net).name().to_string()),
+            });
+        }
+
+        let mut builder = Builder {
+            next_var: var + 1,
+            ands: vec![],
+            hashed: HashMap::new(),
+        };
+        for &id in &order {
+            let cell = netlist.cell(id);
+            let ModelCmdKind::Gate(gate) = &cell.cmd.kind else {
+                continue;
+            };
+            let (output, inputs) = cell.pins.split_last().unwrap();
+            let inputs = inputs
+                .iter()
+                .map(|pin| lit_of(&lits, pin.net))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            // like `LUT::eval`: rows with the output 0 describe the off-set
+            let off = gate.lut.0.iter().any(|(_, out)| *out == Some(false));
+            let products = gate
+                .lut
+                .0
+                .iter()
+                .filter(|(_, out)| *out == Some(!off))
+                .map(|(row, _)| {
+                    let literals = row
+                        .iter()
+                        .zip(&inputs)
+                        .filter_map(|(value, &lit)| match value {
+                            Tristate::False => Some(lit ^ 1),
+                            Tristate::True => Some(lit),
+                            Tristate::Ignored => None,
+                        })
+                        .collect();
+                    builder.and_all(literals)
+                })
+                .collect();
+            let sum = builder.or_all(products);
+            lits[output.net.0] = Some(sum ^ off as Lit);
+        }
+
+        for (latch, cell) in aig.latches.iter_mut().zip(&latches) {
+            latch.next = lit_of(&lits, cell.pins[0].net)?;
+        }
+        for (&net, name) in netlist
+            .outputs()
+            .iter()
+            .zip(model.meta.outputs.iter().flatten())
+        {
+            aig.outputs.push(Port {
+                lit: lit_of(&lits, net)?,
+                name: Some(name.to_string()),
+            });
+        }
+
+        aig.max_var = builder.next_var - 1;
+        aig.ands = builder.ands;
+        Ok(aig)
+    }
+}
+
+impl Aig {
+    /// Convert the AIG to a model called `name`, made of `.names` with at
+    /// most two inputs and `.latch` with the reset values of the latches.
+    ///
+    /// Inputs, latches and outputs get their names from the symbol table,
+    /// or `i0`, `l0`, `o0` and so on if they have none. AND gates are
+    /// called `n` followed by their variable index, unless an output takes
+    /// their name, and negated literals that are not read by an AND gate get
+    /// an inverter. Names that are taken get a `_1`, `_2`, ... suffix.
+    ///
+    /// Bad-state properties, and the literals of justice and fairness
+    /// properties, become outputs after the regular ones, called `b0`, `j0_0`,
+    /// `f0` and so on by default. Invariant constraints become a single
+    /// `.constraint` command.
+    pub fn to_model(&self, name: &str) -> Model {
+        let mut b = ModelBuilder::default();
+        let default_name = |name: &Option<String>, kind: &str, i: usize| {
+            name.clone().unwrap_or_else(|| format!("{kind}{i}"))
+        };
+
+        let mut inputs = vec![];
+        for (i, port) in self.inputs.iter().enumerate() {
+            let net = b.unique(&default_name(&port.name, "i", i));
+            b.vars.insert(port.lit / 2, net.clone());
+            inputs.push(net);
+        }
+        let mut latch_outputs = vec![];
+        for (i, latch) in self.latches.iter().enumerate() {
+            let net = b.unique(&default_name(&latch.name, "l", i));
+            b.vars.insert(latch.lit / 2, net.clone());
+            latch_outputs.push(net);
+        }
+
+        let mut outputs = vec![];
+        for (kind, ports) in [("o", &self.outputs), ("b", &self.bad)] {
+            for (i, port) in ports.iter().enumerate() {
+                outputs.push(b.port(port.lit, &default_name(&port.name, kind, i)));
+            }
+        }
+        for (i, justice) in self.justice.iter().enumerate() {
+            let name = default_name(&justice.name, "j", i);
+            for (k, &lit) in justice.lits.iter().enumerate() {
+                outputs.push(b.port(lit, &format!("{name}_{k}")));
+            }
+        }
+        for (i, port) in self.fairness.iter().enumerate() {
+            outputs.push(b.port(port.lit, &default_name(&port.name, "f", i)));
+        }
+        let constraints: Vec<Str<16>> = self
+            .constraints
+            .iter()
+            .enumerate()
+            .map(|(i, port)| b.port(port.lit, &default_name(&port.name, "c", i)))
+            .collect();
+
+        let mut commands: Vec<ModelCmd> = vec![];
+        for (latch, output) in self.latches.iter().zip(latch_outputs) {
+            let ff = FlipFlop {
+                ty: None,
+                input: b.lit(latch.next),
+                output,
+                clock: None,
+                init: latch.init.clone(),
+            };
+            commands.push(ModelCmdKind::FF(ff).into());
+        }
+        for and in &self.ands {
+            let output = b.var(and.lhs / 2);
+            let mut inputs = vec![];
+            let mut row = SmallVec::new();
+            let mut zero = false;
+            for rhs in [and.rhs0, and.rhs1] {
+                match rhs {
+                    0 => zero = true,
+                    1 => {}
+                    _ => {
+                        inputs.push(b.var(rhs / 2));
+                        row.push(polarity(rhs));
+                    }
+                }
+            }
+            let rows = if zero {
+                vec![]
+            } else {
+                vec![(row, Some(true))]
+            };
+            commands.push(names(inputs, output, rows).into());
+        }
+        commands.append(&mut b.gates);
+        if !constraints.is_empty() {
+            commands.push(ModelCmdKind::Constraint(constraints).into());
+        }
+
+        Model {
+            meta: ModelMeta {
+                name: name.into(),
+                inputs: Some(inputs),
+                outputs: Some(outputs),
+                clocks: vec![],
+            },
+            commands,
+            attr: ModelAttr::default(),
+        }
+    }
+}
+
+/// The row value that selects the literal `lit`.
+fn polarity(lit: Lit) -> Tristate {
+    if lit & 1 == 1 {
+        Tristate::False
+    } else {
+        Tristate::True
+    }
+}
+
+fn names(
+    inputs: Vec<Str<16>>,
+    output: Str<16>,
+    rows: Vec<(SmallVec<[Tristate; 8]>, Option<bool>)>,
+) -> ModelCmdKind {
+    ModelCmdKind::Gate(Gate {
+        meta: GateMeta {
+            inputs,
+            output,
+            external_dc: false,
+            syntax: Default::default(),
+        },
+        lut: LUT(rows),
+    })
+}
+
+/// Names the nets of an [`Aig`] that is converted to a [`Model`].
+#[derive(Default)]
+struct ModelBuilder {
+    used: HashSet<String>,
+    /// The net of every variable that has one.
+    vars: HashMap<u32, Str<16>>,
+    /// Nets of negated literals and constants.
+    derived: HashMap<Lit, Str<16>>,
+    /// Inverters, constants and buffers for outputs.
+    gates: Vec<ModelCmd>,
+}
+
+impl ModelBuilder {
+    /// `name`, or `name` with a suffix if it is taken.
+    fn unique(&mut self, name: &str) -> Str<16> {
+        let mut candidate = name.to_string();
+        let mut n = 0;
+        while !self.used.insert(candidate.clone()) {
+            n += 1;
+            candidate = format!("{name}_{n}");
+        }
+        candidate.as_str().into()
+    }
+
+    /// The net of the variable `var`.
+    fn var(&mut self, var: u32) -> Str<16> {
+        if let Some(net) = self.vars.get(&var) {
+            return net.clone();
+        }
+        let net = self.unique(&format!("n{var}"));
+        self.vars.insert(var, net.clone());
+        net
+    }
+
+    /// A net with the value of `lit`.
+    fn lit(&mut self, lit: Lit) -> Str<16> {
+        if lit >= 2 && lit & 1 == 0 {
+            return self.var(lit / 2);
+        }
+        if let Some(net) = self.derived.get(&lit) {
+            return net.clone();
+        }
+        let net = match lit {
+            0 => self.unique("$false"),
+            1 => self.unique("$true"),
+            _ => {
+                let var = self.var(lit / 2);
+                self.unique(&format!("{var}_n"))
+            }
+        };
+        self.derived.insert(lit, net.clone());
+        self.copy(lit, net.clone());
+        net
+    }
+
+    /// A `.names` that drives `output` with the value of `lit`.
+    fn copy(&mut self, lit: Lit, output: Str<16>) {
+        let gate = match lit {
+            0 => names(vec![], output, vec![]),
+            1 => names(vec![], output, vec![(SmallVec::new(), Some(true))]),
+            _ => {
+                let row = SmallVec::from_elem(polarity(lit), 1);
+                names(vec![self.var(lit / 2)], output, vec![(row, Some(true))])
+            }
+        };
+        self.gates.push(gate.into());
+    }
+
+    /// The net of an output called `name` with the value `lit`. The output
+    /// is the net of `lit` itself if that has no name yet, and a copy of it
+    /// otherwise.
+    fn port(&mut self, lit: Lit, name: &str) -> Str<16> {
+        if lit >= 2 && lit & 1 == 0 {
+            match self.vars.get(&(lit / 2)) {
+                None if !self.used.contains(name) => {
+                    let net = self.unique(name);
+                    self.vars.insert(lit / 2, net.clone());
+                    return net;
+                }
+                Some(net) if net == name => return net.clone(),
+                _ => {}
+            }
+        }
+        let net = self.unique(name);
+        self.copy(lit, net.clone());
+        net
+    }
+}
+
+/// The init field of a latch line, if it is not the default 0.
+fn latch_init(latch: &Latch) -> Option<Lit> {
+    match latch.init {
+        FlipFlopInit::Const(false) => None,
+        FlipFlopInit::Const(true) => Some(1),
+        FlipFlopInit::DontCare | FlipFlopInit::Unknown => Some(latch.lit),
+    }
+}
+
+/// The header line, without the format identifier. The sizes of the
+/// sections of AIGER 1.9 are only written if one of them is not empty.
+fn header(aig: &Aig) -> String {
+    let mut header = format!(
+        "{} {} {} {} {}",
+        aig.max_var,
+        aig.inputs.len(),
+        aig.latches.len(),
+        aig.outputs.len(),
+        aig.ands.len()
+    );
+    let extra = [
+        aig.bad.len(),
+        aig.constraints.len(),
+        aig.justice.len(),
+        aig.fairness.len(),
+    ];
+    if extra.iter().any(|&n| n > 0) {
+        for n in extra {
+            header += &format!(" {n}");
+        }
+    }
+    header
+}
+
+/// The outputs and the properties, which look the same in both formats.
+fn write_outputs<W: fmt::Write>(aig: &Aig, w: &mut W) -> fmt::Result {
+    for port in aig.outputs.iter().chain(&aig.bad).chain(&aig.constraints) {
+        writeln!(w, "{}", port.lit)?;
+    }
+    for justice in &aig.justice {
+        writeln!(w, "{}", justice.lits.len())?;
+    }
+    for lit in aig.justice.iter().flat_map(|j| &j.lits) {
+        writeln!(w, "{lit}")?;
+    }
+    for port in &aig.fairness {
+        writeln!(w, "{}", port.lit)?;
+    }
+    Ok(())
+}
+
+/// The symbol table and the comment section.
+fn write_symbols<W: fmt::Write>(aig: &Aig, w: &mut W) -> fmt::Result {
+    let ports = |ports: &[Port]| ports.iter().map(|p| p.name.clone()).collect::<Vec<_>>();
+    let sections = [
+        ('i', ports(&aig.inputs)),
+        ('l', aig.latches.iter().map(|l| l.name.clone()).collect()),
+        ('o', ports(&aig.outputs)),
+        ('b', ports(&aig.bad)),
+        ('c', ports(&aig.constraints)),
+        ('j', aig.justice.iter().map(|j| j.name.clone()).collect()),
+        ('f', ports(&aig.fairness)),
+    ];
+    for (kind, names) in sections {
+        for (i, name) in names.into_iter().enumerate() {
+            if let Some(name) = name {
+                writeln!(w, "{kind}{i} {name}")?;
+            }
+        }
+    }
+    if !aig.comments.is_empty() {
+        writeln!(w, "c")?;
+        for line in &aig.comments {
+            writeln!(w, "{line}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Write `aig` in the ASCII AIGER format (`.aag`).
+///
+/// Latches with a reset value other than 0 get the reset field of AIGER 1.9,
+/// and so do the header and the sections of its properties if there are any.
+///
+/// # Errors
+///
+/// Returns [`fmt::Error`] if the underlying writer fails.
+pub fn write_aag<W: fmt::Write>(aig: &Aig, w: &mut W) -> fmt::Result {
+    writeln!(w, "aag {}", header(aig))?;
+    for input in &aig.inputs {
+        writeln!(w, "{}", input.lit)?;
+    }
+    for latch in &aig.latches {
+        write!(w, "{} {}", latch.lit, latch.next)?;
+        if let Some(init) = latch_init(latch) {
+            write!(w, " {init}")?;
+        }
+        writeln!(w)?;
+    }
+    write_outputs(aig, w)?;
+    for and in &aig.ands {
+        writeln!(w, "{} {} {}", and.lhs, and.rhs0, and.rhs1)?;
+    }
+    write_symbols(aig, w)
+}
+
+/// Whether the variables of `aig` are numbered like the binary format
+/// requires: inputs, then latches, then AND gates, in order, each AND gate
+/// after its inputs.
+fn is_binary_order(aig: &Aig) -> bool {
+    let inputs = aig.inputs.len() as u32;
+    let latches = aig.latches.len() as u32;
+    aig.inputs
+        .iter()
+        .enumerate()
+        .all(|(i, p)| p.lit == 2 * (i as u32 + 1))
+        && aig
+            .latches
+            .iter()
+            .enumerate()
+            .all(|(i, l)| l.lit == 2 * (inputs + i as u32 + 1))
+        && aig.ands.iter().enumerate().all(|(i, a)| {
+            a.lhs == 2 * (inputs + latches + i as u32 + 1) && a.lhs > a.rhs0 && a.rhs0 >= a.rhs1
+        })
+        && aig.max_var == inputs + latches + aig.ands.len() as u32
+}
+
+/// Write `n` in the variable-length encoding of the binary format.
+fn write_delta(out: &mut Vec<u8>, mut n: u32) {
+    while n >= 0x80 {
+        out.push(n as u8 & 0x7f | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// Write `aig` in the binary AIGER format (`.aig`).
+///
+/// The binary format leaves out the literals of inputs, latches and AND
+/// gates, so they must be numbered in that order, starting at 2, and every
+/// AND gate must have `lhs > rhs0 >= rhs1`. [`Aig::from_model`] and
+/// [`parse_aiger`] of a binary file number them like that.
+///
+/// # Errors
+///
+/// Returns an error of the kind [`io::ErrorKind::InvalidInput`] if the
+/// variables are not numbered like that, and the first I/O error of the
+/// underlying writer.
+pub fn write_aig<W: io::Write>(aig: &Aig, mut w: W) -> io::Result<()> {
+    if !is_binary_order(aig) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the variables of the AIG are not numbered like the binary AIGER format requires",
+        ));
+    }
+
+    let mut text = format!("aig {}\n", header(aig));
+    for latch in &aig.latches {
+        match latch_init(latch) {
+            Some(init) => text += &format!("{} {init}\n", latch.next),
+            None => text += &format!("{}\n", latch.next),
+        }
+    }
+    write_outputs(aig, &mut text).expect("writing to a String never fails");
+    let mut out = text.into_bytes();
+    for and in &aig.ands {
+        write_delta(&mut out, and.lhs - and.rhs0);
+        write_delta(&mut out, and.rhs0 - and.rhs1);
+    }
+    let mut symbols = String::new();
+    write_symbols(aig, &mut symbols).expect("writing to a String never fails");
+    out.extend_from_slice(symbols.as_bytes());
+    w.write_all(&out)?;
+    w.flush()
+}
+
+/// Why an AIGER file can not be read.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AigerParseError {
+    /// The first line is not `aag` or `aig` followed by the sizes of the
+    /// sections.
+    Header,
+    /// A line is malformed, or a literal in it is out of range or defines a
+    /// variable for the second time.
+    Invalid {
+        /// The line, starting at 1.
+        line: usize,
+    },
+    /// The AND gates of a binary file are truncated or malformed.
+    Binary,
+    /// The file ends before all sections were read.
+    UnexpectedEnd,
+    /// A literal refers to a variable that is never defined.
+    Undefined {
+        /// The literal.
+        lit: Lit,
+    },
+}
+
+impl fmt::Display for AigerParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AigerParseError::Header => write!(f, "invalid AIGER header"),
+            AigerParseError::Invalid { line } => write!(f, "line {line}: invalid AIGER syntax"),
+            AigerParseError::Binary => write!(f, "invalid binary AND gates"),
+            AigerParseError::UnexpectedEnd => write!(f, "unexpected end of file"),
+            AigerParseError::Undefined { lit } => {
+                write!(f, "literal {lit} refers to an undefined variable")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AigerParseError {}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// The number of the last line that was read.
+    line: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn at_end(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    /// The next line, without the line break.
+    fn line(&mut self) -> Result<&'a [u8], AigerParseError> {
+        let rest = self
+            .data
+            .get(self.pos..)
+            .filter(|rest| !rest.is_empty())
+            .ok_or(AigerParseError::UnexpectedEnd)?;
+        let len = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+        self.pos += len + 1;
+        self.line += 1;
+        let line = &rest[..len];
+        Ok(line.strip_suffix(b"\r").unwrap_or(line))
+    }
+
+    fn invalid(&self) -> AigerParseError {
+        AigerParseError::Invalid { line: self.line }
+    }
+
+    /// The next line, which must consist of `min` to `max` numbers.
+    fn numbers(&mut self, min: usize, max: usize) -> Result<Vec<u32>, AigerParseError> {
+        let line = self.line()?;
+        let nums = std::str::from_utf8(line)
+            .ok()
+            .and_then(|text| {
+                text.split_whitespace()
+                    .map(|x| x.parse().ok())
+                    .collect::<Option<Vec<u32>>>()
+            })
+            .filter(|nums| (min..=max).contains(&nums.len()));
+        nums.ok_or_else(|| self.invalid())
+    }
+
+    /// A number in the variable-length encoding of the binary format.
+    fn delta(&mut self) -> Result<u32, AigerParseError> {
+        let mut n: u32 = 0;
+        for shift in (0..32).step_by(7) {
+            let &byte = self.data.get(self.pos).ok_or(AigerParseError::Binary)?;
+            self.pos += 1;
+            let bits = u32::from(byte & 0x7f);
+            if bits.leading_zeros() < shift {
+                return Err(AigerParseError::Binary);
+            }
+            n |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(AigerParseError::Binary)
+    }
+}
+
+/// Checks the literals while a file is read.
+struct Literals {
+    max_lit: Lit,
+    defined: Vec<bool>,
+}
+
+impl Literals {
+    fn check(&self, lit: Lit, r: &Reader) -> Result<Lit, AigerParseError> {
+        if lit <= self.max_lit {
+            Ok(lit)
+        } else {
+            Err(r.invalid())
+        }
+    }
+
+    /// Mark the variable of `lit` as defined.
+    fn define(&mut self, lit: Lit, r: &Reader) -> Result<Lit, AigerParseError> {
+        let var = self.check(lit, r)? as usize / 2;
+        if lit & 1 == 1 || var == 0 || self.defined[var] {
+            return Err(r.invalid());
+        }
+        self.defined[var] = true;
+        Ok(lit)
+    }
+
+    fn read(&self, r: &mut Reader) -> Result<Lit, AigerParseError> {
+        let lit = r.numbers(1, 1)?[0];
+        self.check(lit, r)
+    }
+}
+
+fn port_name(ports: &mut [Port], idx: usize) -> Option<&mut Option<String>> {
+    ports.get_mut(idx).map(|p| &mut p.name)
+}
+
+/// Read an AIGER file in the ASCII (`aag`) or binary (`aig`) format, which
+/// is told apart by the header.
+///
+/// The sections of AIGER 1.9 for bad-state properties, invariant
+/// constraints, justice and fairness properties, and the reset values of
+/// latches are supported. Uninitialized latches get the reset value
+/// [`FlipFlopInit::Unknown`].
+///
+/// # Errors
+///
+/// Fails if the file is malformed, or a literal refers to a variable that is
+/// not an input, latch or AND gate.
+pub fn parse_aiger(source: &[u8]) -> Result<Aig, AigerParseError> {
+    let mut r = Reader {
+        data: source,
+        pos: 0,
+        line: 0,
+    };
+    let header = std::str::from_utf8(r.line()?).map_err(|_| AigerParseError::Header)?;
+    let mut fields = header.split_whitespace();
+    let binary = match fields.next() {
+        Some("aag") => false,
+        Some("aig") => true,
+        _ => return Err(AigerParseError::Header),
+    };
+    let sizes: Vec<u32> = fields
+        .map(|x| x.parse().map_err(|_| AigerParseError::Header))
+        .collect::<Result<_, _>>()?;
+    if !(5..=9).contains(&sizes.len()) {
+        return Err(AigerParseError::Header);
+    }
+    let size = |i: usize| sizes.get(i).copied().unwrap_or(0);
+    let (max_var, inputs, latches, outputs, ands) = (size(0), size(1), size(2), size(3), size(4));
+    let max_lit = max_var
+        .checked_mul(2)
+        .and_then(|x| x.checked_add(1))
+        .ok_or(AigerParseError::Header)?;
+    let vars = inputs
+        .checked_add(latches)
+        .and_then(|x| x.checked_add(ands));
+    if vars.is_none_or(|vars| vars > max_var || binary && vars != max_var) {
+        return Err(AigerParseError::Header);
+    }
+
+    let mut lits = Literals {
+        max_lit,
+        defined: vec![false; max_var as usize + 1],
+    };
+    let mut aig = Aig {
+        max_var,
+        ..Aig::default()
+    };
+    for i in 0..inputs {
+        let lit = if binary {
+            lits.define(2 * (i + 1), &r)?
+        } else {
+            let lit = r.numbers(1, 1)?[0];
+            lits.define(lit, &r)?
+        };
+        aig.inputs.push(Port { lit, name: None });
+    }
+    for i in 0..latches {
+        let (lit, fields) = if binary {
+            let fields = r.numbers(1, 2)?;
+            (lits.define(2 * (inputs + i + 1), &r)?, fields)
+        } else {
+            let mut fields = r.numbers(2, 3)?;
+            (lits.define(fields.remove(0), &r)?, fields)
+        };
+        let next = lits.check(fields[0], &r)?;
+        let init = match fields.get(1) {
+            None | Some(0) => FlipFlopInit::Const(false),
+            Some(1) => FlipFlopInit::Const(true),
+            Some(&x) if x == lit => FlipFlopInit::Unknown,
+            Some(_) => return Err(r.invalid()),
+        };
+        aig.latches.push(Latch {
+            lit,
+            next,
+            init,
+            name: None,
+        });
+    }
+
+    let ports = |count: u32, r: &mut Reader| {
+        (0..count)
+            .map(|_| {
+                Ok(Port {
+                    lit: lits.read(r)?,
+                    name: None,
+                })
+            })
+            .collect::<Result<Vec<_>, AigerParseError>>()
+    };
+    aig.outputs = ports(outputs, &mut r)?;
+    aig.bad = ports(size(5), &mut r)?;
+    aig.constraints = ports(size(6), &mut r)?;
+    let justice_sizes = (0..size(7))
+        .map(|_| Ok(r.numbers(1, 1)?[0]))
+        .collect::<Result<Vec<_>, AigerParseError>>()?;
+    for size in justice_sizes {
+        let lits = (0..size)
+            .map(|_| lits.read(&mut r))
+            .collect::<Result<_, _>>()?;
+        aig.justice.push(Justice { lits, name: None });
+    }
+    aig.fairness = ports(size(8), &mut r)?;
+
+    for i in 0..ands {
+        let and = if binary {
+            let lhs = lits.define(2 * (inputs + latches + i + 1), &r)?;
+            let rhs0 = lhs.checked_sub(r.delta()?);
+            let rhs1 = rhs0.and_then(|rhs0| rhs0.checked_sub(r.delta().ok()?));
+            let (Some(rhs0), Some(rhs1)) = (rhs0, rhs1) else {
+                return Err(AigerParseError::Binary);
+            };
+            And { lhs, rhs0, rhs1 }
+        } else {
+            let fields = r.numbers(3, 3)?;
+            And {
+                lhs: lits.define(fields[0], &r)?,
+                rhs0: lits.check(fields[1], &r)?,
+                rhs1: lits.check(fields[2], &r)?,
+            }
+        };
+        aig.ands.push(and);
+    }
+
+    while !r.at_end() {
+        let line = r.line()?;
+        if line == b"c" {
+            let rest = String::from_utf8_lossy(r.data.get(r.pos..).unwrap_or_default());
+            aig.comments = rest.lines().map(str::to_string).collect();
+            break;
+        }
+        if line.is_empty() {
+            continue;
+        }
+        let text = String::from_utf8_lossy(line);
+        let (head, name) = text.split_once(' ').ok_or_else(|| r.invalid())?;
+        let idx: usize = head
+            .get(1..)
+            .and_then(|x| x.parse().ok())
+            .ok_or_else(|| r.invalid())?;
+        let slot = match head.get(..1) {
+            Some("i") => port_name(&mut aig.inputs, idx),
+            Some("l") => aig.latches.get_mut(idx).map(|l| &mut l.name),
+            Some("o") => port_name(&mut aig.outputs, idx),
+            Some("b") => port_name(&mut aig.bad, idx),
+            Some("c") => port_name(&mut aig.constraints, idx),
+            Some("j") => aig.justice.get_mut(idx).map(|j| &mut j.name),
+            Some("f") => port_name(&mut aig.fairness, idx),
+            _ => None,
+        };
+        *slot.ok_or_else(|| r.invalid())? = Some(name.to_string());
+    }
+
+    let used = aig
+        .latches
+        .iter()
+        .map(|l| l.next)
+        .chain(aig.outputs.iter().map(|p| p.lit))
+        .chain(aig.bad.iter().map(|p| p.lit))
+        .chain(aig.constraints.iter().map(|p| p.lit))
+        .chain(aig.justice.iter().flat_map(|j| j.lits.iter().copied()))
+        .chain(aig.fairness.iter().map(|p| p.lit))
+        .chain(aig.ands.iter().flat_map(|a| [a.rhs0, a.rhs1]));
+    for lit in used {
+        if lit >= 2 && !lits.defined[lit as usize / 2] {
+            return Err(AigerParseError::Undefined { lit });
+        }
+    }
+    Ok(aig)
+}
+
+/// Read an AIGER file with [`parse_aiger`] and convert it to a BLIF AST with
+/// a single model called `name`, see [`Aig::to_model`].
+///
+/// # Errors
+///
+/// See [`parse_aiger`].
+pub fn parse_aiger_to_ast(name: &str, source: &[u8]) -> Result<Blif, AigerParseError> {
+    let model = parse_aiger(source)?.to_model(name);
+    let mut blif = Blif::new();
+    blif.entries.push(BlifEntry::Model(model));
+    Ok(blif)
+}
@@ -163,6 +163,8 @@ impl From<ModelCmdKind> for ModelCmd {
 pub struct ModelAttr {
     /// from blif `.area` attribute
     pub area: Option<f64>,
+    /// from `.blackbox`: the model only declares its ports
+    pub blackbox: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
@@ -172,6 +174,19 @@ pub struct Model {
     pub attr: ModelAttr,
 }
 
+impl Model {
+    /// Fill in omitted `.inputs` and `.outputs`, see
+    /// [`netlist::model_ports`](crate::netlist::model_ports).
+    ///
+    /// Nets connected to `.subckt` pins are never inferred as ports; use
+    /// [`Blif::infer_ports`] to resolve the directions of those pins.
+    pub fn infer_ports(&mut self) {
+        let (inputs, outputs) = crate::netlist::model_ports(self, None);
+        self.meta.inputs = Some(inputs);
+        self.meta.outputs = Some(outputs);
+    }
+}
+
 impl CommandConsumer for Model {
     type Gate = Gate;
 
@@ -264,6 +279,10 @@ impl CommandConsumer for Model {
         self.attr.area = Some(area);
     }
 
+    fn blackbox(&mut self) {
+        self.attr.blackbox = true;
+    }
+
     fn set_cycle_time(&mut self, cycle_time: f32) {
         self.commands
             .push(ModelCmdKind::CycleTime(cycle_time).into())
@@ -352,10 +371,58 @@ pub enum BlifEntry {
     Model(Model),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Default)]
 pub struct Blif {
     pub entries: Vec<BlifEntry>,
     to_search: Vec<String>,
+    infer_ports: bool,
+}
+
+impl Blif {
+    /// An empty AST, to be filled by [`parse_blif`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Like [`new`](Self::new), but fill in omitted `.inputs` and `.outputs`
+    /// of every model when it is done parsing, with
+    /// [`Model::infer_ports`].
+    ///
+    /// Directions of `.subckt` pins are only known for models that were
+    /// parsed before; use [`infer_ports`](Self::infer_ports) after parsing to
+    /// take all models into account.
+    pub fn with_port_inference() -> Self {
+        Self {
+            infer_ports: true,
+            ..Self::default()
+        }
+    }
+
+    /// Fill in the omitted `.inputs` and `.outputs` of every model, taking
+    /// the directions of `.subckt` pins from the other models.
+    pub fn infer_ports(&mut self) {
+        let ports: Vec<_> = self
+            .models()
+            .map(|model| crate::netlist::model_ports(model, Some(self)))
+            .collect();
+        for (entry, (inputs, outputs)) in self.entries.iter_mut().zip(ports) {
+            let BlifEntry::Model(model) = entry;
+            model.meta.inputs = Some(inputs);
+            model.meta.outputs = Some(outputs);
+        }
+    }
+
+    /// All models, in source order.
+    pub fn models(&self) -> impl Iterator<Item = &Model> {
+        self.entries.iter().map(|entry| match entry {
+            BlifEntry::Model(model) => model,
+        })
+    }
+
+    /// The first model called `name`.
+    pub fn model(&self, name: &str) -> Option<&Model> {
+        self.models().find(|model| model.meta.name == name)
+    }
 }
 
 impl ModelConsumer for Blif {
@@ -369,7 +436,12 @@ impl ModelConsumer for Blif {
         }
     }
 
-    fn model_done(&mut self, model: Self::Inner) {
+    fn model_done(&mut self, mut model: Self::Inner) {
+        if self.infer_ports && (model.meta.inputs.is_none() || model.meta.outputs.is_none()) {
+            let (inputs, outputs) = crate::netlist::model_ports(&model, Some(self));
+            model.meta.inputs = Some(inputs);
+            model.meta.outputs = Some(outputs);
+        }
         self.entries.push(BlifEntry::Model(model));
     }
 
@@ -385,9 +457,13 @@ pub fn parse_many_blif_to_ast<E: std::fmt::Debug, L: IntoIterator<Item = impl As
     let mut blif = Blif {
         entries: vec![],
         to_search: vec![path.to_string()],
+        infer_ports: false,
     };
 
-    while let Some(p) = blif.to_search.pop() {
+    // files are parsed in the order in which they are referenced
+    let mut next = 0;
+    while let Some(p) = blif.to_search.get(next).cloned() {
+        next += 1;
         let filnam = Path::new(p.as_str())
             .file_name()
             .ok_or(FullBlifErr::FileNoName)?;
@@ -406,6 +482,7 @@ pub fn parse_blif_to_ast(
     let mut blif = Blif {
         entries: vec![],
         to_search: vec![],
+        infer_ports: false,
     };
 
     parse_blif(filename, &mut blif, lines).map_err(FullBlifErr::Blif)?;
@@ -420,3 +497,79 @@ pub fn parse_blif_to_ast(
 pub fn parse_str_blif_to_ast(filename: &str, source: &str) -> Result<Blif, FullBlifErr<()>> {
     parse_blif_to_ast(filename, source.split('\n'))
 }
+
+/// Parse a BLIF source into an AST, parsing independent models on multiple threads.
+///
+/// The source is split with [`scan_model_boundaries`], the chunks are
+/// parsed on a pool of scoped threads and the resulting models are
+/// reassembled in source order. The result is identical to the one of
+/// [`parse_str_blif_to_ast`]: if any chunk fails to parse, the whole source
+/// is re-parsed sequentially so that the reported error is exactly the one
+/// the sequential parser produces.
+pub fn parse_blif_parallel(filename: &str, source: &str) -> Result<Blif, FullBlifErr<()>> {
+    let lines: Vec<&str> = source.split('\n').collect();
+    let chunks = scan_chunks(&lines);
+
+    let threads = std::thread::available_parallelism()
+        .map_or(1, |x| x.get())
+        .min(chunks.len())
+        .max(1);
+    let next = std::sync::atomic::AtomicUsize::new(0);
+
+    let mut parsed: Vec<(usize, Result<Blif, BlifParserError>)> = std::thread::scope(|s| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut done = vec![];
+                    loop {
+                        let idx = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        let Some((range, first)) = chunks.get(idx) else {
+                            break;
+                        };
+                        let mut blif = Blif {
+                            entries: vec![],
+                            to_search: vec![],
+                            infer_ports: false,
+                        };
+                        let res = parse_blif_from(
+                            filename,
+                            &mut blif,
+                            lines[range.clone()].iter(),
+                            *first,
+                        );
+                        done.push((idx, res.map(|_| blif)));
+                    }
+                    done
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().expect("BLIF parser thread panicked"))
+            .collect()
+    });
+    parsed.sort_by_key(|(idx, _)| *idx);
+
+    let mut blif = Blif {
+        entries: vec![],
+        to_search: vec![],
+        infer_ports: false,
+    };
+
+    for (_, chunk) in parsed {
+        match chunk {
+            Ok(chunk) => {
+                blif.entries.extend(chunk.entries);
+                blif.to_search.extend(chunk.to_search);
+            }
+            Err(_) => return parse_str_blif_to_ast(filename, source),
+        }
+    }
+
+    if !blif.to_search.is_empty() {
+        Err(FullBlifErr::SearchPathsNotSupported)?;
+    }
+
+    Ok(blif)
+}
@@ -0,0 +1,559 @@
+//! Reading and writing the ISCAS BENCH format.
+//!
+//! BENCH is the format of the ISCAS'85, ISCAS'89 and ITC'99 benchmarks: a
+//! list of `INPUT(a)` and `OUTPUT(y)` declarations, and gates like
+//! `y = NAND(a, b)` or `q = DFF(d)`. [`parse_bench`] turns it into a model
+//! and [`write_bench`] turns a model back into it:
+//!
+//! ```rust
+//! use turbo_blif::bench::{BenchOptions, bench_to_string, parse_bench};
+//!
+//! let source = "INPUT(a)\nINPUT(b)\nOUTPUT(q)\nn = NAND(a, b)\nq = DFF(n)\n";
+//! let model = parse_bench("top", source).unwrap();
+//! assert_eq!(model.commands.len(), 2);
+//!
+//! assert_eq!(
+//!     bench_to_string(&model, &BenchOptions::default()).unwrap(),
+//!     "# top\nINPUT(a)\nINPUT(b)\n\nOUTPUT(q)\n\nn = NAND(a, b)\nq = DFF(n)\n"
+//! );
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use smallvec::SmallVec;
+
+use crate::ast::{Blif, BlifEntry, Gate, LUT, Model, ModelAttr, ModelCmdKind};
+use crate::lut::{MAX_INPUTS, TruthTable};
+use crate::netlist::model_ports;
+use crate::{FlipFlop, FlipFlopInit, GateMeta, ModelMeta, Str, Tristate};
+
+/// Why a BENCH file can not be read.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BenchParseError {
+    /// A line is neither a declaration nor a gate.
+    Invalid {
+        /// The line, starting at 1.
+        line: usize,
+    },
+    /// A gate type that is not supported.
+    UnknownGate {
+        /// The line, starting at 1.
+        line: usize,
+        /// The gate type.
+        gate: String,
+    },
+    /// A gate has the wrong number of inputs for its type.
+    Arity {
+        /// The line, starting at 1.
+        line: usize,
+        /// The gate type.
+        gate: String,
+    },
+}
+
+impl fmt::Display for BenchParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BenchParseError::Invalid { line } => write!(f, "line {line}: invalid BENCH syntax"),
+            BenchParseError::UnknownGate { line, gate } => {
+                write!(f, "line {line}: unknown gate type `{gate}`")
+            }
+            BenchParseError::Arity { line, gate } => {
+                write!(f, "line {line}: wrong number of inputs for `{gate}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BenchParseError {}
+
+/// The `.names` cover of a BENCH gate with `n` inputs, or `None` for unknown
+/// gate types.
+fn gate_cover(gate: &str, n: usize) -> Option<LUT> {
+    let all = |value| SmallVec::from_elem(value, n);
+    let one_hot = |value: bool| {
+        (0..n)
+            .map(|i| {
+                let mut row = all(Tristate::Ignored);
+                row[i] = if value {
+                    Tristate::True
+                } else {
+                    Tristate::False
+                };
+                row
+            })
+            .collect::<Vec<_>>()
+    };
+    let parity = |odd: bool| {
+        (0..1usize << n)
+            .filter(|m| (m.count_ones() % 2 == 1) == odd)
+            .map(|m| {
+                (0..n)
+                    .map(|i| {
+                        if m >> i & 1 == 1 {
+                            Tristate::True
+                        } else {
+                            Tristate::False
+                        }
+                    })
+                    .collect()
+            })
+            .collect::<Vec<_>>()
+    };
+    let with = |rows: Vec<SmallVec<[Tristate; 8]>>, out| {
+        LUT(rows.into_iter().map(|row| (row, Some(out))).collect())
+    };
+    Some(match gate {
+        "AND" => with(vec![all(Tristate::True)], true),
+        "NAND" => with(vec![all(Tristate::True)], false),
+        "OR" => with(one_hot(true), true),
+        "NOR" => with(one_hot(true), false),
+        "XOR" => with(parity(true), true),
+        "XNOR" => with(parity(false), true),
+        "NOT" => with(vec![all(Tristate::False)], true),
+        "BUF" | "BUFF" => with(vec![all(Tristate::True)], true),
+        _ => return None,
+    })
+}
+
+/// The name inside of `KEYWORD(name)`, if `line` is such a declaration.
+fn declaration<'a>(line: &'a str, keyword: &str) -> Option<&'a str> {
+    let head = line.get(..keyword.len())?;
+    let rest = line[keyword.len()..].trim_start();
+    if !head.eq_ignore_ascii_case(keyword) || !rest.starts_with('(') {
+        return None;
+    }
+    Some(rest.strip_prefix('(')?.strip_suffix(')')?.trim())
+}
+
+/// Read a BENCH file as a model called `name`.
+///
+/// `AND`, `NAND`, `OR`, `NOR`, `XOR`, `XNOR`, `NOT` and `BUF`/`BUFF` gates
+/// become `.names` with the matching cover, and `DFF` becomes a `.latch` with
+/// the initial value 0, like ABC reads it. Gate types are case-insensitive,
+/// and `#` starts a comment.
+///
+/// # Errors
+///
+/// Fails for malformed lines, unknown gate types, and gates with the wrong
+/// number of inputs. `XOR` and `XNOR` can have at most
+/// [`MAX_INPUTS`](crate::lut::MAX_INPUTS) inputs.
+pub fn parse_bench(name: &str, source: &str) -> Result<Model, BenchParseError> {
+    let mut model = Model {
+        meta: ModelMeta {
+            name: name.into(),
+            inputs: Some(vec![]),
+            outputs: Some(vec![]),
+            clocks: vec![],
+        },
+        commands: vec![],
+        attr: ModelAttr::default(),
+    };
+
+    for (idx, line) in source.lines().enumerate() {
+        let line_no = idx + 1;
+        let invalid = BenchParseError::Invalid { line: line_no };
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(net) = declaration(line, "INPUT") {
+            model.meta.inputs.as_mut().unwrap().push(net.into());
+            continue;
+        }
+        if let Some(net) = declaration(line, "OUTPUT") {
+            model.meta.outputs.as_mut().unwrap().push(net.into());
+            continue;
+        }
+
+        let (output, expr) = line.split_once('=').ok_or(invalid.clone())?;
+        let output = output.trim();
+        let (gate, args) = expr.split_once('(').ok_or(invalid.clone())?;
+        let gate = gate.trim().to_ascii_uppercase();
+        let args = args.trim_end().strip_suffix(')').ok_or(invalid.clone())?;
+        let inputs: Vec<Str<16>> = args.split(',').map(|x| x.trim().into()).collect();
+        if output.is_empty()
+            || output.contains(char::is_whitespace)
+            || inputs.iter().any(|x| x.is_empty())
+        {
+            return Err(invalid);
+        }
+
+        let arity = BenchParseError::Arity {
+            line: line_no,
+            gate: gate.clone(),
+        };
+        let kind = match gate.as_str() {
+            "DFF" => {
+                let [input] = &inputs[..] else {
+                    return Err(arity);
+                };
+                ModelCmdKind::FF(FlipFlop {
+                    ty: None,
+                    input: input.clone(),
+                    output: output.into(),
+                    clock: None,
+                    init: FlipFlopInit::Const(false),
+                })
+            }
+            "NOT" | "BUF" | "BUFF" if inputs.len() != 1 => return Err(arity),
+            "XOR" | "XNOR" if inputs.len() > MAX_INPUTS => return Err(arity),
+            _ => {
+                let lut = gate_cover(&gate, inputs.len()).ok_or(BenchParseError::UnknownGate {
+                    line: line_no,
+                    gate: gate.clone(),
+                })?;
+                ModelCmdKind::Gate(Gate {
+                    meta: GateMeta {
+                        inputs,
+                        output: output.into(),
+                        external_dc: false,
+                        syntax: Default::default(),
+                    },
+                    lut,
+                })
+            }
+        };
+        model.commands.push(kind.into());
+    }
+    Ok(model)
+}
+
+/// Read a BENCH file with [`parse_bench`] into a BLIF AST with a single
+/// model called `name`.
+///
+/// # Errors
+///
+/// See [`parse_bench`].
+pub fn parse_bench_to_ast(name: &str, source: &str) -> Result<Blif, BenchParseError> {
+    let mut blif = Blif::new();
+    blif.entries
+        .push(BlifEntry::Model(parse_bench(name, source)?));
+    Ok(blif)
+}
+
+/// Options of [`write_bench`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BenchOptions {
+    /// Write `.names` whose function is not a BENCH gate as a sum of products
+    /// of `NOT`, `AND` and `OR` gates, instead of failing.
+    pub decompose: bool,
+}
+
+/// Why a model can not be written as BENCH.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BenchError {
+    /// The function of a `.names` is not a BENCH gate, and
+    /// [`BenchOptions::decompose`] is off.
+    Function {
+        /// The output of the gate.
+        net: String,
+    },
+    /// A constant can only be decomposed if the model has an input.
+    Constant {
+        /// The output of the gate.
+        net: String,
+    },
+    /// A command that has no BENCH equivalent.
+    Unsupported {
+        /// The command, like `.gate` or `.subckt`.
+        command: &'static str,
+        /// Its cell, model or output.
+        name: String,
+    },
+    /// The underlying writer failed.
+    Fmt,
+}
+
+impl fmt::Display for BenchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BenchError::Function { net } => write!(
+                f,
+                "the function of `{net}` is not a BENCH gate and decomposition is disabled"
+            ),
+            BenchError::Constant { net } => write!(
+                f,
+                "the constant `{net}` can not be written, because the model has no inputs"
+            ),
+            BenchError::Unsupported { command, name } => {
+                write!(f, "`{command} {name}` can not be written as BENCH")
+            }
+            BenchError::Fmt => write!(f, "formatter error"),
+        }
+    }
+}
+
+impl std::error::Error for BenchError {}
+
+impl From<fmt::Error> for BenchError {
+    fn from(_: fmt::Error) -> Self {
+        BenchError::Fmt
+    }
+}
+
+/// The BENCH gate that computes the function of `gate`, and the inputs that
+/// the function depends on.
+fn recognize(gate: &Gate) -> Option<(&'static str, Vec<&Str<16>>)> {
+    let n = gate.meta.inputs.len();
+    let tt = gate.lut.truth_table(n).ok()?;
+    let support: Vec<usize> = (0..n).filter(|&v| tt.depends_on(v)).collect();
+    let k = support.len();
+    if k == 0 {
+        return None;
+    }
+    let tt = TruthTable::from_fn(k, |m| {
+        let full = support
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (j, &v)| acc | (m >> j & 1) << v);
+        tt.get(full)
+    });
+    let all = tt.assignments() - 1;
+    let ones = tt.count_ones();
+    let parity = |m: usize| m.count_ones() % 2 == 1;
+    let name = if k == 1 {
+        if tt.get(1) { "BUFF" } else { "NOT" }
+    } else if ones == 1 && tt.get(all) {
+        "AND"
+    } else if ones == all && !tt.get(all) {
+        "NAND"
+    } else if ones == all && !tt.get(0) {
+        "OR"
+    } else if ones == 1 && tt.get(0) {
+        "NOR"
+    } else if (0..=all).all(|m| tt.get(m) == parity(m)) {
+        "XOR"
+    } else if (0..=all).all(|m| tt.get(m) != parity(m)) {
+        "XNOR"
+    } else {
+        return None;
+    };
+    Some((
+        name,
+        support.iter().map(|&v| &gate.meta.inputs[v]).collect(),
+    ))
+}
+
+fn strs(names: &[String]) -> Vec<&str> {
+    names.iter().map(String::as_str).collect()
+}
+
+fn write_gate<W: fmt::Write>(w: &mut W, output: &str, gate: &str, inputs: &[&str]) -> fmt::Result {
+    writeln!(w, "{output} = {gate}({})", inputs.join(", "))
+}
+
+/// Writes `.names` that are not BENCH gates as `NOT`, `AND` and `OR` gates.
+struct Decomposer<'a> {
+    used: HashSet<String>,
+    /// The inverted nets that were written already.
+    nots: HashMap<String, String>,
+    /// A net to build constants from.
+    first_input: Option<&'a str>,
+}
+
+impl Decomposer<'_> {
+    /// `name`, or `name` with a suffix if it is taken.
+    fn unique(&mut self, name: &str) -> String {
+        let mut candidate = name.to_string();
+        let mut n = 0;
+        while !self.used.insert(candidate.clone()) {
+            n += 1;
+            candidate = format!("{name}_{n}");
+        }
+        candidate
+    }
+
+    /// The inverse of `net`, written as a `NOT` gate the first time.
+    fn not<W: fmt::Write>(&mut self, net: &str, w: &mut W) -> Result<String, fmt::Error> {
+        if let Some(inv) = self.nots.get(net) {
+            return Ok(inv.clone());
+        }
+        let inv = self.unique(&format!("{net}_not"));
+        write_gate(w, &inv, "NOT", &[net])?;
+        self.nots.insert(net.to_string(), inv.clone());
+        Ok(inv)
+    }
+
+    fn constant<W: fmt::Write>(
+        &mut self,
+        output: &str,
+        value: bool,
+        w: &mut W,
+    ) -> Result<(), BenchError> {
+        let input = self.first_input.ok_or_else(|| BenchError::Constant {
+            net: output.to_string(),
+        })?;
+        let inv = self.not(input, w)?;
+        let gate = if value { "OR" } else { "AND" };
+        write_gate(w, output, gate, &[input, &inv])?;
+        Ok(())
+    }
+
+    fn decompose<W: fmt::Write>(&mut self, gate: &Gate, w: &mut W) -> Result<(), BenchError> {
+        let output = gate.meta.output.as_str();
+        if let Some(value) = gate.lut.is_constant() {
+            return self.constant(output, value, w);
+        }
+        // like `LUT::eval`: rows with the output 0 describe the off-set
+        let off = gate.lut.0.iter().any(|(_, out)| *out == Some(false));
+        let rows: Vec<_> = gate
+            .lut
+            .0
+            .iter()
+            .filter(|(_, out)| *out == Some(!off))
+            .map(|(row, _)| row)
+            .collect();
+        if rows.is_empty() {
+            return self.constant(output, false, w);
+        }
+
+        let mut products: Vec<Vec<String>> = vec![];
+        for row in &rows {
+            let mut literals = vec![];
+            for (value, input) in row.iter().zip(&gate.meta.inputs) {
+                match value {
+                    Tristate::True => literals.push(input.to_string()),
+                    Tristate::False => literals.push(self.not(input, w)?),
+                    Tristate::Ignored => {}
+                }
+            }
+            if literals.is_empty() {
+                // a row without literals covers everything
+                return self.constant(output, !off, w);
+            }
+            products.push(literals);
+        }
+
+        if let [product] = &products[..] {
+            let gate = match (product.len(), off) {
+                (1, false) => "BUFF",
+                (1, true) => "NOT",
+                (_, false) => "AND",
+                (_, true) => "NAND",
+            };
+            write_gate(w, output, gate, &strs(product))?;
+            return Ok(());
+        }
+        let mut terms = vec![];
+        for (k, product) in products.iter().enumerate() {
+            if let [literal] = &product[..] {
+                terms.push(literal.clone());
+                continue;
+            }
+            let term = self.unique(&format!("{output}_p{k}"));
+            write_gate(w, &term, "AND", &strs(product))?;
+            terms.push(term);
+        }
+        write_gate(w, output, if off { "NOR" } else { "OR" }, &strs(&terms))?;
+        Ok(())
+    }
+}
+
+/// Write `model` in the BENCH format.
+///
+/// `.names` whose function is a BENCH gate of the inputs it depends on are
+/// written as that gate, `.latch` as `DFF`, and `.conn` as `BUFF`. Other
+/// `.names` fail, unless [`BenchOptions::decompose`] is on. The clock, type
+/// and initial value of latches are lost, and `.exdc` gates and timing
+/// constraints are left out.
+///
+/// # Errors
+///
+/// Fails for `.names` that are not BENCH gates, for `.gate`, `.mlatch`,
+/// `.subckt` and FSMs, and if the underlying writer fails.
+pub fn write_bench<W: fmt::Write>(
+    model: &Model,
+    w: &mut W,
+    opts: &BenchOptions,
+) -> Result<(), BenchError> {
+    let (inputs, outputs) = model_ports(model, None);
+    writeln!(w, "# {}", model.meta.name)?;
+    for input in &inputs {
+        writeln!(w, "INPUT({input})")?;
+    }
+    writeln!(w)?;
+    for output in &outputs {
+        writeln!(w, "OUTPUT({output})")?;
+    }
+    writeln!(w)?;
+
+    let mut used: HashSet<String> = inputs
+        .iter()
+        .chain(&outputs)
+        .map(|x| x.to_string())
+        .collect();
+    for cmd in &model.commands {
+        match &cmd.kind {
+            ModelCmdKind::Gate(gate) => {
+                used.extend(gate.meta.inputs.iter().map(|x| x.to_string()));
+                used.insert(gate.meta.output.to_string());
+            }
+            ModelCmdKind::FF(ff) => used.extend([ff.input.to_string(), ff.output.to_string()]),
+            ModelCmdKind::Connect { from, to } => used.extend([from.to_string(), to.to_string()]),
+            _ => {}
+        }
+    }
+    let mut decomposer = Decomposer {
+        used,
+        nots: HashMap::new(),
+        first_input: inputs.first().map(|x| x.as_str()),
+    };
+
+    for cmd in &model.commands {
+        match &cmd.kind {
+            ModelCmdKind::Gate(gate) if !gate.meta.external_dc => match recognize(gate) {
+                Some((name, inputs)) => {
+                    let inputs: Vec<&str> = inputs.iter().map(|x| x.as_str()).collect();
+                    write_gate(w, &gate.meta.output, name, &inputs)?;
+                }
+                None if opts.decompose => decomposer.decompose(gate, w)?,
+                None => {
+                    return Err(BenchError::Function {
+                        net: gate.meta.output.to_string(),
+                    });
+                }
+            },
+            ModelCmdKind::FF(ff) => write_gate(w, &ff.output, "DFF", &[&ff.input])?,
+            ModelCmdKind::Connect { from, to } => write_gate(w, to, "BUFF", &[from])?,
+            ModelCmdKind::LibGate(lg) => {
+                return Err(BenchError::Unsupported {
+                    command: ".gate",
+                    name: lg.name.to_string(),
+                });
+            }
+            ModelCmdKind::LibFF(lf) => {
+                return Err(BenchError::Unsupported {
+                    command: ".mlatch",
+                    name: lf.name.to_string(),
+                });
+            }
+            ModelCmdKind::SubModel { name, .. } => {
+                return Err(BenchError::Unsupported {
+                    command: ".subckt",
+                    name: name.to_string(),
+                });
+            }
+            ModelCmdKind::FSM(_) => {
+                return Err(BenchError::Unsupported {
+                    command: ".start_kiss",
+                    name: model.meta.name.to_string(),
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Convenience wrapper: write a model as BENCH into a `String`.
+///
+/// # Errors
+///
+/// See [`write_bench`].
+pub fn bench_to_string(model: &Model, opts: &BenchOptions) -> Result<String, BenchError> {
+    let mut out = String::new();
+    write_bench(model, &mut out, opts)?;
+    Ok(out)
+}
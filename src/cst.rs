@@ -0,0 +1,237 @@
+//! Lossless concrete syntax tree (CST) of a BLIF file.
+//!
+//! Unlike the [`ast`](crate::ast), the CST keeps every comment, blank line,
+//! indentation, line continuation and the original spelling of each keyword
+//! (`.barbuf` vs `.conn`, `.subcircuit` vs `.subckt`, ...). Printing a
+//! [`Cst`] reproduces its input byte for byte:
+//!
+//! ```rust
+//! use turbo_blif::cst::Cst;
+//!
+//! let source = "# vendor netlist\n.model top  # top level\n.inputs a \\\n  b\n.outputs c\n.names a b c\n11 1\n.end\n";
+//! let mut cst = Cst::parse(source);
+//! assert_eq!(cst.to_string(), source);
+//!
+//! let stmt = cst.stmts.iter_mut().find(|s| s.keyword() == Some(".names")).unwrap();
+//! stmt.set_text(".names b a c");
+//! assert_eq!(cst.to_string(), source.replace(".names a b c", ".names b a c"));
+//! ```
+//!
+//! The CST is purely lexical: it splits the file into logical statements the
+//! same way the parser does, but does not interpret them. Use
+//! [`Cst::to_ast`] to get the AST of the (possibly edited) file.
+
+use std::fmt;
+
+use crate::ast::{Blif, FullBlifErr, parse_str_blif_to_ast};
+use crate::{before_cmt, tokenize};
+
+/// One logical statement: a command or a row of a table, including all of
+/// its continuation lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stmt {
+    /// Blank lines, comment lines and indentation before the statement,
+    /// verbatim.
+    pub leading: String,
+    /// The statement as written, from its first non-whitespace character to
+    /// the last one before the trailing comment of its last line.
+    ///
+    /// Continuation lines, including their `\`, line terminators and
+    /// comments, are part of the text.
+    text: String,
+    /// Whitespace, comment and line terminator after the statement, verbatim.
+    pub trailing: String,
+}
+
+impl Stmt {
+    /// Create a new statement on its own line, without any trivia.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            leading: String::new(),
+            text: text.into(),
+            trailing: "\n".to_string(),
+        }
+    }
+
+    /// The statement as written in the source.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replace the statement, keeping its leading and trailing trivia.
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+    }
+
+    /// The statement as the parser sees it: continuation lines joined and
+    /// comments removed.
+    pub fn logical(&self) -> String {
+        let mut out = String::new();
+        let mut lines = self.text.split('\n').peekable();
+        while let Some(line) = lines.next() {
+            let line = before_cmt(line).trim_end();
+            if lines.peek().is_some() {
+                out.push_str(line.trim_end_matches('\\'));
+            } else {
+                out.push_str(line);
+            }
+        }
+        out
+    }
+
+    /// The tokens of the statement, keyword included.
+    pub fn tokens(&self) -> Vec<String> {
+        tokenize(&self.logical())
+            .into_iter()
+            .map(|x| x.to_string())
+            .collect()
+    }
+
+    /// The keyword of the statement in its original spelling, like
+    /// `.barbuf` or `.subcircuit`.
+    ///
+    /// `None` for table rows and FSM transitions.
+    pub fn keyword(&self) -> Option<&str> {
+        let kw = self.text.split([' ', '\t', '\\', '\n']).next()?;
+        kw.starts_with('.').then_some(kw)
+    }
+
+    /// The keyword, with aliases mapped to the spelling that the AST writer
+    /// uses for the same construct.
+    pub fn canonical_keyword(&self) -> Option<&str> {
+        Some(match self.keyword()? {
+            ".input" => ".inputs",
+            ".output" => ".outputs",
+            ".cover" | ".table" => ".names",
+            ".subcircuit" => ".subckt",
+            ".barbuff" | ".barbuf" | ".short" => ".conn",
+            kw => kw,
+        })
+    }
+}
+
+impl fmt::Display for Stmt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.leading, self.text, self.trailing)
+    }
+}
+
+/// A model inside of a [`Cst`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CstModel {
+    /// Name from the `.model` statement; `None` for a model without one.
+    pub name: Option<String>,
+    /// Indices into [`Cst::stmts`], from the `.model` statement up to and
+    /// including `.end`, if present.
+    pub stmts: std::ops::Range<usize>,
+}
+
+/// Lossless concrete syntax tree of a BLIF file.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Cst {
+    /// All statements, in source order.
+    pub stmts: Vec<Stmt>,
+    /// Blank lines and comments after the last statement, verbatim.
+    pub trailing: String,
+}
+
+impl Cst {
+    /// Split a BLIF source into statements. This never fails: any text is
+    /// accepted, and errors are only reported by [`to_ast`](Self::to_ast).
+    pub fn parse(source: &str) -> Self {
+        let mut cst = Self::default();
+        let mut lines = source.split_inclusive('\n').peekable();
+
+        while let Some(line) = lines.next() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                cst.trailing.push_str(line);
+                continue;
+            }
+
+            let mut leading = std::mem::take(&mut cst.trailing);
+            let indent = line.len() - line.trim_start().len();
+            leading.push_str(&line[..indent]);
+
+            let mut text = String::new();
+            let mut line = &line[indent..];
+            while before_cmt(line).trim_end().ends_with('\\') {
+                let Some(next) = lines.next() else {
+                    break;
+                };
+                text.push_str(line);
+                line = next;
+            }
+
+            let end = before_cmt(line).trim_end().len();
+            text.push_str(&line[..end]);
+
+            cst.stmts.push(Stmt {
+                leading,
+                text,
+                trailing: line[end..].to_string(),
+            });
+        }
+
+        cst
+    }
+
+    /// Parse the source that this CST prints to.
+    pub fn to_ast(&self, filename: &str) -> Result<Blif, FullBlifErr<()>> {
+        parse_str_blif_to_ast(filename, &self.to_string())
+    }
+
+    /// The models of the file.
+    ///
+    /// A model starts with a `.model` statement, or with the first statement
+    /// of the file if that is not `.model` or `.search`, and ends with `.end`.
+    pub fn models(&self) -> Vec<CstModel> {
+        let mut out = vec![];
+        let mut current: Option<CstModel> = None;
+
+        for (idx, stmt) in self.stmts.iter().enumerate() {
+            match stmt.keyword() {
+                Some(".model") => {
+                    if let Some(mut model) = current.take() {
+                        model.stmts.end = idx;
+                        out.push(model);
+                    }
+                    current = Some(CstModel {
+                        name: stmt.tokens().get(1).cloned(),
+                        stmts: idx..idx,
+                    });
+                }
+                Some(".end") => {
+                    if let Some(mut model) = current.take() {
+                        model.stmts.end = idx + 1;
+                        out.push(model);
+                    }
+                }
+                Some(".search") if current.is_none() => {}
+                _ if current.is_none() && out.is_empty() => {
+                    current = Some(CstModel {
+                        name: None,
+                        stmts: idx..idx,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(mut model) = current {
+            model.stmts.end = self.stmts.len();
+            out.push(model);
+        }
+
+        out
+    }
+}
+
+impl fmt::Display for Cst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for stmt in &self.stmts {
+            write!(f, "{stmt}")?;
+        }
+        f.write_str(&self.trailing)
+    }
+}
@@ -0,0 +1,532 @@
+//! Two-level logic on cubes and covers.
+//!
+//! The rows of a `.names` block are cubes, and a set of rows is a cover. A
+//! [`Cube`] uses the positional-cube notation: every input has two bits, one
+//! for "may be 0" and one for "may be 1", packed 32 inputs per word. This
+//! makes the operations on cubes a few word operations, so covers with
+//! thousands of cubes are cheap:
+//!
+//! ```rust
+//! use turbo_blif::cube::{Cover, Cube};
+//!
+//! let a = Cube::parse("1-").unwrap();
+//! let b = Cube::parse("-1").unwrap();
+//! assert_eq!(a.intersect(&b), Cube::parse("11"));
+//! assert_eq!(a.distance(&Cube::parse("0-").unwrap()), 1);
+//!
+//! let f = Cover::from_cubes(2, [a, b]);
+//! assert_eq!(f.complement(), Cover::from_cubes(2, [Cube::parse("00").unwrap()]));
+//! assert!(f.union(&f.complement()).is_tautology());
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use smallvec::SmallVec;
+
+use crate::Tristate;
+use crate::ast::LUT;
+
+/// Inputs per word.
+const VARS_PER_WORD: usize = 32;
+/// The "may be 0" bits of all inputs of a word.
+const LOW: u64 = 0x5555_5555_5555_5555;
+
+/// A product term over a fixed number of inputs.
+///
+/// Every input is `0`, `1` or `-` (don't care). A cube where some input can
+/// be neither is empty; operations that could produce one return `None`
+/// instead.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Cube {
+    vars: usize,
+    /// Two bits per input: bit 0 is "may be 0", bit 1 "may be 1". The bits
+    /// of unused inputs of the last word are zero.
+    words: SmallVec<[u64; 2]>,
+}
+
+/// The bits of the used inputs in word `idx` of cubes with `vars` inputs.
+fn used_mask(vars: usize, idx: usize) -> u64 {
+    let used = vars - idx * VARS_PER_WORD;
+    if used >= VARS_PER_WORD {
+        u64::MAX
+    } else {
+        (1u64 << (2 * used)) - 1
+    }
+}
+
+impl Cube {
+    /// The cube that contains everything: all inputs are don't care.
+    pub fn universe(vars: usize) -> Self {
+        let words = (0..vars.div_ceil(VARS_PER_WORD))
+            .map(|idx| used_mask(vars, idx))
+            .collect();
+        Self { vars, words }
+    }
+
+    /// The cube of a truth-table row.
+    pub fn from_row(row: &[Tristate]) -> Self {
+        let mut cube = Self::universe(row.len());
+        for (var, value) in row.iter().enumerate() {
+            cube.set(var, value.clone());
+        }
+        cube
+    }
+
+    /// Parse a row in the BLIF notation, like `"1-0"`.
+    pub fn parse(row: &str) -> Option<Self> {
+        let row: Option<Vec<Tristate>> = row.chars().map(|c| c.to_string().parse().ok()).collect();
+        Some(Self::from_row(&row?))
+    }
+
+    /// The truth-table row of the cube.
+    pub fn to_row(&self) -> SmallVec<[Tristate; 8]> {
+        (0..self.vars).map(|var| self.get(var)).collect()
+    }
+
+    /// Number of inputs.
+    pub fn vars(&self) -> usize {
+        self.vars
+    }
+
+    fn bits(&self, var: usize) -> u64 {
+        self.words[var / VARS_PER_WORD] >> (2 * (var % VARS_PER_WORD)) & 0b11
+    }
+
+    /// The value of input `var`.
+    pub fn get(&self, var: usize) -> Tristate {
+        match self.bits(var) {
+            0b01 => Tristate::False,
+            0b10 => Tristate::True,
+            _ => Tristate::Ignored,
+        }
+    }
+
+    /// Set the value of input `var`.
+    pub fn set(&mut self, var: usize, value: Tristate) {
+        let shift = 2 * (var % VARS_PER_WORD);
+        let bits = match value {
+            Tristate::False => 0b01,
+            Tristate::True => 0b10,
+            Tristate::Ignored => 0b11,
+        };
+        let word = &mut self.words[var / VARS_PER_WORD];
+        *word = *word & !(0b11 << shift) | bits << shift;
+    }
+
+    /// Number of inputs that are not don't care.
+    pub fn literals(&self) -> usize {
+        self.words
+            .iter()
+            .map(|w| ((w ^ w >> 1) & LOW).count_ones() as usize)
+            .sum()
+    }
+
+    /// Whether every input is don't care.
+    pub fn is_universe(&self) -> bool {
+        self.literals() == 0
+    }
+
+    /// Number of inputs for which the two cubes have no common value.
+    pub fn distance(&self, other: &Cube) -> usize {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .enumerate()
+            .map(|(idx, (a, b))| {
+                let both = a & b;
+                let empty = !(both | both >> 1) & LOW & used_mask(self.vars, idx);
+                empty.count_ones() as usize
+            })
+            .sum()
+    }
+
+    /// The common part of both cubes, or `None` if they don't overlap.
+    pub fn intersect(&self, other: &Cube) -> Option<Cube> {
+        if self.distance(other) > 0 {
+            return None;
+        }
+        Some(self.zip_with(other, |a, b| a & b))
+    }
+
+    /// Whether `other` is a subset of this cube.
+    pub fn contains(&self, other: &Cube) -> bool {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .all(|(a, b)| b & !a == 0)
+    }
+
+    /// The smallest cube that contains both cubes.
+    pub fn supercube(&self, other: &Cube) -> Cube {
+        self.zip_with(other, |a, b| a | b)
+    }
+
+    /// The cofactor of the cube with respect to `other`: the cube restricted
+    /// to `other`, with the inputs fixed by `other` made don't care. `None`
+    /// if the cubes don't overlap.
+    pub fn cofactor(&self, other: &Cube) -> Option<Cube> {
+        if self.distance(other) > 0 {
+            return None;
+        }
+        let mut out = self.zip_with(other, |a, b| a | !b);
+        out.mask();
+        Some(out)
+    }
+
+    /// The cofactor with respect to the literal `var = value`.
+    fn cofactor_literal(&self, var: usize, value: bool) -> Option<Cube> {
+        let bits = self.bits(var);
+        if bits & (1 << value as u64) == 0 {
+            return None;
+        }
+        let mut out = self.clone();
+        out.set(var, Tristate::Ignored);
+        Some(out)
+    }
+
+    fn zip_with(&self, other: &Cube, f: impl Fn(u64, u64) -> u64) -> Cube {
+        assert_eq!(self.vars, other.vars, "cubes over different inputs");
+        Cube {
+            vars: self.vars,
+            words: self
+                .words
+                .iter()
+                .zip(&other.words)
+                .map(|(a, b)| f(*a, *b))
+                .collect(),
+        }
+    }
+
+    fn mask(&mut self) {
+        for (idx, word) in self.words.iter_mut().enumerate() {
+            *word &= used_mask(self.vars, idx);
+        }
+    }
+}
+
+impl fmt::Display for Cube {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for var in 0..self.vars {
+            write!(f, "{}", self.get(var))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Cube {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.to_string())
+    }
+}
+
+/// A sum of cubes over a fixed number of inputs.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cover {
+    vars: usize,
+    cubes: Vec<Cube>,
+}
+
+impl Cover {
+    /// The empty cover, which is constant 0.
+    pub fn new(vars: usize) -> Self {
+        Self {
+            vars,
+            cubes: vec![],
+        }
+    }
+
+    /// The cover of the given cubes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a cube does not have `vars` inputs.
+    pub fn from_cubes(vars: usize, cubes: impl IntoIterator<Item = Cube>) -> Self {
+        let mut cover = Self::new(vars);
+        for cube in cubes {
+            cover.push(cube);
+        }
+        cover
+    }
+
+    /// The on-set of the LUT of a gate with `inputs` inputs. If the rows of
+    /// the LUT describe the off-set, it is complemented, see
+    /// [`LUT::eval`].
+    ///
+    /// Rows whose width is not `inputs` are ignored.
+    pub fn on_set(lut: &LUT, inputs: usize) -> Self {
+        let off = lut.0.iter().any(|(_, out)| *out == Some(false));
+        let rows = lut
+            .0
+            .iter()
+            .filter(|(row, out)| *out == Some(!off) && row.len() == inputs)
+            .map(|(row, _)| Cube::from_row(row));
+        let cover = Self::from_cubes(inputs, rows);
+        if off { cover.complement() } else { cover }
+    }
+
+    /// The cover as LUT rows with output `1`.
+    pub fn to_lut(&self) -> LUT {
+        LUT(self
+            .cubes
+            .iter()
+            .map(|cube| (cube.to_row(), Some(true)))
+            .collect())
+    }
+
+    /// Add a cube.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cube does not have [`vars`](Self::vars) inputs.
+    pub fn push(&mut self, cube: Cube) {
+        assert_eq!(cube.vars, self.vars, "cube over different inputs");
+        self.cubes.push(cube);
+    }
+
+    /// Number of inputs.
+    pub fn vars(&self) -> usize {
+        self.vars
+    }
+
+    /// The cubes.
+    pub fn cubes(&self) -> &[Cube] {
+        &self.cubes
+    }
+
+    /// Number of cubes.
+    pub fn len(&self) -> usize {
+        self.cubes.len()
+    }
+
+    /// Whether there are no cubes, i.e. the cover is constant 0.
+    pub fn is_empty(&self) -> bool {
+        self.cubes.is_empty()
+    }
+
+    /// Whether the cover contains the assignment, with input `i` being
+    /// `inputs[i]`.
+    pub fn eval(&self, inputs: &[bool]) -> bool {
+        let mut minterm = Cube::universe(self.vars);
+        for (var, x) in inputs.iter().enumerate().take(self.vars) {
+            minterm.set(var, if *x { Tristate::True } else { Tristate::False });
+        }
+        self.cubes.iter().any(|c| c.contains(&minterm))
+    }
+
+    /// The cubes of both covers.
+    pub fn union(&self, other: &Cover) -> Cover {
+        let mut out = self.clone();
+        for cube in &other.cubes {
+            out.push(cube.clone());
+        }
+        out
+    }
+
+    /// The pairwise intersections of the cubes of both covers.
+    pub fn intersect(&self, other: &Cover) -> Cover {
+        let mut out = Cover::new(self.vars);
+        for a in &self.cubes {
+            for b in &other.cubes {
+                if let Some(c) = a.intersect(b) {
+                    out.push(c);
+                }
+            }
+        }
+        out
+    }
+
+    /// The cofactor of every cube with respect to `cube`.
+    pub fn cofactor(&self, cube: &Cube) -> Cover {
+        Cover {
+            vars: self.vars,
+            cubes: self.cubes.iter().filter_map(|c| c.cofactor(cube)).collect(),
+        }
+    }
+
+    fn cofactor_literal(&self, var: usize, value: bool) -> Cover {
+        Cover {
+            vars: self.vars,
+            cubes: self
+                .cubes
+                .iter()
+                .filter_map(|c| c.cofactor_literal(var, value))
+                .collect(),
+        }
+    }
+
+    /// The input to split on in recursive algorithms: the one with the most
+    /// literals among the inputs that appear in both polarities, or `None`
+    /// if the cover is unate.
+    fn binate_var(&self) -> Option<usize> {
+        let mut best = None;
+        let mut best_count = 0;
+        for var in 0..self.vars {
+            let (mut zeros, mut ones) = (0, 0);
+            for cube in &self.cubes {
+                match cube.get(var) {
+                    Tristate::False => zeros += 1,
+                    Tristate::True => ones += 1,
+                    Tristate::Ignored => {}
+                }
+            }
+            if zeros > 0 && ones > 0 && zeros + ones > best_count {
+                best = Some(var);
+                best_count = zeros + ones;
+            }
+        }
+        best
+    }
+
+    /// Whether the cover is constant 1.
+    pub fn is_tautology(&self) -> bool {
+        if self.cubes.iter().any(Cube::is_universe) {
+            return true;
+        }
+        // a unate cover is a tautology only if it has the universe cube
+        let Some(var) = self.binate_var() else {
+            return false;
+        };
+        self.cofactor_literal(var, false).is_tautology()
+            && self.cofactor_literal(var, true).is_tautology()
+    }
+
+    /// Whether every assignment of `cube` is in the cover.
+    pub fn contains_cube(&self, cube: &Cube) -> bool {
+        self.cofactor(cube).is_tautology()
+    }
+
+    /// Whether every assignment of `other` is in this cover.
+    pub fn contains(&self, other: &Cover) -> bool {
+        other.cubes.iter().all(|c| self.contains_cube(c))
+    }
+
+    /// Whether both covers describe the same function.
+    pub fn equivalent(&self, other: &Cover) -> bool {
+        self.contains(other) && other.contains(self)
+    }
+
+    /// Remove cubes that are contained in a single other cube, and
+    /// duplicates.
+    pub fn remove_contained(&mut self) {
+        // bigger cubes first, so that they are the ones that are kept
+        self.cubes.sort_by_key(Cube::literals);
+        let mut kept: Vec<Cube> = Vec::with_capacity(self.cubes.len());
+        for cube in self.cubes.drain(..) {
+            if !kept.iter().any(|k| k.contains(&cube)) {
+                kept.push(cube);
+            }
+        }
+        self.cubes = kept;
+    }
+
+    /// A cover of all assignments that are not in this cover.
+    pub fn complement(&self) -> Cover {
+        let mut out = self.complement_rec();
+        out.remove_contained();
+        out
+    }
+
+    fn complement_rec(&self) -> Cover {
+        if self.cubes.is_empty() {
+            return Cover::from_cubes(self.vars, [Cube::universe(self.vars)]);
+        }
+        if self.cubes.iter().any(Cube::is_universe) {
+            return Cover::new(self.vars);
+        }
+        if let [cube] = &self.cubes[..] {
+            // De Morgan
+            let mut out = Cover::new(self.vars);
+            for var in 0..self.vars {
+                let value = match cube.get(var) {
+                    Tristate::False => Tristate::True,
+                    Tristate::True => Tristate::False,
+                    Tristate::Ignored => continue,
+                };
+                let mut c = Cube::universe(self.vars);
+                c.set(var, value);
+                out.push(c);
+            }
+            return out;
+        }
+
+        let var = self.binate_var().unwrap_or_else(|| {
+            // unate: split on the input with the most literals
+            (0..self.vars)
+                .max_by_key(|&v| {
+                    self.cubes
+                        .iter()
+                        .filter(|c| c.get(v) != Tristate::Ignored)
+                        .count()
+                })
+                .unwrap()
+        });
+
+        let mut out = Cover::new(self.vars);
+        for value in [false, true] {
+            let literal = if value {
+                Tristate::True
+            } else {
+                Tristate::False
+            };
+            for mut cube in self.cofactor_literal(var, value).complement_rec().cubes {
+                cube.set(var, literal.clone());
+                out.push(cube);
+            }
+        }
+
+        // merge cubes that only differ in `var`
+        let mut merged: Vec<Cube> = Vec::with_capacity(out.cubes.len());
+        let mut by_rest: HashMap<Cube, usize> = HashMap::new();
+        for cube in out.cubes {
+            let mut rest = cube.clone();
+            rest.set(var, Tristate::Ignored);
+            match by_rest.get(&rest) {
+                Some(&idx) if merged[idx].get(var) != cube.get(var) => merged[idx] = rest,
+                _ => {
+                    by_rest.insert(rest, merged.len());
+                    merged.push(cube);
+                }
+            }
+        }
+        out.cubes = merged;
+        out
+    }
+}
+
+/// The on-set of a `.names` over the covers of its inputs. `complement`
+/// returns the complement of input `i`.
+pub(crate) fn compose(
+    lut: &LUT,
+    vars: usize,
+    inputs: &[&Cover],
+    complement: &mut dyn FnMut(usize) -> Cover,
+) -> Cover {
+    // like `LUT::eval`: rows with the output 0 describe the off-set
+    let off = lut.0.iter().any(|(_, out)| *out == Some(false));
+    let mut sum = Cover::new(vars);
+    for (row, _) in lut.0.iter().filter(|(_, out)| *out == Some(!off)) {
+        let mut product = Cover::from_cubes(vars, [Cube::universe(vars)]);
+        for (i, value) in row.iter().enumerate() {
+            let literal = match value {
+                Tristate::True => inputs[i].clone(),
+                Tristate::False => complement(i),
+                Tristate::Ignored => continue,
+            };
+            product = product.intersect(&literal);
+            product.remove_contained();
+        }
+        sum = sum.union(&product);
+    }
+    sum.remove_contained();
+    if off { sum.complement() } else { sum }
+}
+
+impl fmt::Display for Cover {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for cube in &self.cubes {
+            writeln!(f, "{cube}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,359 @@
+//! Inlining of the model hierarchy into a single model.
+//!
+//! [`flatten`] replaces every `.subckt` of a model by the commands of the
+//! model it instantiates, recursively. Nets of an inlined model that are not
+//! ports get the instance path as prefix:
+//!
+//! ```rust
+//! use turbo_blif::ast::{ModelCmdKind, parse_str_blif_to_ast};
+//! use turbo_blif::flatten::flatten;
+//!
+//! let blif = parse_str_blif_to_ast(
+//!     "top.blif",
+//!     ".model top\n.inputs a\n.outputs y\n.subckt buf|u0 i=a o=y\n.end\n\
+//!      .model buf\n.inputs i\n.outputs o\n.names i t\n0 1\n.names t o\n0 1\n.end\n",
+//! )
+//! .unwrap();
+//! let flat = flatten(&blif, "top").unwrap();
+//!
+//! let ModelCmdKind::Gate(gate) = &flat.commands[0].kind else { panic!() };
+//! assert_eq!(gate.meta.inputs[0], "a");
+//! assert_eq!(gate.meta.output, "u0.t");
+//! ```
+//!
+//! Instances of `.blackbox` models have no commands to inline, so they are
+//! kept as `.subckt`.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::Str;
+use crate::ast::{Blif, CellAttrAst, Model, ModelCmd, ModelCmdKind};
+use crate::hierarchy::{Hierarchy, HierarchyError, instance_label};
+
+/// Options of [`flatten_with`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlattenOptions {
+    /// Put between the instance names of the path of an inlined net, and
+    /// between the path and the name of the net. Defaults to `.`.
+    pub separator: String,
+}
+
+impl Default for FlattenOptions {
+    fn default() -> Self {
+        Self {
+            separator: ".".to_string(),
+        }
+    }
+}
+
+/// Why a model can not be flattened.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FlattenError {
+    /// The top model or an instantiated model does not exist.
+    MissingModel {
+        /// The model containing the instance, `None` for the top model.
+        parent: Option<String>,
+        /// The missing model.
+        model: String,
+    },
+    /// A problem with the hierarchy below the top model that prevents
+    /// inlining it, namely [`HierarchyError::Recursion`].
+    Hierarchy(HierarchyError),
+    /// An instantiated model contains a `.start_kiss` FSM, which refers to
+    /// the ports of its model implicitly and can therefore not be inlined.
+    Fsm {
+        /// The model containing the FSM.
+        model: String,
+    },
+}
+
+impl fmt::Display for FlattenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlattenError::MissingModel {
+                parent: Some(parent),
+                model,
+            } => write!(
+                f,
+                "model `{parent}` instantiates model `{model}`, which does not exist"
+            ),
+            FlattenError::MissingModel {
+                parent: None,
+                model,
+            } => write!(f, "top model `{model}` does not exist"),
+            FlattenError::Hierarchy(err) => write!(f, "{err}"),
+            FlattenError::Fsm { model } => {
+                write!(f, "model `{model}` contains an FSM and can not be inlined")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FlattenError {}
+
+/// Flatten the model `top` of `blif`, with the default [`FlattenOptions`].
+///
+/// # Errors
+///
+/// See [`flatten_with`].
+pub fn flatten(blif: &Blif, top: &str) -> Result<Model, FlattenError> {
+    flatten_with(blif, top, &FlattenOptions::default())
+}
+
+/// Flatten the model `top` of `blif`.
+///
+/// The result has the header and attributes of `top`. Instances are named by
+/// [`instance_label`]. The formals of an inlined model are
+/// replaced by the actuals of the instance; formals that are not connected
+/// become internal nets of the instance.
+///
+/// Names never clash: instance labels that are taken in their model, and
+/// inlined nets whose name is taken by another net, get a suffix `_1`,
+/// `_2`, …. Explicit instance names and `.cname` win over the
+/// `<model>_<index>` fallback.
+///
+/// `.cname` of inlined cells get the instance path as prefix, `.attr` and
+/// `.param` are kept as they are. Timing constraints, `.ltlformula` and
+/// `.spec` of inlined models are dropped, only those of `top` are kept.
+///
+/// # Errors
+///
+/// Returns an error if a model is missing, the hierarchy is recursive, or an
+/// inlined model contains an FSM.
+pub fn flatten_with(blif: &Blif, top: &str, opts: &FlattenOptions) -> Result<Model, FlattenError> {
+    let hier = Hierarchy::new(blif);
+    let Some(model) = hier.model(top) else {
+        return Err(FlattenError::MissingModel {
+            parent: None,
+            model: top.to_string(),
+        });
+    };
+
+    // cycles elsewhere in `blif` don't matter
+    let reachable: HashSet<&str> = hier
+        .bottom_up(top)
+        .iter()
+        .map(|m| m.meta.name.as_str())
+        .collect();
+    let recursion = hier.errors().iter().find(|err| {
+        matches!(err, HierarchyError::Recursion { cycle } if reachable.contains(cycle[0].as_str()))
+    });
+    if let Some(err) = recursion {
+        return Err(FlattenError::Hierarchy(err.clone()));
+    }
+
+    // the nets of `top` keep their names
+    let used = RefCell::new(HashSet::new());
+    let meta = &model.meta;
+    for port in meta.inputs.iter().chain(&meta.outputs).flatten() {
+        used.borrow_mut().insert(port.to_string());
+    }
+    for name in &meta.clocks {
+        used.borrow_mut().insert(name.to_string());
+    }
+    let collect = |name: &str| -> Str<16> {
+        used.borrow_mut().insert(name.to_string());
+        name.into()
+    };
+    for cmd in &model.commands {
+        rename_nets(&cmd.kind, &collect, &|x| x.to_string());
+    }
+
+    let mut flattener = Flattener {
+        hier: &hier,
+        opts,
+        used: &used,
+        commands: vec![],
+    };
+    flattener.inline(model, "", &HashMap::new())?;
+
+    Ok(Model {
+        meta: model.meta.clone(),
+        commands: flattener.commands,
+        attr: model.attr.clone(),
+    })
+}
+
+struct Flattener<'a> {
+    hier: &'a Hierarchy<'a>,
+    opts: &'a FlattenOptions,
+    /// The names of all nets of the result so far.
+    used: &'a RefCell<HashSet<String>>,
+    commands: Vec<ModelCmd>,
+}
+
+/// `name`, or `name` with a suffix if it is taken.
+fn unique(used: &mut HashSet<String>, name: &str) -> String {
+    let mut candidate = name.to_string();
+    let mut n = 0;
+    while !used.insert(candidate.clone()) {
+        n += 1;
+        candidate = format!("{name}_{n}");
+    }
+    candidate
+}
+
+impl<'a> Flattener<'a> {
+    /// Append the commands of `model`, with the nets renamed for the instance
+    /// path `prefix` and the formals in `ports` bound to their actuals.
+    fn inline(
+        &mut self,
+        model: &'a Model,
+        prefix: &str,
+        ports: &HashMap<&str, Str<16>>,
+    ) -> Result<(), FlattenError> {
+        let sep = self.opts.separator.as_str();
+        let path = |name: &str| -> String {
+            if prefix.is_empty() {
+                name.to_string()
+            } else {
+                format!("{prefix}{sep}{name}")
+            }
+        };
+        let used = self.used;
+        let internal: RefCell<HashMap<String, Str<16>>> = RefCell::default();
+        let net = |name: &str| -> Str<16> {
+            if let Some(actual) = ports.get(name) {
+                return actual.clone();
+            }
+            if prefix.is_empty() {
+                return name.into();
+            }
+            if let Some(renamed) = internal.borrow().get(name) {
+                return renamed.clone();
+            }
+            let renamed: Str<16> = unique(&mut used.borrow_mut(), &path(name)).as_str().into();
+            internal
+                .borrow_mut()
+                .insert(name.to_string(), renamed.clone());
+            renamed
+        };
+
+        // explicit instance labels first, then the fallbacks, which are the
+        // only labels that `instance_label` allocates
+        let mut labels = HashSet::new();
+        let mut label_of = vec![None; model.commands.len()];
+        for explicit in [true, false] {
+            for (idx, cmd) in model.commands.iter().enumerate() {
+                let Some(label) = instance_label(idx, cmd) else {
+                    continue;
+                };
+                if matches!(label, Cow::Borrowed(_)) == explicit {
+                    label_of[idx] = Some(unique(&mut labels, &label));
+                }
+            }
+        }
+
+        for (idx, cmd) in model.commands.iter().enumerate() {
+            if let ModelCmdKind::SubModel { name, map, .. } = &cmd.kind {
+                let Some(target) = self.hier.model(name) else {
+                    return Err(FlattenError::MissingModel {
+                        parent: Some(model.meta.name.to_string()),
+                        model: name.to_string(),
+                    });
+                };
+
+                if !target.attr.blackbox {
+                    let inst = path(label_of[idx].as_deref().unwrap());
+                    let ports = map
+                        .iter()
+                        .map(|(formal, actual)| (formal.as_str(), net(actual)))
+                        .collect();
+
+                    self.inline(target, &inst, &ports)?;
+                    continue;
+                }
+            }
+
+            let inlined = !prefix.is_empty();
+            let kind = match &cmd.kind {
+                ModelCmdKind::FSM(_) if inlined => {
+                    return Err(FlattenError::Fsm {
+                        model: model.meta.name.to_string(),
+                    });
+                }
+                ModelCmdKind::CycleTime(_)
+                | ModelCmdKind::ClockEvents(_)
+                | ModelCmdKind::DelayConstraint(_)
+                | ModelCmdKind::LtlFormula(_)
+                | ModelCmdKind::Spec(_)
+                    if inlined =>
+                {
+                    continue;
+                }
+                kind => rename_nets(kind, &net, &path),
+            };
+            let attrs = cmd
+                .attrs
+                .iter()
+                .map(|attr| match attr {
+                    CellAttrAst::CellName(n) => CellAttrAst::CellName(path(n)),
+                    attr => attr.clone(),
+                })
+                .collect();
+            self.commands.push(ModelCmd { kind, attrs });
+        }
+        Ok(())
+    }
+}
+
+/// A copy of `kind` with every net renamed by `net`, and the instance name of
+/// a `.subckt` by `path`.
+pub(crate) fn rename_nets(
+    kind: &ModelCmdKind,
+    net: &impl Fn(&str) -> Str<16>,
+    path: &impl Fn(&str) -> String,
+) -> ModelCmdKind {
+    let nets = |names: &[Str<16>]| names.iter().map(|x| net(x)).collect::<Vec<_>>();
+    let actuals = |map: &[(Str<16>, Str<16>)]| {
+        map.iter()
+            .map(|(formal, actual)| (formal.clone(), net(actual)))
+            .collect::<Vec<_>>()
+    };
+
+    let mut kind = kind.clone();
+    match &mut kind {
+        ModelCmdKind::Gate(gate) => {
+            gate.meta.inputs = nets(&gate.meta.inputs);
+            gate.meta.output = net(&gate.meta.output);
+        }
+        ModelCmdKind::FF(ff) => {
+            ff.input = net(&ff.input);
+            ff.output = net(&ff.output);
+            ff.clock = ff.clock.as_deref().map(net);
+        }
+        ModelCmdKind::LibGate(lg) => lg.maps = actuals(&lg.maps),
+        ModelCmdKind::LibFF(lf) => {
+            lf.maps = actuals(&lf.maps);
+            lf.clock = lf.clock.as_deref().map(net);
+        }
+        ModelCmdKind::SubModel {
+            map, instance_name, ..
+        } => {
+            *map = actuals(map);
+            *instance_name = instance_name.as_deref().map(|x| path(x).as_str().into());
+        }
+        ModelCmdKind::Connect { from, to } => {
+            *from = net(from);
+            *to = net(to);
+        }
+        ModelCmdKind::Constraint(signals) | ModelCmdKind::OneHot(signals) => {
+            *signals = nets(signals);
+        }
+        ModelCmdKind::Reset { signal, .. } | ModelCmdKind::GateInit { signal, .. } => {
+            *signal = net(signal);
+        }
+        ModelCmdKind::Mv { variables, .. } => *variables = nets(variables),
+        // only kept in the top model, where nets are not renamed
+        ModelCmdKind::FSM(_)
+        | ModelCmdKind::CycleTime(_)
+        | ModelCmdKind::ClockEvents(_)
+        | ModelCmdKind::DelayConstraint(_)
+        | ModelCmdKind::LtlFormula(_)
+        | ModelCmdKind::Spec(_) => {}
+    }
+    kind
+}
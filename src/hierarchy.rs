@@ -0,0 +1,758 @@
+//! Resolution of the model hierarchy of a design.
+//!
+//! A [`Hierarchy`] links every `.subckt` instance to the model it
+//! instantiates and checks the instance against the ports of that model:
+//!
+//! ```rust
+//! use turbo_blif::ast::parse_str_blif_to_ast;
+//! use turbo_blif::hierarchy::{Hierarchy, HierarchyError};
+//!
+//! let blif = parse_str_blif_to_ast(
+//!     "top.blif",
+//!     ".model top\n.inputs a\n.outputs y\n.subckt inv i=a o=y\n.subckt ram d=a\n.end\n\
+//!      .model inv\n.inputs i\n.outputs o\n.names i o\n0 1\n.end\n",
+//! )
+//! .unwrap();
+//! let hier = Hierarchy::new(&blif);
+//!
+//! assert_eq!(hier.top().unwrap().meta.name, "top");
+//! assert_eq!(hier.instances()[0].target.unwrap().meta.name, "inv");
+//! assert_eq!(
+//!     hier.errors(),
+//!     &[HierarchyError::MissingModel {
+//!         parent: "top".into(),
+//!         command: 1,
+//!         model: "ram".into(),
+//!     }]
+//! );
+//! ```
+//!
+//! Models declared with `.blackbox` are valid targets: they only declare
+//! their ports, so they are how libraries provide cells whose implementation
+//! lives elsewhere.
+//!
+//! [`uniquify`] gives every instance its own copy of the instantiated model,
+//! and [`dedupe`] merges identical models again.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::Str;
+use crate::ast::{Blif, BlifEntry, CellAttrAst, Model, ModelCmd, ModelCmdKind};
+use crate::flatten::rename_nets;
+use crate::netlist::model_ports;
+
+/// A problem with the hierarchy.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HierarchyError {
+    /// A `.subckt` instantiates a model that does not exist.
+    MissingModel {
+        /// The model containing the instance.
+        parent: String,
+        /// Index of the `.subckt` in the commands of `parent`.
+        command: usize,
+        /// The missing model.
+        model: String,
+    },
+    /// A `.subckt` connects a formal that the instantiated model does not
+    /// have.
+    UnknownFormal {
+        /// The model containing the instance.
+        parent: String,
+        /// Index of the `.subckt` in the commands of `parent`.
+        command: usize,
+        /// The instantiated model.
+        model: String,
+        /// The formal pin.
+        formal: String,
+    },
+    /// A `.subckt` does not connect an input of the instantiated model.
+    UnconnectedInput {
+        /// The model containing the instance.
+        parent: String,
+        /// Index of the `.subckt` in the commands of `parent`.
+        command: usize,
+        /// The instantiated model.
+        model: String,
+        /// The input that is not connected.
+        formal: String,
+    },
+    /// A model instantiates itself, directly or through other models.
+    Recursion {
+        /// The models of the cycle; the first one is repeated at the end.
+        cycle: Vec<String>,
+    },
+    /// Several models have the same name. Only the first one is used.
+    DuplicateModel {
+        /// The name of the models.
+        model: String,
+    },
+}
+
+impl fmt::Display for HierarchyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HierarchyError::MissingModel {
+                parent,
+                command,
+                model,
+            } => write!(
+                f,
+                "model `{parent}`, command {command}: model `{model}` does not exist"
+            ),
+            HierarchyError::UnknownFormal {
+                parent,
+                command,
+                model,
+                formal,
+            } => write!(
+                f,
+                "model `{parent}`, command {command}: model `{model}` has no port `{formal}`"
+            ),
+            HierarchyError::UnconnectedInput {
+                parent,
+                command,
+                model,
+                formal,
+            } => write!(
+                f,
+                "model `{parent}`, command {command}: input `{formal}` of model `{model}` is not connected"
+            ),
+            HierarchyError::Recursion { cycle } => {
+                write!(f, "recursive instantiation: {}", cycle.join(" -> "))
+            }
+            HierarchyError::DuplicateModel { model } => {
+                write!(f, "model `{model}` is defined more than once")
+            }
+        }
+    }
+}
+
+impl std::error::Error for HierarchyError {}
+
+/// A `.subckt` instance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instance<'a> {
+    /// The model containing the instance.
+    pub parent: &'a Model,
+    /// Index of the `.subckt` in the commands of `parent`.
+    pub command: usize,
+    /// Name of the instantiated model.
+    pub model: &'a str,
+    /// The BLIF-MV instance name, from `model|instance`.
+    pub instance_name: Option<&'a str>,
+    /// The formal/actual pin map.
+    pub map: &'a [(Str<16>, Str<16>)],
+    /// The instantiated model, if it exists.
+    pub target: Option<&'a Model>,
+}
+
+/// The model hierarchy of a [`Blif`].
+#[derive(Debug, Clone)]
+pub struct Hierarchy<'a> {
+    models: Vec<&'a Model>,
+    by_name: HashMap<&'a str, usize>,
+    instances: Vec<Instance<'a>>,
+    /// Indices into `instances`, per model.
+    children: Vec<Vec<usize>>,
+    tops: Vec<usize>,
+    errors: Vec<HierarchyError>,
+}
+
+impl<'a> Hierarchy<'a> {
+    /// Link and check all instances of `blif`.
+    pub fn new(blif: &'a Blif) -> Self {
+        let models: Vec<&Model> = blif.models().collect();
+        let mut errors = vec![];
+
+        let mut by_name = HashMap::new();
+        for (idx, model) in models.iter().enumerate() {
+            if by_name.contains_key(model.meta.name.as_str()) {
+                errors.push(HierarchyError::DuplicateModel {
+                    model: model.meta.name.to_string(),
+                });
+            } else {
+                by_name.insert(model.meta.name.as_str(), idx);
+            }
+        }
+
+        let mut instances = vec![];
+        let mut children = vec![vec![]; models.len()];
+        let mut instantiated = vec![false; models.len()];
+        for (idx, parent) in models.iter().enumerate() {
+            for (command, cmd) in parent.commands.iter().enumerate() {
+                let ModelCmdKind::SubModel {
+                    name,
+                    map,
+                    instance_name,
+                } = &cmd.kind
+                else {
+                    continue;
+                };
+
+                let target = by_name.get(name.as_str()).copied();
+                if let Some(target) = target {
+                    instantiated[target] = true;
+                    let (unknown, unconnected) = check_ports(blif, models[target], map);
+                    let model = &models[target].meta.name;
+                    for formal in unknown {
+                        errors.push(HierarchyError::UnknownFormal {
+                            parent: parent.meta.name.to_string(),
+                            command,
+                            model: model.to_string(),
+                            formal: formal.to_string(),
+                        });
+                    }
+                    for formal in unconnected {
+                        errors.push(HierarchyError::UnconnectedInput {
+                            parent: parent.meta.name.to_string(),
+                            command,
+                            model: model.to_string(),
+                            formal: formal.to_string(),
+                        });
+                    }
+                } else {
+                    errors.push(HierarchyError::MissingModel {
+                        parent: parent.meta.name.to_string(),
+                        command,
+                        model: name.to_string(),
+                    });
+                }
+
+                children[idx].push(instances.len());
+                instances.push(Instance {
+                    parent,
+                    command,
+                    model: name,
+                    instance_name: instance_name.as_deref(),
+                    map,
+                    target: target.map(|x| models[x]),
+                });
+            }
+        }
+
+        let tops = (0..models.len())
+            .filter(|&idx| !instantiated[idx] && !models[idx].attr.blackbox)
+            .filter(|&idx| by_name[models[idx].meta.name.as_str()] == idx)
+            .collect();
+
+        let mut hier = Self {
+            models,
+            by_name,
+            instances,
+            children,
+            tops,
+            errors,
+        };
+        hier.find_recursion();
+        hier
+    }
+
+    /// Report every cycle of the instantiation graph once.
+    fn find_recursion(&mut self) {
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            New,
+            OnStack,
+            Done,
+        }
+
+        let mut state = vec![State::New; self.models.len()];
+        let mut cycles = vec![];
+        for root in 0..self.models.len() {
+            if state[root] != State::New {
+                continue;
+            }
+            // iterative DFS: (model, next child to visit)
+            let mut stack = vec![(root, 0)];
+            state[root] = State::OnStack;
+            while let Some((model, child)) = stack.last_mut() {
+                let model = *model;
+                let Some(&inst) = self.children[model].get(*child) else {
+                    state[model] = State::Done;
+                    stack.pop();
+                    continue;
+                };
+                *child += 1;
+
+                let Some(target) = self.target_idx(inst) else {
+                    continue;
+                };
+                match state[target] {
+                    State::New => {
+                        state[target] = State::OnStack;
+                        stack.push((target, 0));
+                    }
+                    State::OnStack => {
+                        let start = stack.iter().position(|(m, _)| *m == target).unwrap();
+                        let mut cycle: Vec<String> = stack[start..]
+                            .iter()
+                            .map(|(m, _)| self.models[*m].meta.name.to_string())
+                            .collect();
+                        cycle.push(self.models[target].meta.name.to_string());
+                        cycles.push(cycle);
+                    }
+                    State::Done => {}
+                }
+            }
+        }
+
+        self.errors.extend(
+            cycles
+                .into_iter()
+                .map(|cycle| HierarchyError::Recursion { cycle }),
+        );
+    }
+
+    fn target_idx(&self, inst: usize) -> Option<usize> {
+        self.by_name.get(self.instances[inst].model).copied()
+    }
+
+    /// Everything that is wrong with the hierarchy.
+    pub fn errors(&self) -> &[HierarchyError] {
+        &self.errors
+    }
+
+    /// The model called `name`; the first one if there are several.
+    pub fn model(&self, name: &str) -> Option<&'a Model> {
+        self.by_name.get(name).map(|&idx| self.models[idx])
+    }
+
+    /// All instances, in source order.
+    pub fn instances(&self) -> &[Instance<'a>] {
+        &self.instances
+    }
+
+    /// The instances inside of the model called `name`.
+    pub fn instances_in(&self, name: &str) -> impl Iterator<Item = &Instance<'a>> {
+        let children = self
+            .by_name
+            .get(name)
+            .map_or(&[][..], |&idx| &self.children[idx]);
+        children.iter().map(|&inst| &self.instances[inst])
+    }
+
+    /// The models that are never instantiated, in source order.
+    ///
+    /// `.blackbox` models are never top models.
+    pub fn tops(&self) -> Vec<&'a Model> {
+        self.tops.iter().map(|&idx| self.models[idx]).collect()
+    }
+
+    /// The top model, if there is exactly one.
+    pub fn top(&self) -> Option<&'a Model> {
+        match self.tops[..] {
+            [idx] => Some(self.models[idx]),
+            _ => None,
+        }
+    }
+
+    /// Names of the models that are instantiated but don't exist, without
+    /// duplicates.
+    pub fn missing(&self) -> Vec<&'a str> {
+        let mut out: Vec<&str> = vec![];
+        for inst in &self.instances {
+            if inst.target.is_none() && !out.contains(&inst.model) {
+                out.push(inst.model);
+            }
+        }
+        out
+    }
+
+    /// The models reachable from `top`, every model after all models it
+    /// instantiates. `top` is last.
+    ///
+    /// Recursive instantiations are skipped.
+    pub fn bottom_up(&self, top: &str) -> Vec<&'a Model> {
+        let Some(&root) = self.by_name.get(top) else {
+            return vec![];
+        };
+        let mut visited = vec![false; self.models.len()];
+        let mut out = vec![];
+        let mut stack = vec![(root, 0)];
+        visited[root] = true;
+        while let Some((model, child)) = stack.last_mut() {
+            let model = *model;
+            let Some(&inst) = self.children[model].get(*child) else {
+                out.push(self.models[model]);
+                stack.pop();
+                continue;
+            };
+            *child += 1;
+            if let Some(target) = self.target_idx(inst)
+                && !visited[target]
+            {
+                visited[target] = true;
+                stack.push((target, 0));
+            }
+        }
+        out
+    }
+}
+
+/// Check the connections `map` of a `.subckt` against the ports of the
+/// instantiated model `target`. Returns the formals that `target` has no port
+/// for, and the inputs of `target` that `map` does not connect. Omitted
+/// `.inputs` or `.outputs` are inferred with [`model_ports`].
+pub(crate) fn check_ports<'m>(
+    blif: &Blif,
+    target: &Model,
+    map: &'m [(Str<16>, Str<16>)],
+) -> (Vec<&'m Str<16>>, Vec<Str<16>>) {
+    let meta = &target.meta;
+    let (inputs, outputs): (Cow<[Str<16>]>, Cow<[Str<16>]>) = match (&meta.inputs, &meta.outputs) {
+        (Some(inputs), Some(outputs)) => (inputs.into(), outputs.into()),
+        _ => {
+            let (inputs, outputs) = model_ports(target, Some(blif));
+            (inputs.into(), outputs.into())
+        }
+    };
+
+    let unknown = map
+        .iter()
+        .map(|(formal, _)| formal)
+        .filter(|formal| {
+            !inputs
+                .iter()
+                .chain(outputs.iter())
+                .chain(&meta.clocks)
+                .any(|x| x == *formal)
+        })
+        .collect();
+    let unconnected = inputs
+        .iter()
+        .filter(|input| !map.iter().any(|(formal, _)| formal == *input))
+        .cloned()
+        .collect();
+    (unknown, unconnected)
+}
+
+/// The name of the `.subckt` at index `cmd_index` of its model: its BLIF-MV
+/// instance name (`.subckt model|name`), its `.cname`, or else
+/// `<model>_<cmd_index>`.
+///
+/// Returns `None` if `cmd` is not a `.subckt`.
+pub fn instance_label(cmd_index: usize, cmd: &ModelCmd) -> Option<Cow<'_, str>> {
+    let ModelCmdKind::SubModel {
+        name,
+        instance_name,
+        ..
+    } = &cmd.kind
+    else {
+        return None;
+    };
+    let cname = cmd.attrs.iter().find_map(|attr| match attr {
+        CellAttrAst::CellName(n) => Some(n.as_str()),
+        _ => None,
+    });
+    Some(match instance_name.as_deref().or(cname) {
+        Some(label) => Cow::Borrowed(label),
+        None => Cow::Owned(format!("{name}_{cmd_index}")),
+    })
+}
+
+/// Give every instance of a model its own copy of the model.
+///
+/// Starting at the [top models](Hierarchy::tops), every model that is
+/// reached through more than one instance is replaced by one clone per
+/// instance, named `<model>__<instance path>`, with the [instance
+/// labels](instance_label) of the path joined by `__`, for example
+/// `adder__u1` or `adder__alu0__u1`. A numeric suffix is added if that name
+/// is already taken. The `.subckt` commands are changed to instantiate the
+/// clones.
+///
+/// The clones take the place of the original model. `.blackbox` models,
+/// missing models and models that are not reachable from a top model are
+/// left alone.
+///
+/// # Errors
+///
+/// Returns the first [`HierarchyError::Recursion`] if the hierarchy is
+/// recursive. `blif` is not changed in that case.
+pub fn uniquify(blif: &mut Blif) -> Result<(), HierarchyError> {
+    let models: Vec<Model> = blif.models().cloned().collect();
+    let hier = Hierarchy::new(blif);
+    if let Some(err) = hier
+        .errors()
+        .iter()
+        .find(|err| matches!(err, HierarchyError::Recursion { .. }))
+    {
+        return Err(err.clone());
+    }
+    let tops: Vec<usize> = hier.tops.clone();
+    let by_name: HashMap<String, usize> = hier
+        .by_name
+        .iter()
+        .map(|(name, idx)| (name.to_string(), *idx))
+        .collect();
+    drop(hier);
+
+    let mut uniq = Uniquifier {
+        models: &models,
+        by_name: &by_name,
+        paths: vec![0; models.len()],
+        taken: models.iter().map(|m| m.meta.name.to_string()).collect(),
+        copies: vec![vec![]; models.len()],
+    };
+    for &top in &tops {
+        uniq.count(top);
+    }
+    for &top in &tops {
+        let copy = uniq.copy(top, models[top].meta.name.to_string(), "");
+        uniq.copies[top].push(copy);
+    }
+
+    let Uniquifier { mut copies, .. } = uniq;
+    blif.entries = models
+        .into_iter()
+        .enumerate()
+        .flat_map(|(idx, model)| match std::mem::take(&mut copies[idx]) {
+            copies if copies.is_empty() => vec![model],
+            copies => copies,
+        })
+        .map(BlifEntry::Model)
+        .collect();
+    Ok(())
+}
+
+struct Uniquifier<'a> {
+    models: &'a [Model],
+    by_name: &'a HashMap<String, usize>,
+    /// Number of instance paths that reach each model.
+    paths: Vec<usize>,
+    /// Names that can not be used for clones.
+    taken: HashSet<String>,
+    /// The new versions of each model.
+    copies: Vec<Vec<Model>>,
+}
+
+impl Uniquifier<'_> {
+    /// The models instantiated by `model` that have an implementation.
+    fn targets(&self, model: usize) -> impl Iterator<Item = (usize, &ModelCmd, usize)> {
+        self.models[model]
+            .commands
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, cmd)| match &cmd.kind {
+                ModelCmdKind::SubModel { name, .. } => {
+                    let target = *self.by_name.get(name.as_str())?;
+                    (!self.models[target].attr.blackbox).then_some((idx, cmd, target))
+                }
+                _ => None,
+            })
+    }
+
+    fn count(&mut self, model: usize) {
+        let targets: Vec<usize> = self.targets(model).map(|(_, _, t)| t).collect();
+        for target in targets {
+            self.paths[target] += 1;
+            self.count(target);
+        }
+    }
+
+    /// A copy of `model` called `name`, with all instances pointing at
+    /// their own copies. Models that are only reached once are stored in
+    /// `copies` under their own name.
+    fn copy(&mut self, model: usize, name: String, path: &str) -> Model {
+        let mut out = self.models[model].clone();
+        out.meta.name = name.as_str().into();
+
+        let targets: Vec<(usize, String, usize)> = self
+            .targets(model)
+            .map(|(idx, cmd, target)| (idx, instance_label(idx, cmd).unwrap().into(), target))
+            .collect();
+        for (idx, label, target) in targets {
+            let path = if path.is_empty() {
+                label
+            } else {
+                format!("{path}__{label}")
+            };
+            let target_name = self.models[target].meta.name.to_string();
+            if self.paths[target] <= 1 {
+                let copy = self.copy(target, target_name, &path);
+                self.copies[target].push(copy);
+                continue;
+            }
+
+            let base = format!("{target_name}__{path}");
+            let mut clone_name = base.clone();
+            let mut suffix = 0;
+            while self.taken.contains(&clone_name) {
+                suffix += 1;
+                clone_name = format!("{base}_{suffix}");
+            }
+            self.taken.insert(clone_name.clone());
+
+            let copy = self.copy(target, clone_name.clone(), &path);
+            self.copies[target].push(copy);
+            if let ModelCmdKind::SubModel { name, .. } = &mut out.commands[idx].kind {
+                *name = clone_name.as_str().into();
+            }
+        }
+        out
+    }
+}
+
+/// Merge models that only differ in their name, and return the number of
+/// removed models.
+///
+/// Of each group of identical models, the first one is kept, and `.subckt`
+/// commands are changed to instantiate it. Merging can make more models
+/// identical, so this repeats until nothing changes. `.blackbox` models are
+/// never merged, because they stand for different cells.
+///
+/// Models are compared regardless of the order of their cells, truth-table
+/// rows and `.subckt` pin maps, and of the names of their internal nets.
+/// Timing constraints and other directives keep their order, like in
+/// [`WriteOptions::canonical`](crate::writer::WriteOptions::canonical).
+/// Internal nets are numbered in the order of the sorted cells, so models
+/// with several cells that only differ in internal nets may be missed.
+///
+/// To undo [`uniquify`], the kept model gets the name in front of the first
+/// `__` of its name, if all models of the group share that prefix and no
+/// other model has that name.
+pub fn dedupe(blif: &mut Blif) -> usize {
+    let mut removed = 0;
+    loop {
+        let models: Vec<&Model> = blif.models().collect();
+        let forms: Vec<Vec<String>> = models.iter().map(|m| canonical_form(m)).collect();
+        let identical = |a: usize, b: usize| {
+            let (ma, mb) = (models[a], models[b]);
+            !ma.attr.blackbox
+                && ma.attr == mb.attr
+                && ma.meta.inputs == mb.meta.inputs
+                && ma.meta.outputs == mb.meta.outputs
+                && ma.meta.clocks == mb.meta.clocks
+                && forms[a] == forms[b]
+        };
+
+        let mut merged = vec![false; models.len()];
+        let mut renames: HashMap<String, String> = HashMap::new();
+        for (idx, model) in models.iter().enumerate() {
+            if merged[idx] {
+                continue;
+            }
+            let group: Vec<usize> = (idx + 1..models.len())
+                .filter(|&other| !merged[other] && identical(idx, other))
+                .collect();
+            if group.is_empty() {
+                continue;
+            }
+
+            let prefix = |m: &Model| m.meta.name.split_once("__").map(|(p, _)| p.to_string());
+            let mut keep = model.meta.name.to_string();
+            if let Some(base) = prefix(model)
+                && group
+                    .iter()
+                    .all(|&x| prefix(models[x]).as_ref() == Some(&base))
+                && !models.iter().any(|m| m.meta.name == base.as_str())
+                && !renames.values().any(|x| *x == base)
+            {
+                renames.insert(keep.clone(), base.clone());
+                keep = base;
+            }
+            for other in group {
+                merged[other] = true;
+                renames.insert(models[other].meta.name.to_string(), keep.clone());
+            }
+        }
+        if renames.is_empty() {
+            return removed;
+        }
+
+        let mut idx = 0;
+        blif.entries.retain(|_| {
+            idx += 1;
+            !merged[idx - 1]
+        });
+        removed += merged.iter().filter(|x| **x).count();
+
+        for entry in &mut blif.entries {
+            let BlifEntry::Model(model) = entry;
+            if let Some(new) = renames.get(model.meta.name.as_str()) {
+                model.meta.name = new.as_str().into();
+            }
+            for cmd in &mut model.commands {
+                if let ModelCmdKind::SubModel { name, .. } = &mut cmd.kind
+                    && let Some(new) = renames.get(name.as_str())
+                {
+                    *name = new.as_str().into();
+                }
+            }
+        }
+    }
+}
+
+/// The commands of `model` as text, with cells sorted and internal nets
+/// numbered in the order of the sorted cells, see [`dedupe`].
+fn canonical_form(model: &Model) -> Vec<String> {
+    let meta = &model.meta;
+    // without declared ports, every net may be a port
+    let declared = meta.inputs.is_some() && meta.outputs.is_some();
+    let ports: HashSet<&str> = meta
+        .inputs
+        .iter()
+        .chain(&meta.outputs)
+        .flatten()
+        .chain(&meta.clocks)
+        .map(|x| x.as_str())
+        .collect();
+    let is_port = |net: &str| !declared || ports.contains(net);
+
+    let mut cells = vec![];
+    let mut directives = vec![];
+    for cmd in &model.commands {
+        let mut cmd = cmd.clone();
+        match &mut cmd.kind {
+            ModelCmdKind::Gate(gate) => gate.lut.0.sort_by(|a, b| a.partial_cmp(b).unwrap()),
+            ModelCmdKind::SubModel { map, .. } => map.sort(),
+            _ => {}
+        }
+        match cmd.kind {
+            ModelCmdKind::Gate(_)
+            | ModelCmdKind::FF(_)
+            | ModelCmdKind::LibGate(_)
+            | ModelCmdKind::LibFF(_)
+            | ModelCmdKind::FSM(_)
+            | ModelCmdKind::SubModel { .. }
+            | ModelCmdKind::Connect { .. } => {
+                // the cell with its internal nets left out
+                let anonymous =
+                    |net: &str| -> Str<16> { if is_port(net) { net.into() } else { "".into() } };
+                let kind = rename_nets(&cmd.kind, &anonymous, &|x| x.to_string());
+                cells.push((format!("{kind:?}"), cmd));
+            }
+            _ => directives.push(cmd),
+        }
+    }
+    cells.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let numbers = RefCell::new(HashMap::new());
+    let number = |net: &str| -> Str<16> {
+        if is_port(net) {
+            return net.into();
+        }
+        let mut numbers = numbers.borrow_mut();
+        let next = numbers.len();
+        // `#` starts a comment, so no port has this name
+        format!("#{}", numbers.entry(net.to_string()).or_insert(next))
+            .as_str()
+            .into()
+    };
+    let rename = |cmd: &ModelCmd| {
+        let kind = rename_nets(&cmd.kind, &number, &|x| x.to_string());
+        format!(
+            "{:?}",
+            ModelCmd {
+                kind,
+                attrs: cmd.attrs.clone()
+            }
+        )
+    };
+    let mut out: Vec<String> = cells.iter().map(|(_, cmd)| rename(cmd)).collect();
+    out.sort();
+    out.extend(directives.iter().map(rename));
+    out
+}
@@ -0,0 +1,273 @@
+//! Reading and writing standalone KISS2 state machines.
+//!
+//! KISS2 is the format of the MCNC FSM benchmarks, and the same format as
+//! the body of a `.start_kiss` block: `.i`, `.o`, `.p`, `.s` and `.r`,
+//! followed by one `input current-state next-state output` line per
+//! transition. [`parse_kiss`] reads it into an [`FSM`], [`write_kiss`]
+//! writes one, and [`fsm_model`] wraps an FSM into a model:
+//!
+//! ```rust
+//! use turbo_blif::kiss::{fsm_model, kiss_to_string, parse_kiss};
+//!
+//! let source = ".i 1\n.o 1\n.p 3\n.s 2\n.r a\n0 a a 0\n1 a b 0\n- b a 1\n";
+//! let fsm = parse_kiss(source).unwrap();
+//! assert_eq!(fsm.reset_state.as_deref(), Some("a"));
+//! assert_eq!(kiss_to_string(&fsm), source);
+//!
+//! let model = fsm_model("toggle", fsm);
+//! assert_eq!(model.meta.inputs.as_deref(), Some(&["i0".into()][..]));
+//! assert_eq!(model.meta.outputs.as_deref(), Some(&["o0".into()][..]));
+//! ```
+
+use std::collections::HashSet;
+use std::fmt;
+
+use smallvec::SmallVec;
+
+use crate::ast::{Blif, BlifEntry, FSM, FSMTransitionAST, Model, ModelAttr, ModelCmdKind};
+use crate::{ModelMeta, Tristate};
+
+/// Why a KISS2 file can not be read.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum KissParseError {
+    /// A malformed line.
+    Invalid {
+        /// The line, starting at 1.
+        line: usize,
+    },
+    /// A keyword that is not supported.
+    Unsupported {
+        /// The line, starting at 1.
+        line: usize,
+        /// The keyword, with its leading `.`.
+        keyword: String,
+    },
+    /// A transition came before `.i` or `.o`, or the file has neither.
+    Missing {
+        /// The line, starting at 1.
+        line: usize,
+        /// `.i` or `.o`.
+        keyword: &'static str,
+    },
+    /// A transition has the wrong number of inputs or outputs.
+    Width {
+        /// The line, starting at 1.
+        line: usize,
+    },
+}
+
+impl fmt::Display for KissParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KissParseError::Invalid { line } => write!(f, "line {line}: invalid KISS2 syntax"),
+            KissParseError::Unsupported { line, keyword } => {
+                write!(f, "line {line}: `{keyword}` is not supported")
+            }
+            KissParseError::Missing { line, keyword } => {
+                write!(f, "line {line}: expected `{keyword}` before this line")
+            }
+            KissParseError::Width { line } => {
+                write!(f, "line {line}: wrong number of inputs or outputs")
+            }
+        }
+    }
+}
+
+impl std::error::Error for KissParseError {}
+
+fn tristates(s: &str) -> Option<SmallVec<[Tristate; 8]>> {
+    s.chars().map(|c| c.to_string().parse().ok()).collect()
+}
+
+/// Read a standalone KISS2 file.
+///
+/// `.i` and `.o` are required before the first transition; `.p` and `.s`
+/// are ignored. If `.i` or `.o` is 0, the transitions have no input or
+/// output field. `.code state bits` lines are read into
+/// [`FSM::state_assignments`]. `#` starts a comment, and reading stops at
+/// `.e` or `.end`.
+///
+/// The reset state is only set by `.r`; tools that read the FSM usually
+/// take the current state of the first transition otherwise.
+///
+/// # Errors
+///
+/// Fails for malformed lines, transitions of the wrong width, and other
+/// keywords.
+pub fn parse_kiss(source: &str) -> Result<FSM, KissParseError> {
+    let mut inputs: Option<usize> = None;
+    let mut outputs: Option<usize> = None;
+    let mut fsm = FSM {
+        inputs: 0,
+        outputs: 0,
+        reset_state: None,
+        states: vec![],
+        physical_latch_order: None,
+        state_assignments: None,
+    };
+
+    for (idx, line) in source.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let invalid = || KissParseError::Invalid { line: line_no };
+        let number = |x: Option<&str>| x.and_then(|x| x.parse::<usize>().ok()).ok_or_else(invalid);
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        if words[0].starts_with('.') {
+            match words[..] {
+                [".i", n] => inputs = Some(number(Some(n))?),
+                [".o", n] => outputs = Some(number(Some(n))?),
+                [".p", n] | [".s", n] => {
+                    number(Some(n))?;
+                }
+                [".r", state] => fsm.reset_state = Some(state.to_string()),
+                [".code", state, bits] => {
+                    let bits = bits
+                        .chars()
+                        .map(|c| match c {
+                            '0' => Some(false),
+                            '1' => Some(true),
+                            _ => None,
+                        })
+                        .collect::<Option<_>>()
+                        .ok_or_else(invalid)?;
+                    fsm.state_assignments
+                        .get_or_insert_with(Vec::new)
+                        .push((state.to_string(), bits));
+                }
+                [".e"] | [".end"] => break,
+                [
+                    ".i" | ".o" | ".p" | ".s" | ".r" | ".code" | ".e" | ".end",
+                    ..,
+                ] => {
+                    return Err(invalid());
+                }
+                _ => {
+                    return Err(KissParseError::Unsupported {
+                        line: line_no,
+                        keyword: words[0].to_string(),
+                    });
+                }
+            }
+            continue;
+        }
+
+        let missing = |keyword| KissParseError::Missing {
+            line: line_no,
+            keyword,
+        };
+        let inputs = inputs.ok_or_else(|| missing(".i"))?;
+        let outputs = outputs.ok_or_else(|| missing(".o"))?;
+        let fields = usize::from(inputs > 0) + 2 + usize::from(outputs > 0);
+        if words.len() != fields {
+            return Err(invalid());
+        }
+        let (input, rest) = if inputs > 0 {
+            (tristates(words[0]).ok_or_else(invalid)?, &words[1..])
+        } else {
+            (SmallVec::new(), &words[..])
+        };
+        let output = if outputs > 0 {
+            tristates(rest[2]).ok_or_else(invalid)?
+        } else {
+            SmallVec::new()
+        };
+        if input.len() != inputs || output.len() != outputs {
+            return Err(KissParseError::Width { line: line_no });
+        }
+        fsm.states.push(FSMTransitionAST {
+            input,
+            current_state: rest[0].to_string(),
+            next_state: rest[1].to_string(),
+            output,
+        });
+    }
+
+    let last = source.lines().count();
+    fsm.inputs = inputs.ok_or(KissParseError::Missing {
+        line: last,
+        keyword: ".i",
+    })?;
+    fsm.outputs = outputs.ok_or(KissParseError::Missing {
+        line: last,
+        keyword: ".o",
+    })?;
+    Ok(fsm)
+}
+
+/// Write an FSM as a standalone KISS2 file, with `.code` lines for the
+/// state assignments. `.s` counts the states in the transitions, without
+/// the `*` of "any state". The latch order is left out, because it refers
+/// to the nets of a model.
+pub fn write_kiss<W: fmt::Write>(fsm: &FSM, w: &mut W) -> fmt::Result {
+    let mut states = HashSet::new();
+    for t in &fsm.states {
+        states.insert(t.current_state.as_str());
+        states.insert(t.next_state.as_str());
+    }
+    states.remove("*");
+
+    writeln!(w, ".i {}", fsm.inputs)?;
+    writeln!(w, ".o {}", fsm.outputs)?;
+    writeln!(w, ".p {}", fsm.states.len())?;
+    writeln!(w, ".s {}", states.len())?;
+    if let Some(reset) = &fsm.reset_state {
+        writeln!(w, ".r {reset}")?;
+    }
+    for t in &fsm.states {
+        if fsm.inputs > 0 {
+            let input: String = t.input.iter().map(Tristate::to_string).collect();
+            write!(w, "{input} ")?;
+        }
+        write!(w, "{} {}", t.current_state, t.next_state)?;
+        if fsm.outputs > 0 {
+            let output: String = t.output.iter().map(Tristate::to_string).collect();
+            write!(w, " {output}")?;
+        }
+        writeln!(w)?;
+    }
+    for (state, code) in fsm.state_assignments.iter().flatten() {
+        let code: String = code.iter().map(|&b| if b { '1' } else { '0' }).collect();
+        writeln!(w, ".code {state} {code}")?;
+    }
+    Ok(())
+}
+
+/// Convenience wrapper: write an FSM as KISS2 into a `String`.
+pub fn kiss_to_string(fsm: &FSM) -> String {
+    let mut out = String::new();
+    write_kiss(fsm, &mut out).expect("writing to a String never fails");
+    out
+}
+
+/// Wrap an FSM into a model called `name` with the inputs `i0`, `i1`, …
+/// and the outputs `o0`, `o1`, …, which the `.start_kiss` block refers to
+/// in order.
+pub fn fsm_model(name: &str, fsm: FSM) -> Model {
+    Model {
+        meta: ModelMeta {
+            name: name.into(),
+            inputs: Some((0..fsm.inputs).map(|i| format!("i{i}").into()).collect()),
+            outputs: Some((0..fsm.outputs).map(|i| format!("o{i}").into()).collect()),
+            clocks: vec![],
+        },
+        commands: vec![ModelCmdKind::FSM(fsm).into()],
+        attr: ModelAttr::default(),
+    }
+}
+
+/// Read a standalone KISS2 file as a model called `name`, see
+/// [`parse_kiss`] and [`fsm_model`].
+///
+/// # Errors
+///
+/// See [`parse_kiss`].
+pub fn parse_kiss_to_ast(name: &str, source: &str) -> Result<Blif, KissParseError> {
+    let mut blif = Blif::new();
+    blif.entries
+        .push(BlifEntry::Model(fsm_model(name, parse_kiss(source)?)));
+    Ok(blif)
+}
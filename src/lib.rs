@@ -43,7 +43,24 @@
 use smallvec::SmallVec;
 use std::{iter::Peekable, str::FromStr};
 
+pub mod aiger;
 pub mod ast;
+pub mod bench;
+pub mod cst;
+pub mod cube;
+pub mod flatten;
+pub mod hierarchy;
+pub mod kiss;
+pub mod lut;
+pub mod minimize;
+pub mod names;
+pub mod netlist;
+pub mod pla;
+pub mod strash;
+pub mod sweep;
+pub mod topo;
+pub mod validate;
+pub mod verilog;
 pub mod writer;
 
 /// A fixed-capacity, inline-or-heap string used throughout the crate.
@@ -63,6 +80,23 @@ pub struct GateMeta {
     pub output: Str<16>,
     /// Whether this gate is part of an `.exdc` (external don't-care) block.
     pub external_dc: bool,
+    /// The command that declared this gate in the source.
+    pub syntax: LutSyntax,
+}
+
+/// The command a logic gate was declared with.
+///
+/// All three describe the same single-output truth table; this only exists so
+/// that writers can keep the dialect of the input.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum LutSyntax {
+    /// `.names <in>* <out>`
+    #[default]
+    Names,
+    /// SIS `.cover <nin> <nout> <nterms>`, followed by the signal names.
+    Cover,
+    /// BLIF-MV `.table <in>* -> <out>`
+    Table,
 }
 
 /// Consumer for the truth-table rows of a single gate.
@@ -277,6 +311,9 @@ pub trait CommandConsumer {
     fn mv(&mut self, variables: Vec<Str<16>>, nvalues: usize, value_names: Vec<String>) {
         let _ = (variables, nvalues, value_names);
     }
+
+    /// ABC / Yosys: `.blackbox`, the model has no implementation.
+    fn blackbox(&mut self) {}
 }
 
 /// Metadata for a `.model` declaration.
@@ -779,16 +816,17 @@ fn tokenize(src: &str) -> Vec<&str> {
 
     for x in iter {
         match (x.1, first, ind) {
-            ('(', _, _) => {
-                if ind == 0 {
-                    first = Some(x.0);
-                }
+            // only a `(` at the start of a token opens a group, so that names
+            // like `388GAT(133)` stay intact
+            ('(', None, _) => {
+                first = Some(x.0);
+                ind = 1;
+            }
+            ('(', Some(_), 1..) => {
                 ind += 1;
             }
-            (')', Some(f), _) => {
-                if ind > 0 {
-                    ind -= 1;
-                }
+            (')', Some(f), 1..) => {
+                ind -= 1;
                 if ind == 0 {
                     out.push(&src[f..=x.0]);
                     first = None;
@@ -837,6 +875,10 @@ fn test_tokenize_parens() {
             "works"
         ],
     );
+    assert_eq!(
+        tokenize(".names 290GAT(100) 388GAT(133)"),
+        vec![".names", "290GAT(100)", "388GAT(133)"],
+    );
 }
 
 /// Parse the contents of a single `.model` ... `.end` block.
@@ -862,7 +904,8 @@ fn parse_mod(
             let mut args = line.split(' ');
             let _cmd = args.next().ok_or(BlifParserError::Invalid)?;
 
-            Some(args.map(|x| x.into()).collect())
+            // continuation lines may be indented
+            Some(args.filter(|x| !x.is_empty()).map(|x| x.into()).collect())
         } else {
             None
         }
@@ -876,7 +919,8 @@ fn parse_mod(
             let mut args = line.split(' ');
             let _cmd = args.next().ok_or(BlifParserError::Invalid)?;
 
-            Some(args.map(|x| x.into()).collect())
+            // continuation lines may be indented
+            Some(args.filter(|x| !x.is_empty()).map(|x| x.into()).collect())
         } else {
             None
         }
@@ -890,7 +934,7 @@ fn parse_mod(
             let mut args = line.split(' ');
             let _cmd = args.next().ok_or(BlifParserError::Invalid)?;
 
-            args.map(|x| x.into()).collect()
+            args.filter(|x| !x.is_empty()).map(|x| x.into()).collect()
         } else {
             vec![]
         }
@@ -933,17 +977,22 @@ fn parse_mod(
                     inputs,
                     output,
                     external_dc: extdc,
+                    syntax: LutSyntax::Names,
                 });
 
                 while {
                     parse_padding(lines);
-                    lines.peek().is_some_and(|x| !x.as_ref().starts_with("."))
+                    lines
+                        .peek()
+                        .is_some_and(|x| !x.as_ref().trim_start().starts_with("."))
                 } {
                     let l = next_stmt(lines)?.unwrap();
-                    let l = l.as_ref();
+                    let l = l.as_ref().trim();
 
+                    // rows may be indented and aligned
                     let (l, r) = if l.contains(' ') {
-                        l.split_once(' ').unwrap()
+                        let (l, r) = l.split_once(' ').unwrap();
+                        (l, r.trim_start())
                     } else {
                         ("", l)
                     };
@@ -1812,6 +1861,7 @@ fn parse_mod(
                 if args.next().is_some() {
                     Err(BlifParserError::TooManyArgs)?
                 }
+                consumer.blackbox();
             }
 
             // BLIF-MV: .short <in> <out> — buffer (equivalent to .conn)
@@ -2007,17 +2057,22 @@ fn parse_mod(
                         inputs,
                         output,
                         external_dc: extdc,
+                        syntax: LutSyntax::Table,
                     });
 
                     while {
                         parse_padding(lines);
-                        lines.peek().is_some_and(|x| !x.as_ref().starts_with("."))
+                        lines
+                            .peek()
+                            .is_some_and(|x| !x.as_ref().trim_start().starts_with("."))
                     } {
                         let l = next_stmt(lines)?.unwrap();
-                        let l = l.as_ref();
+                        let l = l.as_ref().trim();
 
+                        // rows may be indented and aligned
                         let (l, r) = if l.contains(' ') {
-                            l.split_once(' ').unwrap()
+                            let (l, r) = l.split_once(' ').unwrap();
+                            (l, r.trim_start())
                         } else {
                             ("", l)
                         };
@@ -2080,17 +2135,22 @@ fn parse_mod(
                     inputs,
                     output,
                     external_dc: extdc,
+                    syntax: LutSyntax::Cover,
                 });
 
                 while {
                     parse_padding(lines);
-                    lines.peek().is_some_and(|x| !x.as_ref().starts_with("."))
+                    lines
+                        .peek()
+                        .is_some_and(|x| !x.as_ref().trim_start().starts_with("."))
                 } {
                     let l = next_stmt(lines)?.unwrap();
-                    let l = l.as_ref();
+                    let l = l.as_ref().trim();
 
+                    // rows may be indented and aligned
                     let (l, r) = if l.contains(' ') {
-                        l.split_once(' ').unwrap()
+                        let (l, r) = l.split_once(' ').unwrap();
+                        (l, r.trim_start())
                     } else {
                         ("", l)
                     };
@@ -2137,15 +2197,31 @@ pub fn parse_blif(
     file_name: &str,
     consumer: &mut impl ModelConsumer,
     lines: impl IntoIterator<Item = impl AsRef<str>>,
+) -> Result<(), BlifParserError> {
+    parse_blif_from(file_name, consumer, lines, true)
+}
+
+/// Run the top-level parser loop.
+///
+/// `first` tells whether the lines start at the beginning of the file, which
+/// is the only place where a model without a `.model` line may appear. This
+/// lets [`ast::parse_blif_parallel`] parse the chunks returned by
+/// [`scan_model_boundaries`] exactly like the sequential parser would.
+fn parse_blif_from(
+    file_name: &str,
+    consumer: &mut impl ModelConsumer,
+    lines: impl IntoIterator<Item = impl AsRef<str>>,
+    first: bool,
 ) -> Result<(), BlifParserError> {
     let mut lines = lines.into_iter().peekable();
-    let mut first = true;
+    let mut first = first;
 
     while {
         parse_padding(&mut lines);
         lines.peek().is_some()
     } {
-        if is_kw(&mut lines, ".model") || !first {
+        // `.search` before the first model does not start an unnamed model
+        if is_kw(&mut lines, ".model") || is_kw(&mut lines, ".search") || !first {
             let line = next_stmt(&mut lines)?.unwrap();
             let line = line.as_ref();
             let mut args = line.split(' ');
@@ -2160,6 +2236,7 @@ pub fn parse_blif(
                     }
 
                     consumer.search(path);
+                    continue;
                 }
 
                 ".model" => {
@@ -2181,5 +2258,75 @@ pub fn parse_blif(
     Ok(())
 }
 
+/// Split the lines of a BLIF file at model boundaries, without parsing them.
+///
+/// Every returned range of line indices covers exactly what one iteration
+/// of the top-level loop of [`parse_blif`] consumes: either one model
+/// (from `.model`, or from the start of the file for an unnamed model, up
+/// to and including its `.end`) or one top-level statement like `.search`.
+/// Padding before a chunk belongs to that chunk, and trailing padding
+/// belongs to the last one.
+///
+/// Chunks that start with `.search` do not end the start of the file: a
+/// chunk directly following only such chunks may still be an unnamed model.
+///
+/// The scan is purely lexical, so chunks of malformed input do not
+/// necessarily parse to the same error as the whole file would.
+pub fn scan_model_boundaries<S: AsRef<str>>(lines: &[S]) -> Vec<std::ops::Range<usize>> {
+    scan_chunks(lines)
+        .into_iter()
+        .map(|(range, _)| range)
+        .collect()
+}
+
+/// [`scan_model_boundaries`], together with the `first` flag of the
+/// top-level parser loop at the start of every chunk.
+fn scan_chunks<S: AsRef<str>>(lines: &[S]) -> Vec<(std::ops::Range<usize>, bool)> {
+    let total = lines.len();
+    let mut lines = lines.iter().map(|x| x.as_ref()).peekable();
+    let mut out: Vec<(std::ops::Range<usize>, bool)> = vec![];
+    let mut first = true;
+
+    loop {
+        let start = total - lines.len();
+
+        parse_padding(&mut lines);
+        if lines.peek().is_none() {
+            if let Some((last, _)) = out.last_mut() {
+                last.end = total;
+            }
+            break;
+        }
+
+        let is_search = is_kw(&mut lines, ".search");
+        let is_model = if is_kw(&mut lines, ".model") || is_search || !first {
+            match next_stmt(&mut lines) {
+                Ok(Some(line)) => line.as_ref().split(' ').next() == Some(".model"),
+                _ => false,
+            }
+        } else {
+            true
+        };
+
+        if is_model {
+            while {
+                parse_padding(&mut lines);
+                lines.peek().is_some()
+            } {
+                match next_stmt(&mut lines) {
+                    Ok(Some(line)) if line.as_ref().trim() == ".end" => break,
+                    Ok(_) => {}
+                    Err(_) => break,
+                }
+            }
+        }
+
+        out.push((start..total - lines.len(), first));
+        first &= is_search;
+    }
+
+    out
+}
+
 #[cfg(test)]
 mod tests;
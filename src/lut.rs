@@ -0,0 +1,384 @@
+//! Evaluation of [`ast::LUT`](crate::ast::LUT) truth tables.
+//!
+//! The rows of a `.names` block are a cover: a row with output `1` is a cube
+//! of the on-set, a row with output `0` a cube of the off-set. A
+//! [`TruthTable`] is the expanded form, with one bit per input assignment:
+//!
+//! ```rust
+//! use turbo_blif::ast::parse_str_blif_to_ast;
+//! use turbo_blif::ast::{LUT, ModelCmdKind};
+//!
+//! let blif = parse_str_blif_to_ast(
+//!     "top.blif",
+//!     ".model top\n.inputs a b\n.outputs y\n.names a b y\n11 1\n.end\n",
+//! )
+//! .unwrap();
+//! let ModelCmdKind::Gate(gate) = &blif.model("top").unwrap().commands[0].kind else {
+//!     panic!()
+//! };
+//!
+//! assert!(!gate.lut.eval(&[false, true]));
+//! let tt = gate.lut.truth_table(2).unwrap();
+//! assert_eq!(tt.words(), &[0b1000]);
+//! assert_eq!(LUT::from_truth_table(&tt), gate.lut);
+//! ```
+
+use std::fmt;
+
+use smallvec::SmallVec;
+
+use crate::Tristate;
+use crate::ast::LUT;
+
+/// The most inputs a [`TruthTable`] can have.
+pub const MAX_INPUTS: usize = 16;
+
+/// A completely specified boolean function of up to [`MAX_INPUTS`] inputs,
+/// stored as one bit per input assignment.
+///
+/// Bit `m` is the value for the assignment where input `i` is
+/// `(m >> i) & 1`, so the first input is the least significant one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TruthTable {
+    inputs: usize,
+    /// Bits beyond `2^inputs` are always zero.
+    words: Vec<u64>,
+}
+
+impl TruthTable {
+    /// The constant 0 function of `inputs` inputs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `inputs` is larger than [`MAX_INPUTS`].
+    pub fn new(inputs: usize) -> Self {
+        assert!(inputs <= MAX_INPUTS, "too many inputs for a truth table");
+        Self {
+            inputs,
+            words: vec![0; (1usize << inputs).div_ceil(64)],
+        }
+    }
+
+    /// The function with the value `f(m)` for every assignment `m`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `inputs` is larger than [`MAX_INPUTS`].
+    pub fn from_fn(inputs: usize, mut f: impl FnMut(usize) -> bool) -> Self {
+        let mut tt = Self::new(inputs);
+        for m in 0..tt.assignments() {
+            if f(m) {
+                tt.set(m, true);
+            }
+        }
+        tt
+    }
+
+    /// The projection onto input `var`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `inputs` is larger than [`MAX_INPUTS`], or `var` is not
+    /// smaller than `inputs`.
+    pub fn var(inputs: usize, var: usize) -> Self {
+        assert!(var < inputs, "variable out of range");
+        Self::from_fn(inputs, |m| m >> var & 1 == 1)
+    }
+
+    /// Number of inputs.
+    pub fn inputs(&self) -> usize {
+        self.inputs
+    }
+
+    /// Number of input assignments, `2^inputs`.
+    pub fn assignments(&self) -> usize {
+        1 << self.inputs
+    }
+
+    /// The packed bits, 64 assignments per word.
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// The value for the assignment `m`.
+    pub fn get(&self, m: usize) -> bool {
+        self.words[m / 64] >> (m % 64) & 1 == 1
+    }
+
+    /// Set the value for the assignment `m`.
+    pub fn set(&mut self, m: usize, value: bool) {
+        let bit = 1 << (m % 64);
+        if value {
+            self.words[m / 64] |= bit;
+        } else {
+            self.words[m / 64] &= !bit;
+        }
+    }
+
+    /// Number of assignments for which the function is 1.
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// The value of the function, if it is constant.
+    pub fn constant(&self) -> Option<bool> {
+        match self.count_ones() {
+            0 => Some(false),
+            n if n == self.assignments() => Some(true),
+            _ => None,
+        }
+    }
+
+    /// Whether the value of the function depends on input `var`.
+    pub fn depends_on(&self, var: usize) -> bool {
+        let bit = 1 << var;
+        (0..self.assignments())
+            .filter(|m| m & bit == 0)
+            .any(|m| self.get(m) != self.get(m | bit))
+    }
+
+    fn mask_unused(&mut self) {
+        if self.inputs < 6 {
+            self.words[0] &= (1 << self.assignments()) - 1;
+        }
+    }
+}
+
+impl std::ops::Not for TruthTable {
+    type Output = TruthTable;
+
+    fn not(mut self) -> TruthTable {
+        self.words.iter_mut().for_each(|w| *w = !*w);
+        self.mask_unused();
+        self
+    }
+}
+
+/// Why a [`LUT`] can not be expanded to a [`TruthTable`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TruthTableError {
+    /// The LUT has more than [`MAX_INPUTS`] inputs.
+    TooManyInputs(usize),
+    /// A row does not have one value per input.
+    RowWidth {
+        /// Number of inputs.
+        expected: usize,
+        /// Number of values in the row.
+        found: usize,
+    },
+}
+
+impl fmt::Display for TruthTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TruthTableError::TooManyInputs(n) => write!(
+                f,
+                "{n} inputs are too many for a truth table, at most {MAX_INPUTS} are supported"
+            ),
+            TruthTableError::RowWidth { expected, found } => write!(
+                f,
+                "truth-table row has {found} input values, but the gate has {expected} inputs"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TruthTableError {}
+
+/// Whether the row matches the assignment `m`.
+fn row_matches_bits(row: &[Tristate], m: usize) -> bool {
+    row.iter().enumerate().all(|(i, v)| match v {
+        Tristate::False => m >> i & 1 == 0,
+        Tristate::True => m >> i & 1 == 1,
+        Tristate::Ignored => true,
+    })
+}
+
+impl LUT {
+    /// Whether the rows are an off-set cover, i.e. some row has the output
+    /// `0`.
+    fn is_off_set(&self) -> bool {
+        self.0.iter().any(|(_, out)| *out == Some(false))
+    }
+
+    /// The value of the output for the given input values.
+    ///
+    /// If any row has the output `0`, the rows describe the off-set: the
+    /// output is 0 if a row matches, and 1 otherwise. Else they describe the
+    /// on-set, and the output is 1 if a row with the output `1` matches. A
+    /// LUT without rows is therefore constant 0. Rows with a don't-care
+    /// output are ignored.
+    ///
+    /// Rows must have one value per input; extra values are ignored.
+    pub fn eval(&self, inputs: &[bool]) -> bool {
+        let off = self.is_off_set();
+        let matches = self.0.iter().any(|(row, out)| {
+            *out == Some(!off)
+                && row.iter().zip(inputs).all(|(v, x)| match v {
+                    Tristate::False => !x,
+                    Tristate::True => *x,
+                    Tristate::Ignored => true,
+                })
+        });
+        matches != off
+    }
+
+    /// The truth table of the LUT of a gate with `inputs` inputs, with the
+    /// same semantics as [`eval`](Self::eval).
+    ///
+    /// # Errors
+    ///
+    /// Fails if there are more than [`MAX_INPUTS`] inputs, or a row does not
+    /// have `inputs` values.
+    pub fn truth_table(&self, inputs: usize) -> Result<TruthTable, TruthTableError> {
+        if inputs > MAX_INPUTS {
+            return Err(TruthTableError::TooManyInputs(inputs));
+        }
+        if let Some((row, _)) = self.0.iter().find(|(row, _)| row.len() != inputs) {
+            return Err(TruthTableError::RowWidth {
+                expected: inputs,
+                found: row.len(),
+            });
+        }
+
+        let off = self.is_off_set();
+        let rows: Vec<&[Tristate]> = self
+            .0
+            .iter()
+            .filter(|(_, out)| *out == Some(!off))
+            .map(|(row, _)| &row[..])
+            .collect();
+        Ok(TruthTable::from_fn(inputs, |m| {
+            rows.iter().any(|row| row_matches_bits(row, m)) != off
+        }))
+    }
+
+    /// The number of inputs, taken from the width of the rows.
+    ///
+    /// `None` if there are no rows, because then it can't be known.
+    fn row_width(&self) -> Option<usize> {
+        self.0.first().map(|(row, _)| row.len())
+    }
+
+    fn own_truth_table(&self) -> Option<TruthTable> {
+        self.truth_table(self.row_width()?).ok()
+    }
+
+    /// A cover of `tt` with prime cubes and no redundant rows.
+    ///
+    /// The on-set is used if it needs at most as many rows as the off-set,
+    /// and the off-set otherwise. Constant 0 has no rows. Every cube is
+    /// expanded greedily, so the cover is small, but not necessarily the
+    /// smallest possible one.
+    pub fn from_truth_table(tt: &TruthTable) -> LUT {
+        if tt.constant() == Some(false) {
+            return LUT(vec![]);
+        }
+        let on = prime_cover(tt);
+        let off = prime_cover(&!tt.clone());
+        let (cover, value) = if on.len() <= off.len() || off.is_empty() {
+            (on, true)
+        } else {
+            (off, false)
+        };
+        LUT(cover.into_iter().map(|row| (row, Some(value))).collect())
+    }
+
+    /// The output value, if it does not depend on the inputs.
+    ///
+    /// Returns `None` for LUTs that are not constant, or whose truth table
+    /// can't be computed.
+    pub fn is_constant(&self) -> Option<bool> {
+        if self.0.is_empty() {
+            return Some(false);
+        }
+        self.own_truth_table()?.constant()
+    }
+
+    /// The input that the output is a copy of, if the LUT is a buffer.
+    pub fn is_buffer(&self) -> Option<usize> {
+        self.single_input(false)
+    }
+
+    /// The input that the output is the inverse of, if the LUT is an
+    /// inverter.
+    pub fn is_inverter(&self) -> Option<usize> {
+        self.single_input(true)
+    }
+
+    fn single_input(&self, inverted: bool) -> Option<usize> {
+        let tt = self.own_truth_table()?;
+        let var = (0..tt.inputs()).find(|&v| tt.depends_on(v))?;
+        let expected = TruthTable::var(tt.inputs(), var);
+        let expected = if inverted { !expected } else { expected };
+        (tt == expected).then_some(var)
+    }
+}
+
+/// The minterms of the cube with the values `values` for the inputs in the
+/// mask `care`.
+fn cube_minterms(care: usize, values: usize, inputs: usize) -> impl Iterator<Item = usize> {
+    let free = ((1 << inputs) - 1) & !care;
+    // enumerate the subsets of `free`, ending with `free` itself
+    let mut next = Some(0usize);
+    std::iter::from_fn(move || {
+        let sub = next?;
+        next = (sub != free).then(|| (sub.wrapping_sub(free)) & free);
+        Some(values | sub)
+    })
+}
+
+/// Prime cubes covering the on-set of `tt`, without redundant ones.
+fn prime_cover(tt: &TruthTable) -> Vec<SmallVec<[Tristate; 8]>> {
+    let n = tt.inputs();
+    // a cube as (care mask, values)
+    let mut cubes: Vec<(usize, usize)> = vec![];
+    let mut done = TruthTable::new(n);
+    for m in 0..tt.assignments() {
+        if !tt.get(m) || done.get(m) {
+            continue;
+        }
+        let mut care = tt.assignments() - 1;
+        for var in 0..n {
+            let bit = 1 << var;
+            if cube_minterms(care & !bit, m & care & !bit, n).all(|x| tt.get(x)) {
+                care &= !bit;
+            }
+        }
+        let values = m & care;
+        for x in cube_minterms(care, values, n) {
+            done.set(x, true);
+        }
+        cubes.push((care, values));
+    }
+
+    // drop cubes whose minterms are all covered by other cubes
+    let mut idx = 0;
+    while idx < cubes.len() {
+        let (care, values) = cubes[idx];
+        let redundant = cube_minterms(care, values, n).all(|m| {
+            cubes
+                .iter()
+                .enumerate()
+                .any(|(j, (c, v))| j != idx && m & c == *v)
+        });
+        if redundant {
+            cubes.remove(idx);
+        } else {
+            idx += 1;
+        }
+    }
+
+    cubes
+        .into_iter()
+        .map(|(care, values)| {
+            (0..n)
+                .map(|var| match (care >> var & 1, values >> var & 1) {
+                    (0, _) => Tristate::Ignored,
+                    (_, 0) => Tristate::False,
+                    _ => Tristate::True,
+                })
+                .collect()
+        })
+        .collect()
+}
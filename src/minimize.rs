@@ -0,0 +1,757 @@
+//! Two-level minimization of `.names` covers.
+//!
+//! [`minimize_cover`] implements the expand, irredundant and reduce loop of
+//! Espresso on the [`cube`](crate::cube) types, and an exact Quine-McCluskey
+//! minimization for gates with few inputs. [`minimize_model`] rewrites all
+//! gates of a model, using the `.exdc` network of the model as don't-cares:
+//!
+//! ```rust
+//! use turbo_blif::ast::{BlifEntry, ModelCmdKind, parse_str_blif_to_ast};
+//! use turbo_blif::minimize::{MinimizeMode, minimize_model};
+//!
+//! let mut blif = parse_str_blif_to_ast(
+//!     "top.blif",
+//!     ".model top\n.inputs a b\n.outputs y\n.names a b y\n10 1\n11 1\n01 1\n.end\n",
+//! )
+//! .unwrap();
+//! let BlifEntry::Model(model) = &mut blif.entries[0];
+//! let stats = minimize_model(model, MinimizeMode::Heuristic);
+//! assert_eq!((stats.cubes_before, stats.cubes_after), (3, 1));
+//! assert_eq!((stats.literals_before, stats.literals_after), (6, 2));
+//!
+//! // the off-set is smaller
+//! let ModelCmdKind::Gate(gate) = &model.commands[0].kind else { panic!() };
+//! assert_eq!(gate.lut.to_string(), "00 0\n");
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::ast::{Gate, LUT, Model, ModelCmdKind};
+use crate::cube::{Cover, Cube, compose};
+use crate::{GateMeta, Str, Tristate};
+
+/// Gates with more inputs than this are minimized heuristically, even in
+/// [`MinimizeMode::Exact`].
+pub const EXACT_MAX_INPUTS: usize = 10;
+
+/// The covering step of [`MinimizeMode::Exact`] gives up after this many
+/// branches.
+pub const EXACT_MAX_NODES: usize = 2_000;
+
+/// How to minimize.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MinimizeMode {
+    /// Espresso-style expand, irredundant and reduce. Fast, and usually
+    /// close to the minimum.
+    #[default]
+    Heuristic,
+    /// Quine-McCluskey with an exact covering step, for gates with at most
+    /// [`EXACT_MAX_INPUTS`] inputs. Finds a cover with the fewest cubes, and
+    /// among those the fewest literals.
+    ///
+    /// Proving that a cover is minimal can take exponential time, so the
+    /// search stops after [`EXACT_MAX_NODES`] branches and takes the best
+    /// cover found until then. That cover is never larger than the
+    /// heuristic one.
+    Exact,
+}
+
+/// Sizes of the gates of a model before and after [`minimize_model`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MinimizeStats {
+    /// Number of `.names` that were considered.
+    pub gates: usize,
+    /// Number of `.names` that were rewritten.
+    pub rewritten: usize,
+    /// Number of truth-table rows before.
+    pub cubes_before: usize,
+    /// Number of truth-table rows after.
+    pub cubes_after: usize,
+    /// Number of `0` and `1` input values in the rows before.
+    pub literals_before: usize,
+    /// Number of `0` and `1` input values in the rows after.
+    pub literals_after: usize,
+}
+
+impl fmt::Display for MinimizeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} gates rewritten, cubes: {} -> {}, literals: {} -> {}",
+            self.rewritten,
+            self.gates,
+            self.cubes_before,
+            self.cubes_after,
+            self.literals_before,
+            self.literals_after
+        )
+    }
+}
+
+/// Number of rows and literals of a LUT.
+fn lut_cost(lut: &LUT) -> (usize, usize) {
+    let literals = lut
+        .0
+        .iter()
+        .flat_map(|(row, _)| row)
+        .filter(|v| **v != Tristate::Ignored)
+        .count();
+    (lut.0.len(), literals)
+}
+
+fn cover_cost(cover: &Cover) -> (usize, usize) {
+    (cover.len(), cover.cubes().iter().map(Cube::literals).sum())
+}
+
+/// A small cover `F` with `on ⊆ F ⊆ on ∪ dc`.
+///
+/// # Panics
+///
+/// Panics if the covers have different numbers of inputs.
+pub fn minimize_cover(on: &Cover, dc: &Cover, mode: MinimizeMode) -> Cover {
+    assert_eq!(on.vars(), dc.vars(), "covers over different inputs");
+    if mode == MinimizeMode::Exact && on.vars() <= EXACT_MAX_INPUTS {
+        exact(on, dc)
+    } else {
+        espresso(on, dc)
+    }
+}
+
+/// Minimize `gate`, taking don't-cares from `dc`, an `.exdc` gate with the
+/// same output.
+///
+/// The DC gate is only used if all of its inputs are inputs of `gate`.
+/// Both the on-set and the off-set are minimized, and the smaller one is
+/// used. Returns `None` if the gate can't be minimized, because its rows
+/// have the wrong width or a don't-care output, or if the result would not
+/// be smaller.
+pub fn minimize_gate(gate: &Gate, dc: Option<&Gate>, mode: MinimizeMode) -> Option<Gate> {
+    let inputs = gate.meta.inputs.len();
+    let rows_ok = |lut: &LUT, width: usize| {
+        lut.0
+            .iter()
+            .all(|(row, out)| row.len() == width && out.is_some())
+    };
+    if inputs == 0 || !rows_ok(&gate.lut, inputs) {
+        return None;
+    }
+
+    let dc = dc
+        .filter(|dc| rows_ok(&dc.lut, dc.meta.inputs.len()))
+        .and_then(|dc| {
+            // positions of the DC inputs among the gate inputs
+            let pos: Option<Vec<usize>> = dc
+                .meta
+                .inputs
+                .iter()
+                .map(|x| gate.meta.inputs.iter().position(|y| y == x))
+                .collect();
+            let pos = pos?;
+            Some(embed(&Cover::on_set(&dc.lut, pos.len()), &pos, inputs))
+        })
+        .unwrap_or_else(|| Cover::new(inputs));
+
+    let on = Cover::on_set(&gate.lut, inputs);
+    let off = on.union(&dc).complement();
+    let on = minimize_cover(&on, &dc, mode);
+    let off = minimize_cover(&off, &dc, mode);
+    let (cover, value) = if cover_cost(&on) <= cover_cost(&off) || off.is_empty() {
+        (on, true)
+    } else {
+        (off, false)
+    };
+
+    if cover_cost(&cover) >= lut_cost(&gate.lut) {
+        return None;
+    }
+    Some(Gate {
+        meta: gate.meta.clone(),
+        lut: LUT(cover
+            .cubes()
+            .iter()
+            .map(|c| (c.to_row(), Some(value)))
+            .collect()),
+    })
+}
+
+/// The cubes of `cover` with their variable `i` moved to `pos[i]`, in a
+/// cover over `vars` variables.
+fn embed(cover: &Cover, pos: &[usize], vars: usize) -> Cover {
+    let cubes = cover.cubes().iter().map(|c| {
+        let mut out = Cube::universe(vars);
+        for (i, &p) in pos.iter().enumerate() {
+            out.set(p, c.get(i));
+        }
+        out
+    });
+    Cover::from_cubes(vars, cubes)
+}
+
+/// Collapse the `.exdc` network of `model`, like
+/// [`Pla::from_model`](crate::pla::Pla::from_model) does: every `.exdc` gate
+/// becomes a gate over the nets that are not driven by earlier `.exdc`
+/// gates. Gates with malformed rows are left out, together with the gates
+/// reading them.
+fn collapse_exdc(model: &Model) -> HashMap<String, Gate> {
+    let mut collapsed: HashMap<String, Gate> = HashMap::new();
+    let mut broken: HashSet<&str> = HashSet::new();
+    for cmd in &model.commands {
+        let ModelCmdKind::Gate(gate) = &cmd.kind else {
+            continue;
+        };
+        if !gate.meta.external_dc {
+            continue;
+        }
+        let output = gate.meta.output.as_str();
+        let width = gate.meta.inputs.len();
+        let rows_ok = gate
+            .lut
+            .0
+            .iter()
+            .all(|(row, out)| row.len() == width && out.is_some());
+        if !rows_ok || gate.meta.inputs.iter().any(|x| broken.contains(x.as_str())) {
+            collapsed.remove(output);
+            broken.insert(output);
+            continue;
+        }
+        broken.remove(output);
+
+        let mut support: Vec<Str<16>> = vec![];
+        for input in &gate.meta.inputs {
+            let leaves = match collapsed.get(input.as_str()) {
+                Some(dc) => &dc.meta.inputs[..],
+                None => std::slice::from_ref(input),
+            };
+            for leaf in leaves {
+                if !support.contains(leaf) {
+                    support.push(leaf.clone());
+                }
+            }
+        }
+        let vars = support.len();
+        let position = |name: &Str<16>| support.iter().position(|x| x == name).unwrap();
+        let covers: Vec<Cover> = gate
+            .meta
+            .inputs
+            .iter()
+            .map(|input| match collapsed.get(input.as_str()) {
+                Some(dc) => {
+                    let pos: Vec<usize> = dc.meta.inputs.iter().map(position).collect();
+                    embed(&Cover::on_set(&dc.lut, pos.len()), &pos, vars)
+                }
+                None => {
+                    let mut cube = Cube::universe(vars);
+                    cube.set(position(input), Tristate::True);
+                    Cover::from_cubes(vars, [cube])
+                }
+            })
+            .collect();
+        let refs: Vec<&Cover> = covers.iter().collect();
+        let on = compose(&gate.lut, vars, &refs, &mut |i| covers[i].complement());
+
+        let meta = GateMeta {
+            inputs: support,
+            output: gate.meta.output.clone(),
+            external_dc: true,
+            syntax: gate.meta.syntax,
+        };
+        collapsed.insert(
+            output.to_string(),
+            Gate {
+                meta,
+                lut: on.to_lut(),
+            },
+        );
+    }
+    collapsed
+}
+
+/// Minimize every `.names` of `model` in place.
+///
+/// The `.exdc` network is collapsed onto the nets it reads that it doesn't
+/// drive itself, and the part for the net a gate drives provides the
+/// don't-cares of the gate, see [`minimize_gate`]. `.exdc` gates themselves
+/// are not changed. A gate is only rewritten if that makes it smaller.
+pub fn minimize_model(model: &mut Model, mode: MinimizeMode) -> MinimizeStats {
+    let dcs = collapse_exdc(model);
+
+    let mut stats = MinimizeStats::default();
+    for cmd in &mut model.commands {
+        let ModelCmdKind::Gate(gate) = &mut cmd.kind else {
+            continue;
+        };
+        if gate.meta.external_dc {
+            continue;
+        }
+
+        let (cubes, literals) = lut_cost(&gate.lut);
+        stats.gates += 1;
+        stats.cubes_before += cubes;
+        stats.literals_before += literals;
+
+        if let Some(min) = minimize_gate(gate, dcs.get(gate.meta.output.as_str()), mode) {
+            *gate = min;
+            stats.rewritten += 1;
+        }
+
+        let (cubes, literals) = lut_cost(&gate.lut);
+        stats.cubes_after += cubes;
+        stats.literals_after += literals;
+    }
+    stats
+}
+
+// ---------------------------------------------------------------------------
+// Espresso
+// ---------------------------------------------------------------------------
+
+fn espresso(on: &Cover, dc: &Cover) -> Cover {
+    if on.is_empty() {
+        return on.clone();
+    }
+    let off = on.union(dc).complement();
+    if off.is_empty() {
+        return Cover::from_cubes(on.vars(), [Cube::universe(on.vars())]);
+    }
+
+    let mut f = irredundant(&expand(on, &off), dc);
+    let mut best = f.clone();
+    loop {
+        f = irredundant(&expand(&reduce(&f, dc), &off), dc);
+        if cover_cost(&f) >= cover_cost(&best) {
+            return best;
+        }
+        best = f.clone();
+    }
+}
+
+/// Make every cube as big as possible without hitting the off-set, and drop
+/// the cubes that become contained in another one.
+fn expand(f: &Cover, off: &Cover) -> Cover {
+    let mut cubes: Vec<Cube> = f.cubes().to_vec();
+    // big cubes first, they are the most likely to swallow others
+    cubes.sort_by_key(Cube::literals);
+
+    let mut out: Vec<Cube> = vec![];
+    for cube in cubes {
+        if out.iter().any(|c| c.contains(&cube)) {
+            continue;
+        }
+        let mut cube = cube;
+        for var in 0..cube.vars() {
+            if cube.get(var) == Tristate::Ignored {
+                continue;
+            }
+            let mut raised = cube.clone();
+            raised.set(var, Tristate::Ignored);
+            if off.cubes().iter().all(|r| raised.distance(r) > 0) {
+                cube = raised;
+            }
+        }
+        out.retain(|c| !cube.contains(c));
+        out.push(cube);
+    }
+    Cover::from_cubes(f.vars(), out)
+}
+
+/// Drop cubes that are covered by the others and the don't-cares.
+fn irredundant(f: &Cover, dc: &Cover) -> Cover {
+    let mut cubes: Vec<Cube> = f.cubes().to_vec();
+    // small cubes are tried first, they are the most likely to be redundant
+    cubes.sort_by_key(|c| std::cmp::Reverse(c.literals()));
+
+    let mut idx = 0;
+    while idx < cubes.len() {
+        let rest = cubes
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != idx)
+            .map(|(_, c)| c.clone());
+        let rest = Cover::from_cubes(f.vars(), rest.collect::<Vec<_>>()).union(dc);
+        if rest.contains_cube(&cubes[idx]) {
+            cubes.remove(idx);
+        } else {
+            idx += 1;
+        }
+    }
+    Cover::from_cubes(f.vars(), cubes)
+}
+
+/// Make every cube as small as possible while the cover stays the same, so
+/// that the next expand can find different primes.
+fn reduce(f: &Cover, dc: &Cover) -> Cover {
+    let mut cubes: Vec<Cube> = f.cubes().to_vec();
+    cubes.sort_by_key(Cube::literals);
+
+    let mut idx = 0;
+    while idx < cubes.len() {
+        let rest = cubes
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != idx)
+            .map(|(_, c)| c.clone());
+        let rest = Cover::from_cubes(f.vars(), rest.collect::<Vec<_>>()).union(dc);
+        // the part of the cube that only it covers
+        let only = rest.cofactor(&cubes[idx]).complement();
+        let Some(first) = only.cubes().first() else {
+            cubes.remove(idx);
+            continue;
+        };
+        let sup = only.cubes()[1..]
+            .iter()
+            .fold(first.clone(), |acc, c| acc.supercube(c));
+        cubes[idx] = cubes[idx].intersect(&sup).unwrap();
+        idx += 1;
+    }
+    Cover::from_cubes(f.vars(), cubes)
+}
+
+// ---------------------------------------------------------------------------
+// Quine-McCluskey
+// ---------------------------------------------------------------------------
+
+/// A cube as (care mask, values), with input `i` in bit `i`.
+type Implicant = (usize, usize);
+
+fn minterms(cover: &Cover) -> Vec<usize> {
+    let n = cover.vars();
+    (0..1usize << n)
+        .filter(|m| {
+            let inputs: Vec<bool> = (0..n).map(|i| m >> i & 1 == 1).collect();
+            cover.eval(&inputs)
+        })
+        .collect()
+}
+
+fn exact(on: &Cover, dc: &Cover) -> Cover {
+    let n = on.vars();
+    let full = (1usize << n) - 1;
+    let on_minterms = minterms(on);
+    let dc_minterms: HashSet<usize> = minterms(dc).into_iter().collect();
+
+    // all prime implicants of on ∪ dc
+    let mut level: HashSet<Implicant> = on_minterms
+        .iter()
+        .chain(&dc_minterms)
+        .map(|&m| (full, m))
+        .collect();
+    let mut primes: Vec<Implicant> = vec![];
+    while !level.is_empty() {
+        let mut next = HashSet::new();
+        let mut combined = HashSet::new();
+        for &(care, values) in &level {
+            for var in 0..n {
+                let bit = 1 << var;
+                if care & bit == 0 || values & bit != 0 {
+                    continue;
+                }
+                let twin = (care, values | bit);
+                if level.contains(&twin) {
+                    next.insert((care & !bit, values));
+                    combined.insert((care, values));
+                    combined.insert(twin);
+                }
+            }
+        }
+        let mut rest: Vec<Implicant> = level.difference(&combined).copied().collect();
+        rest.sort();
+        primes.extend(rest);
+        level = next;
+    }
+
+    // the primes covering every on-set minterm
+    let todo: Vec<usize> = on_minterms
+        .into_iter()
+        .filter(|m| !dc_minterms.contains(m))
+        .collect();
+    let row_cols: Vec<Vec<usize>> = todo
+        .iter()
+        .map(|&m| {
+            (0..primes.len())
+                .filter(|&p| m & primes[p].0 == primes[p].1)
+                .collect()
+        })
+        .collect();
+    let table = Table::new(
+        primes.iter().map(|p| p.0.count_ones() as usize).collect(),
+        &row_cols,
+    );
+
+    // the heuristic cover is the bound to beat
+    let heuristic = espresso(on, dc);
+    let mut search = Search {
+        best: None,
+        cost: cover_cost(&heuristic),
+        budget: EXACT_MAX_NODES,
+    };
+    table.solve(
+        bits(todo.len(), 0..todo.len()),
+        bits(primes.len(), 0..primes.len()),
+        vec![],
+        &mut search,
+    );
+    let Some(best) = search.best else {
+        return heuristic;
+    };
+
+    let cubes = best.into_iter().map(|p| {
+        let (care, values) = primes[p];
+        let mut cube = Cube::universe(n);
+        for var in (0..n).filter(|v| care >> v & 1 == 1) {
+            let value = if values >> var & 1 == 1 {
+                Tristate::True
+            } else {
+                Tristate::False
+            };
+            cube.set(var, value);
+        }
+        cube
+    });
+    Cover::from_cubes(n, cubes)
+}
+
+/// State of the branch and bound of [`Table::solve`].
+struct Search {
+    /// The cheapest columns found so far, `None` while nothing beats the
+    /// initial bound.
+    best: Option<Vec<usize>>,
+    /// Cubes and literals to beat.
+    cost: (usize, usize),
+    /// Branches left to visit.
+    budget: usize,
+}
+
+/// A set of row or column indices of a [`Table`].
+type Bits = Vec<u64>;
+
+/// The set of `items`, out of `len` indices.
+fn bits(len: usize, items: impl IntoIterator<Item = usize>) -> Bits {
+    let mut out = vec![0; len.div_ceil(64)];
+    for i in items {
+        out[i / 64] |= 1 << (i % 64);
+    }
+    out
+}
+
+fn has(set: &[u64], i: usize) -> bool {
+    set[i / 64] >> (i % 64) & 1 == 1
+}
+
+fn unset(set: &mut [u64], i: usize) {
+    set[i / 64] &= !(1 << (i % 64));
+}
+
+/// Number of elements of `a ∩ mask`.
+fn count(a: &[u64], mask: &[u64]) -> usize {
+    a.iter()
+        .zip(mask)
+        .map(|(a, m)| (a & m).count_ones() as usize)
+        .sum()
+}
+
+/// The elements of `a ∩ mask`.
+fn members<'a>(a: &'a [u64], mask: &'a [u64]) -> impl Iterator<Item = usize> + 'a {
+    a.iter().zip(mask).enumerate().flat_map(|(word, (a, m))| {
+        let mut w = a & m;
+        std::iter::from_fn(move || {
+            (w != 0).then(|| {
+                let bit = w.trailing_zeros() as usize;
+                w &= w - 1;
+                word * 64 + bit
+            })
+        })
+    })
+}
+
+/// Whether `a ∩ mask ⊆ b`.
+fn subset(a: &[u64], b: &[u64], mask: &[u64]) -> bool {
+    a.iter().zip(b).zip(mask).all(|((a, b), m)| a & m & !b == 0)
+}
+
+/// The covering table of [`exact`]: every row is an on-set minterm, every
+/// column a prime, and the chosen columns must cover all rows.
+struct Table {
+    /// Literals of each column.
+    cost: Vec<usize>,
+    /// The columns covering each row.
+    row_cols: Vec<Bits>,
+    /// The rows covered by each column.
+    col_rows: Vec<Bits>,
+}
+
+impl Table {
+    /// A table with `cost.len()` columns, where row `i` is covered by the
+    /// columns in `rows[i]`.
+    fn new(cost: Vec<usize>, rows: &[Vec<usize>]) -> Self {
+        let mut col_rows = vec![vec![]; cost.len()];
+        for (row, cols) in rows.iter().enumerate() {
+            for &col in cols {
+                col_rows[col].push(row);
+            }
+        }
+        Self {
+            row_cols: rows
+                .iter()
+                .map(|cols| bits(cost.len(), cols.iter().copied()))
+                .collect(),
+            col_rows: col_rows
+                .into_iter()
+                .map(|list| bits(rows.len(), list))
+                .collect(),
+            cost,
+        }
+    }
+
+    /// Cubes and literals of a set of columns.
+    fn cost(&self, cols: &[usize]) -> (usize, usize) {
+        (cols.len(), cols.iter().map(|&c| self.cost[c]).sum())
+    }
+
+    /// Mark the rows covered by `col` as covered.
+    fn cover(&self, rows: &mut [u64], col: usize) {
+        for (row, covered) in rows.iter_mut().zip(&self.col_rows[col]) {
+            *row &= !covered;
+        }
+    }
+
+    /// Take essential columns and drop dominated rows and columns until
+    /// nothing changes. Returns `false` if a row can't be covered anymore.
+    fn reduce(&self, rows: &mut Bits, cols: &mut Bits, chosen: &mut Vec<usize>) -> bool {
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            // a row with a single column needs that column
+            for row in members(rows, rows).collect::<Vec<_>>() {
+                if !has(rows, row) {
+                    continue;
+                }
+                let col = match count(&self.row_cols[row], cols) {
+                    0 => return false,
+                    1 => members(&self.row_cols[row], cols).next().unwrap(),
+                    _ => continue,
+                };
+                chosen.push(col);
+                unset(cols, col);
+                self.cover(rows, col);
+                changed = true;
+            }
+
+            // covering a row covers every row with a superset of its columns
+            for row in members(rows, rows).collect::<Vec<_>>() {
+                if !has(rows, row) {
+                    continue;
+                }
+                let mine = &self.row_cols[row];
+                let Some(first) = members(mine, cols).next() else {
+                    continue;
+                };
+                let len = count(mine, cols);
+                for other in members(&self.col_rows[first], rows).collect::<Vec<_>>() {
+                    // of two equal rows, keep the first one
+                    if other == row
+                        || !subset(mine, &self.row_cols[other], cols)
+                        || (count(&self.row_cols[other], cols) == len && other < row)
+                    {
+                        continue;
+                    }
+                    unset(rows, other);
+                    changed = true;
+                }
+            }
+
+            // a column is not needed if another, no more expensive one
+            // covers all of its rows
+            for col in members(cols, cols).collect::<Vec<_>>() {
+                let mine = &self.col_rows[col];
+                let Some(first) = members(mine, rows).next() else {
+                    unset(cols, col);
+                    changed = true;
+                    continue;
+                };
+                let len = count(mine, rows);
+                let dominated = members(&self.row_cols[first], cols).any(|other| {
+                    // of two equal columns, keep the first one
+                    let equal = self.cost[other] == self.cost[col]
+                        && count(&self.col_rows[other], rows) == len;
+                    other != col
+                        && self.cost[other] <= self.cost[col]
+                        && subset(mine, &self.col_rows[other], rows)
+                        && (!equal || other < col)
+                });
+                if dominated {
+                    unset(cols, col);
+                    changed = true;
+                }
+            }
+        }
+        true
+    }
+
+    /// A lower bound of the cost of covering the remaining rows: rows that
+    /// share no column each need their own one.
+    fn lower_bound(&self, rows: &[u64], cols: &[u64]) -> (usize, usize) {
+        let mut order: Vec<(usize, usize)> = members(rows, rows)
+            .map(|r| (count(&self.row_cols[r], cols), r))
+            .collect();
+        order.sort();
+        let mut used = vec![0; cols.len()];
+        let (mut cubes, mut literals) = (0, 0);
+        for (_, row) in order {
+            let mine = &self.row_cols[row];
+            if count(mine, &used) > 0 {
+                continue;
+            }
+            cubes += 1;
+            literals += members(mine, cols).map(|c| self.cost[c]).min().unwrap_or(0);
+            for (used, (mine, col)) in used.iter_mut().zip(mine.iter().zip(cols)) {
+                *used |= mine & col;
+            }
+        }
+        (cubes, literals)
+    }
+
+    /// Branch and bound on the columns of the row with the fewest of them.
+    fn solve(&self, mut rows: Bits, mut cols: Bits, mut chosen: Vec<usize>, search: &mut Search) {
+        if search.budget == 0 {
+            return;
+        }
+        search.budget -= 1;
+        if !self.reduce(&mut rows, &mut cols, &mut chosen) {
+            return;
+        }
+
+        let (cubes, literals) = self.cost(&chosen);
+        let (more_cubes, more_literals) = self.lower_bound(&rows, &cols);
+        let bound = (cubes + more_cubes, literals + more_literals);
+        if bound >= search.cost {
+            return;
+        }
+
+        let pick = members(&rows, &rows).min_by_key(|&r| count(&self.row_cols[r], &cols));
+        let Some(pick) = pick else {
+            search.cost = (cubes, literals);
+            search.best = Some(chosen);
+            return;
+        };
+
+        let mut branches: Vec<usize> = members(&self.row_cols[pick], &cols).collect();
+        // columns covering more rows first, to find a good bound early
+        branches.sort_by_key(|&c| {
+            (
+                std::cmp::Reverse(count(&self.col_rows[c], &rows)),
+                self.cost[c],
+            )
+        });
+        for col in branches {
+            let mut rows = rows.clone();
+            self.cover(&mut rows, col);
+            // covers with the columns of earlier branches were tried there
+            unset(&mut cols, col);
+            let mut chosen = chosen.clone();
+            chosen.push(col);
+            self.solve(rows, cols.clone(), chosen, search);
+        }
+    }
+}
@@ -0,0 +1,380 @@
+//! Escaping of signal and model names that can not be written as-is.
+//!
+//! BLIF has no quoting: names are separated by spaces, `#` starts a comment,
+//! `=` separates formal and actual pins, a trailing `\` continues the line,
+//! and so on. A name containing any of those produces a file that reads back
+//! differently, or not at all. Yosys works around this by replacing `#`, `=`,
+//! `<` and `>` with `?` (see syntax.md §5).
+//!
+//! The writer applies a [`NameEscaping`] policy through
+//! [`WriteOptions::name_escaping`](crate::writer::WriteOptions::name_escaping),
+//! and [`validate_names`] reports the names of an AST that the chosen flavor
+//! can not represent.
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::ast::{Blif, BlifEntry, CellAttrAst, Model, ModelCmdKind};
+use crate::writer::BlifFlavor;
+use crate::{ModelDelayConstraint, ModelMeta};
+
+/// What the writer does with names that can not be written as-is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NameEscaping {
+    /// Write every name unchanged, even if that produces an unparsable file.
+    #[default]
+    Verbatim,
+    /// Replace every offending character with `?`, like Yosys does.
+    ///
+    /// Not reversible.
+    Yosys,
+    /// Replace every offending character with `_`, like VTR does.
+    ///
+    /// Not reversible.
+    Vtr,
+    /// Fail writing with [`fmt::Error`] on the first unrepresentable name.
+    ///
+    /// Use [`validate_names`] to find out which names are the problem.
+    Error,
+    /// Reversible `%XX` escaping, see [`mangle_name`] and [`demangle_name`].
+    Mangle,
+}
+
+/// Where a name is used, because some characters are only a problem in some
+/// places.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NameKind {
+    /// A net, pin, instance or library cell name.
+    Net,
+    /// The name of a model, which can not contain the `|` that separates it
+    /// from the instance name in `.subckt`.
+    Model,
+}
+
+/// Why a name can not be written as-is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NameProblem {
+    /// The name is empty.
+    Empty,
+    /// The name contains a character that has a meaning in BLIF.
+    Char(char),
+    /// The name ends with `\`, which continues the line.
+    TrailingBackslash,
+    /// The name is a keyword of the flavor, like `->` in BLIF-MV tables.
+    Reserved,
+}
+
+impl fmt::Display for NameProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameProblem::Empty => write!(f, "is empty"),
+            NameProblem::Char(c) => write!(f, "contains {c:?}"),
+            NameProblem::TrailingBackslash => write!(f, "ends with a line continuation"),
+            NameProblem::Reserved => write!(f, "is reserved"),
+        }
+    }
+}
+
+impl std::error::Error for NameProblem {}
+
+/// A name that can not be written in some flavor, found by [`validate_names`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidName {
+    /// The model that the name is used in.
+    pub model: String,
+    /// The offending name.
+    pub name: String,
+    /// Where the name is used.
+    pub kind: NameKind,
+    /// Why it can not be written.
+    pub problem: NameProblem,
+}
+
+impl fmt::Display for InvalidName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "model `{}`: name `{}` {}",
+            self.model, self.name, self.problem
+        )
+    }
+}
+
+impl std::error::Error for InvalidName {}
+
+/// `first` is whether `c` is the first character of the name, because a `(`
+/// there starts a parenthesized group.
+fn is_bad_char(c: char, first: bool, kind: NameKind, flavor: BlifFlavor) -> bool {
+    c.is_whitespace()
+        || matches!(c, '#' | '=')
+        || (first && c == '(')
+        || (kind == NameKind::Model && c == '|')
+        || (flavor == BlifFlavor::Yosys && matches!(c, '<' | '>'))
+}
+
+/// Check whether `name` can be written as-is in `flavor`.
+pub fn check_name(name: &str, kind: NameKind, flavor: BlifFlavor) -> Result<(), NameProblem> {
+    if name.is_empty() {
+        return Err(NameProblem::Empty);
+    }
+    if let Some((_, c)) = name
+        .char_indices()
+        .find(|&(i, c)| is_bad_char(c, i == 0, kind, flavor))
+    {
+        return Err(NameProblem::Char(c));
+    }
+    if name.ends_with('\\') {
+        return Err(NameProblem::TrailingBackslash);
+    }
+    if flavor == BlifFlavor::SisMV && name == "->" {
+        return Err(NameProblem::Reserved);
+    }
+    Ok(())
+}
+
+/// Apply `escaping` to `name`.
+///
+/// Names that can be written as-is are returned unchanged, except with
+/// [`NameEscaping::Mangle`], which always escapes `%`.
+pub fn escape_name(
+    name: &str,
+    kind: NameKind,
+    escaping: NameEscaping,
+    flavor: BlifFlavor,
+) -> Result<Cow<'_, str>, NameProblem> {
+    let replace = match escaping {
+        NameEscaping::Verbatim => return Ok(Cow::Borrowed(name)),
+        NameEscaping::Mangle => return Ok(mangle_name(name, kind, flavor)),
+        NameEscaping::Error => {
+            return check_name(name, kind, flavor).map(|_| Cow::Borrowed(name));
+        }
+        NameEscaping::Yosys => '?',
+        NameEscaping::Vtr => '_',
+    };
+
+    Ok(match check_name(name, kind, flavor) {
+        Ok(()) => Cow::Borrowed(name),
+        Err(NameProblem::Empty) => Cow::Owned(replace.to_string()),
+        Err(NameProblem::Reserved) => Cow::Owned(format!("{replace}>")),
+        Err(_) => {
+            let mut out: String = name
+                .char_indices()
+                .map(|(i, c)| {
+                    if is_bad_char(c, i == 0, kind, flavor) {
+                        replace
+                    } else {
+                        c
+                    }
+                })
+                .collect();
+            if out.ends_with('\\') {
+                out.pop();
+                out.push(replace);
+            }
+            Cow::Owned(out)
+        }
+    })
+}
+
+/// Reversibly escape `name`, so that it can be written in `flavor`.
+///
+/// Offending characters and `%` are replaced with `%` followed by the two hex
+/// digits of each of their UTF-8 bytes, and the empty name becomes `%`. Names
+/// without either are returned unchanged.
+pub fn mangle_name(name: &str, kind: NameKind, flavor: BlifFlavor) -> Cow<'_, str> {
+    if name.is_empty() {
+        return Cow::Borrowed("%");
+    }
+    if !name.contains('%') && check_name(name, kind, flavor).is_ok() {
+        return Cow::Borrowed(name);
+    }
+
+    let reserved = flavor == BlifFlavor::SisMV && name == "->";
+    let last = name.char_indices().last().map_or(0, |(i, _)| i);
+    let mut out = String::with_capacity(name.len() + 8);
+    for (i, c) in name.char_indices() {
+        let escape = c == '%'
+            || is_bad_char(c, i == 0, kind, flavor)
+            || (c == '\\' && i == last)
+            || (reserved && i == 0);
+        if escape {
+            let mut buf = [0; 4];
+            for b in c.encode_utf8(&mut buf).bytes() {
+                out.push_str(&format!("%{b:02X}"));
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Undo [`mangle_name`].
+///
+/// Returns `None` if `name` is not a valid mangled name.
+pub fn demangle_name(name: &str) -> Option<Cow<'_, str>> {
+    if name == "%" {
+        return Some(Cow::Borrowed(""));
+    }
+    if !name.contains('%') {
+        return Some(Cow::Borrowed(name));
+    }
+
+    let mut out = Vec::with_capacity(name.len());
+    let mut bytes = name.bytes();
+    while let Some(b) = bytes.next() {
+        if b == b'%' {
+            let hi = (bytes.next()? as char).to_digit(16)?;
+            let lo = (bytes.next()? as char).to_digit(16)?;
+            out.push((hi * 16 + lo) as u8);
+        } else {
+            out.push(b);
+        }
+    }
+    String::from_utf8(out).ok().map(Cow::Owned)
+}
+
+/// Report every name in `blif` that can not be written as-is in `flavor`.
+///
+/// Each name is reported once per model.
+pub fn validate_names(blif: &Blif, flavor: BlifFlavor) -> Vec<InvalidName> {
+    let mut out = vec![];
+    for entry in &blif.entries {
+        match entry {
+            BlifEntry::Model(model) => {
+                let mut seen = HashSet::new();
+                for_each_name(model, &mut |name, kind| {
+                    if let Err(problem) = check_name(name, kind, flavor)
+                        && seen.insert((name.to_string(), kind))
+                    {
+                        out.push(InvalidName {
+                            model: model.meta.name.to_string(),
+                            name: name.to_string(),
+                            kind,
+                            problem,
+                        });
+                    }
+                });
+            }
+        }
+    }
+    out
+}
+
+/// Call `f` on every name of the model header.
+fn for_each_meta_name(meta: &ModelMeta, f: &mut impl FnMut(&str, NameKind)) {
+    f(&meta.name, NameKind::Model);
+    let ports = meta.inputs.iter().chain(&meta.outputs).flatten();
+    for name in ports.chain(&meta.clocks) {
+        f(name, NameKind::Net);
+    }
+}
+
+/// Call `f` on every name that the writer writes for `model`, in the same
+/// places where it applies [`NameEscaping`].
+fn for_each_name(model: &Model, f: &mut impl FnMut(&str, NameKind)) {
+    for_each_meta_name(&model.meta, f);
+
+    for cmd in &model.commands {
+        let mut net = |name: &str| f(name, NameKind::Net);
+        match &cmd.kind {
+            ModelCmdKind::Gate(gate) => {
+                gate.meta.inputs.iter().for_each(|x| net(x));
+                net(&gate.meta.output);
+            }
+            ModelCmdKind::FF(ff) => {
+                net(&ff.input);
+                net(&ff.output);
+                ff.clock.iter().for_each(|x| net(x));
+            }
+            ModelCmdKind::LibGate(lg) => {
+                net(&lg.name);
+                lg.maps.iter().for_each(|(a, b)| {
+                    net(a);
+                    net(b)
+                });
+            }
+            ModelCmdKind::LibFF(lf) => {
+                net(&lf.name);
+                lf.maps.iter().for_each(|(a, b)| {
+                    net(a);
+                    net(b)
+                });
+                lf.clock.iter().for_each(|x| net(x));
+            }
+            ModelCmdKind::FSM(fsm) => {
+                fsm.physical_latch_order
+                    .iter()
+                    .flatten()
+                    .for_each(|x| net(x));
+            }
+            ModelCmdKind::SubModel {
+                name,
+                map,
+                instance_name,
+            } => {
+                map.iter().for_each(|(a, b)| {
+                    net(a);
+                    net(b)
+                });
+                instance_name.iter().for_each(|x| net(x));
+                f(name, NameKind::Model);
+            }
+            ModelCmdKind::Connect { from, to } => {
+                net(from);
+                net(to);
+            }
+            ModelCmdKind::ClockEvents(ev) => {
+                ev.events.iter().for_each(|x| net(&x.clock_name));
+            }
+            ModelCmdKind::DelayConstraint(dc) => for_each_delay_name(dc, &mut net),
+            ModelCmdKind::Constraint(signals) | ModelCmdKind::OneHot(signals) => {
+                signals.iter().for_each(|x| net(x));
+            }
+            ModelCmdKind::Reset { signal, .. } | ModelCmdKind::GateInit { signal, .. } => {
+                net(signal);
+            }
+            ModelCmdKind::Mv { variables, .. } => variables.iter().for_each(|x| net(x)),
+            ModelCmdKind::CycleTime(_) | ModelCmdKind::LtlFormula(_) | ModelCmdKind::Spec(_) => {}
+        }
+
+        for attr in &cmd.attrs {
+            if let CellAttrAst::CellName(name) = attr {
+                f(name, NameKind::Net);
+            }
+        }
+    }
+}
+
+fn for_each_delay_name(dc: &ModelDelayConstraint, f: &mut impl FnMut(&str)) {
+    match dc {
+        ModelDelayConstraint::Input(idc) => f(&idc.input),
+        ModelDelayConstraint::InputArrivalTime(sat)
+        | ModelDelayConstraint::OutputRequiredTime(sat) => {
+            f(&sat.signal);
+            if let Some(rel) = &sat.event_relative {
+                f(&rel.event);
+            }
+        }
+        ModelDelayConstraint::InputDrive(sd) => f(&sd.signal),
+        ModelDelayConstraint::MaxInputLoad(sl)
+        | ModelDelayConstraint::OutputLoad(sl)
+        | ModelDelayConstraint::InputRequired(sl)
+        | ModelDelayConstraint::OutputArrival(sl) => f(&sl.signal),
+        ModelDelayConstraint::DelayPerPair {
+            in_sig, out_sig, ..
+        } => {
+            f(in_sig);
+            f(out_sig);
+        }
+        ModelDelayConstraint::WireLoadSlope(_)
+        | ModelDelayConstraint::WireLoads(_)
+        | ModelDelayConstraint::DefaultInputArrivalTime(_)
+        | ModelDelayConstraint::DefaultOutputRequiredTime(_)
+        | ModelDelayConstraint::DefaultInputDrive(_)
+        | ModelDelayConstraint::DefaultMaxInputLoad(_)
+        | ModelDelayConstraint::DefaultOutputLoad(_)
+        | ModelDelayConstraint::AndGateDelay(_) => {}
+    }
+}
@@ -0,0 +1,464 @@
+//! Connectivity view of an [`ast::Model`](crate::ast::Model).
+//!
+//! A [`Netlist`] assigns every net of a model a [`NetId`], and records which
+//! pins drive it and which pins read it:
+//!
+//! ```rust
+//! use turbo_blif::ast::parse_str_blif_to_ast;
+//! use turbo_blif::netlist::{Driver, Netlist};
+//!
+//! let blif = parse_str_blif_to_ast(
+//!     "top.blif",
+//!     ".model top\n.inputs a b\n.outputs y\n.names a b t\n11 1\n.latch t y re clk 0\n.end\n",
+//! )
+//! .unwrap();
+//! let model = blif.model("top").unwrap();
+//! let netlist = Netlist::new(model);
+//!
+//! let t = netlist.net_id("t").unwrap();
+//! let Some(Driver::Pin(pin)) = netlist.net(t).driver() else { panic!() };
+//! assert_eq!(netlist.cell(pin.cell).cmd_index, 0);
+//! assert_eq!(netlist.net(t).fanout.len(), 1);
+//! ```
+//!
+//! Names connected with `.conn`, `.barbuf` or `.short` are aliases of the
+//! same net. Gates inside of `.exdc` blocks describe a don't-care network and
+//! are not part of the netlist.
+
+use std::collections::HashMap;
+
+use crate::Str;
+use crate::ast::{Blif, Model, ModelCmd, ModelCmdKind};
+
+/// Index of a net in a [`Netlist`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NetId(pub usize);
+
+/// Index of a cell in a [`Netlist`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CellId(pub usize);
+
+/// Direction of a cell pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PinDir {
+    /// The cell reads the net.
+    Input,
+    /// The cell drives the net.
+    Output,
+    /// The direction is not known, because the referenced model is missing
+    /// or does not declare the pin.
+    Unknown,
+}
+
+/// A pin of a cell, identified by the cell and the index into
+/// [`Cell::pins`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PinRef {
+    /// The cell.
+    pub cell: CellId,
+    /// Index into [`Cell::pins`].
+    pub pin: usize,
+}
+
+/// A connection between a cell and a net.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pin<'a> {
+    /// The formal pin name of `.gate`, `.mlatch` and `.subckt` pins.
+    pub formal: Option<&'a str>,
+    /// The connected net.
+    pub net: NetId,
+    /// Direction of the pin.
+    pub dir: PinDir,
+}
+
+/// A logic gate, latch, library cell or sub-circuit instance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell<'a> {
+    /// Index of the command in [`Model::commands`].
+    pub cmd_index: usize,
+    /// The command.
+    pub cmd: &'a ModelCmd,
+    /// Pins of the cell, in the order in which they appear in the command.
+    ///
+    /// For `.names`, these are the inputs followed by the output. For
+    /// latches, the input, the output and then the clock, if any.
+    pub pins: Vec<Pin<'a>>,
+}
+
+/// Something that drives a net.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Driver {
+    /// The net is a primary input (`.inputs` or `.clock`) of the model.
+    Input,
+    /// An output pin of a cell.
+    Pin(PinRef),
+}
+
+/// A net, and everything connected to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Net<'a> {
+    /// All names of the net, in order of their first appearance in the model.
+    ///
+    /// There is more than one if the net is aliased with `.conn`.
+    pub names: Vec<&'a str>,
+    /// Everything that drives the net.
+    ///
+    /// A well-formed net has exactly one driver.
+    pub drivers: Vec<Driver>,
+    /// Input pins reading the net.
+    pub fanout: Vec<PinRef>,
+    /// Pins of unknown direction connected to the net.
+    pub unknown: Vec<PinRef>,
+    /// Whether the net is a primary output (`.outputs`) of the model.
+    pub is_output: bool,
+}
+
+impl<'a> Net<'a> {
+    /// The first name of the net.
+    pub fn name(&self) -> &'a str {
+        self.names[0]
+    }
+
+    /// The driver of the net, or the first one if there are several.
+    pub fn driver(&self) -> Option<&Driver> {
+        self.drivers.first()
+    }
+}
+
+/// Connectivity view of a model.
+#[derive(Debug, Clone)]
+pub struct Netlist<'a> {
+    model: &'a Model,
+    nets: Vec<Net<'a>>,
+    by_name: HashMap<&'a str, NetId>,
+    cells: Vec<Cell<'a>>,
+    inputs: Vec<NetId>,
+    outputs: Vec<NetId>,
+}
+
+impl<'a> Netlist<'a> {
+    /// Build the netlist of a model on its own.
+    ///
+    /// All `.subckt` pins have an unknown direction, and the last pin of a
+    /// `.gate` or `.mlatch` is its output, like the spec requires.
+    pub fn new(model: &'a Model) -> Self {
+        Self::build(model, None)
+    }
+
+    /// Build the netlist of a model, taking the directions of `.gate`,
+    /// `.mlatch` and `.subckt` pins from the `.inputs` and `.outputs` of the
+    /// referenced models in `blif`.
+    pub fn in_blif(model: &'a Model, blif: &'a Blif) -> Self {
+        Self::build(model, Some(blif))
+    }
+
+    fn build(model: &'a Model, blif: Option<&'a Blif>) -> Self {
+        // the first model of a name wins, like in `Blif::model`
+        let mut models: HashMap<&str, &Model> = HashMap::new();
+        for m in blif.into_iter().flat_map(|blif| blif.models()) {
+            models.entry(m.meta.name.as_str()).or_insert(m);
+        }
+
+        // 1. every name in order of appearance
+        let mut names = Names::default();
+        let meta = &model.meta;
+        let ports = meta.inputs.iter().chain(&meta.outputs).flatten();
+        for name in ports.chain(&meta.clocks) {
+            names.intern(name);
+        }
+        let mut aliases = vec![];
+        for cmd in cell_cmds(model) {
+            for_each_pin(&cmd.kind, |_, name, _| {
+                names.intern(name);
+            });
+            if let ModelCmdKind::Connect { from, to } = &cmd.kind {
+                aliases.push((names.intern(from), names.intern(to)));
+            }
+        }
+
+        // 2. aliases share a net
+        let mut parent: Vec<usize> = (0..names.list.len()).collect();
+        for (a, b) in aliases {
+            let (a, b) = (find(&mut parent, a), find(&mut parent, b));
+            parent[a.max(b)] = a.min(b);
+        }
+
+        let mut nets: Vec<Net> = vec![];
+        let mut net_of_root = HashMap::new();
+        let mut by_name = HashMap::new();
+        for (idx, &name) in names.list.iter().enumerate() {
+            let root = find(&mut parent, idx);
+            let id = *net_of_root.entry(root).or_insert_with(|| {
+                nets.push(Net {
+                    names: vec![],
+                    drivers: vec![],
+                    fanout: vec![],
+                    unknown: vec![],
+                    is_output: false,
+                });
+                NetId(nets.len() - 1)
+            });
+            nets[id.0].names.push(name);
+            by_name.insert(name, id);
+        }
+
+        // 3. ports
+        let mut inputs = vec![];
+        for name in meta.inputs.iter().flatten().chain(&meta.clocks) {
+            let id = by_name[name.as_str()];
+            if !nets[id.0].drivers.contains(&Driver::Input) {
+                nets[id.0].drivers.push(Driver::Input);
+                inputs.push(id);
+            }
+        }
+        let mut outputs = vec![];
+        for name in meta.outputs.iter().flatten() {
+            let id = by_name[name.as_str()];
+            nets[id.0].is_output = true;
+            outputs.push(id);
+        }
+
+        // 4. cells
+        let mut cells = vec![];
+        for (cmd_index, cmd) in model.commands.iter().enumerate() {
+            if !is_cell(cmd) {
+                continue;
+            }
+            let cell = CellId(cells.len());
+            let target = match &cmd.kind {
+                ModelCmdKind::LibGate(lg) => models.get(lg.name.as_str()),
+                ModelCmdKind::LibFF(lf) => models.get(lf.name.as_str()),
+                ModelCmdKind::SubModel { name, .. } => models.get(name.as_str()),
+                _ => None,
+            };
+
+            let mut pins = vec![];
+            for_each_pin(&cmd.kind, |formal, name, dir| {
+                let dir = match (formal, target) {
+                    (Some(formal), Some(target)) => port_dir(target, formal),
+                    _ => dir,
+                };
+                let net = by_name[name];
+                let pin = PinRef {
+                    cell,
+                    pin: pins.len(),
+                };
+                match dir {
+                    PinDir::Input => nets[net.0].fanout.push(pin),
+                    PinDir::Output => nets[net.0].drivers.push(Driver::Pin(pin)),
+                    PinDir::Unknown => nets[net.0].unknown.push(pin),
+                }
+                pins.push(Pin { formal, net, dir });
+            });
+
+            cells.push(Cell {
+                cmd_index,
+                cmd,
+                pins,
+            });
+        }
+
+        Self {
+            model,
+            nets,
+            by_name,
+            cells,
+            inputs,
+            outputs,
+        }
+    }
+
+    /// The model that this netlist was built from.
+    pub fn model(&self) -> &'a Model {
+        self.model
+    }
+
+    /// All nets, in order of their first appearance in the model.
+    pub fn nets(&self) -> impl Iterator<Item = (NetId, &Net<'a>)> {
+        self.nets.iter().enumerate().map(|(i, net)| (NetId(i), net))
+    }
+
+    /// The net with the given id.
+    pub fn net(&self, id: NetId) -> &Net<'a> {
+        &self.nets[id.0]
+    }
+
+    /// The net that `name` belongs to.
+    pub fn net_id(&self, name: &str) -> Option<NetId> {
+        self.by_name.get(name).copied()
+    }
+
+    /// Number of nets.
+    pub fn net_count(&self) -> usize {
+        self.nets.len()
+    }
+
+    /// All cells, in the order of their commands.
+    pub fn cells(&self) -> &[Cell<'a>] {
+        &self.cells
+    }
+
+    /// The cell with the given id.
+    pub fn cell(&self, id: CellId) -> &Cell<'a> {
+        &self.cells[id.0]
+    }
+
+    /// The pin that `pin` refers to.
+    pub fn pin(&self, pin: PinRef) -> &Pin<'a> {
+        &self.cells[pin.cell.0].pins[pin.pin]
+    }
+
+    /// Primary inputs, from `.inputs` followed by `.clock`, without
+    /// duplicates.
+    pub fn inputs(&self) -> &[NetId] {
+        &self.inputs
+    }
+
+    /// Primary outputs, from `.outputs`.
+    pub fn outputs(&self) -> &[NetId] {
+        &self.outputs
+    }
+}
+
+/// The ports of a model: its `.inputs` and `.outputs`, or the inferred ones
+/// if they are omitted.
+///
+/// Like syntax.md §5.3 describes, inferred inputs are the nets that nothing
+/// drives, and inferred outputs are the nets that are driven by a cell but not
+/// read by any other one. Both are in order of the first appearance of the
+/// net. Nets connected to `.subckt` pins of unknown direction are never
+/// inferred as ports, so pass `blif` to resolve the directions of those pins
+/// from the instantiated models.
+pub fn model_ports(model: &Model, blif: Option<&Blif>) -> (Vec<Str<16>>, Vec<Str<16>>) {
+    let netlist = Netlist::build(model, blif);
+    let unconnected = |net: &Net| net.unknown.is_empty();
+
+    let inputs = match &model.meta.inputs {
+        Some(inputs) => inputs.clone(),
+        None => netlist
+            .nets()
+            .filter(|(_, net)| net.drivers.is_empty() && unconnected(net))
+            .map(|(_, net)| net.name().into())
+            .collect(),
+    };
+
+    let outputs = match &model.meta.outputs {
+        Some(outputs) => outputs.clone(),
+        None => netlist
+            .nets()
+            .filter(|(_, net)| {
+                net.fanout.is_empty()
+                    && unconnected(net)
+                    && net.drivers.iter().any(|x| matches!(x, Driver::Pin(_)))
+            })
+            // the last alias is the one furthest downstream of a `.conn` chain
+            .map(|(_, net)| (*net.names.last().unwrap()).into())
+            .collect(),
+    };
+
+    (inputs, outputs)
+}
+
+#[derive(Default)]
+struct Names<'a> {
+    list: Vec<&'a str>,
+    ids: HashMap<&'a str, usize>,
+}
+
+impl<'a> Names<'a> {
+    fn intern(&mut self, name: &'a str) -> usize {
+        *self.ids.entry(name).or_insert_with(|| {
+            self.list.push(name);
+            self.list.len() - 1
+        })
+    }
+}
+
+fn find(parent: &mut [usize], mut x: usize) -> usize {
+    while parent[x] != x {
+        parent[x] = parent[parent[x]];
+        x = parent[x];
+    }
+    x
+}
+
+/// Whether `cmd` instantiates something that connects to nets.
+fn is_cell(cmd: &ModelCmd) -> bool {
+    match &cmd.kind {
+        ModelCmdKind::Gate(gate) => !gate.meta.external_dc,
+        ModelCmdKind::FF(_)
+        | ModelCmdKind::LibGate(_)
+        | ModelCmdKind::LibFF(_)
+        | ModelCmdKind::SubModel { .. } => true,
+        _ => false,
+    }
+}
+
+/// The commands that contribute nets: cells and aliases.
+fn cell_cmds(model: &Model) -> impl Iterator<Item = &ModelCmd> {
+    model
+        .commands
+        .iter()
+        .filter(|cmd| is_cell(cmd) || matches!(cmd.kind, ModelCmdKind::Connect { .. }))
+}
+
+/// Direction of the port `formal` of `model`.
+fn port_dir(model: &Model, formal: &str) -> PinDir {
+    let has = |ports: &Option<Vec<crate::Str<16>>>| ports.iter().flatten().any(|x| x == formal);
+    if has(&model.meta.inputs) || model.meta.clocks.iter().any(|x| x == formal) {
+        PinDir::Input
+    } else if has(&model.meta.outputs) {
+        PinDir::Output
+    } else {
+        PinDir::Unknown
+    }
+}
+
+/// Call `f` with the formal name, the net and the default direction of
+/// every pin of a cell, in the order of [`Cell::pins`].
+fn for_each_pin<'a>(kind: &'a ModelCmdKind, mut f: impl FnMut(Option<&'a str>, &'a str, PinDir)) {
+    match kind {
+        ModelCmdKind::Gate(gate) => {
+            for input in &gate.meta.inputs {
+                f(None, input, PinDir::Input);
+            }
+            f(None, &gate.meta.output, PinDir::Output);
+        }
+        ModelCmdKind::FF(ff) => {
+            f(None, &ff.input, PinDir::Input);
+            f(None, &ff.output, PinDir::Output);
+            if let Some(clock) = &ff.clock {
+                f(None, clock, PinDir::Input);
+            }
+        }
+        ModelCmdKind::LibGate(lg) => {
+            for (idx, (formal, actual)) in lg.maps.iter().enumerate() {
+                let dir = if idx + 1 == lg.maps.len() {
+                    PinDir::Output
+                } else {
+                    PinDir::Input
+                };
+                f(Some(formal), actual, dir);
+            }
+        }
+        ModelCmdKind::LibFF(lf) => {
+            for (idx, (formal, actual)) in lf.maps.iter().enumerate() {
+                let dir = if idx + 1 == lf.maps.len() {
+                    PinDir::Output
+                } else {
+                    PinDir::Input
+                };
+                f(Some(formal), actual, dir);
+            }
+            if let Some(clock) = &lf.clock {
+                f(None, clock, PinDir::Input);
+            }
+        }
+        ModelCmdKind::SubModel { map, .. } => {
+            for (formal, actual) in map {
+                f(Some(formal), actual, PinDir::Unknown);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,619 @@
+//! Reading and writing the Espresso PLA format.
+//!
+//! A PLA describes a multi-output function in two-level form: every row is
+//! an input cube followed by one character per output. [`Pla::from_model`]
+//! collapses a combinational model into a [`Pla`], [`write_pla`] writes it,
+//! [`parse_pla`] reads one, and [`Pla::to_model`] turns it back into a
+//! model:
+//!
+//! ```rust
+//! use turbo_blif::pla::{PlaType, parse_pla, pla_to_string};
+//!
+//! let source = ".i 2\n.o 2\n.ilb a b\n.ob x y\n.p 3\n11 10\n0- 01\n-0 0-\n.e\n";
+//! let pla = parse_pla(source).unwrap();
+//! assert_eq!(pla.ty, PlaType::Fd);
+//! assert_eq!(pla.outputs[1].dc.len(), 1);
+//!
+//! let model = pla.to_model("top");
+//! assert_eq!(model.commands.len(), 3);
+//!
+//! assert_eq!(
+//!     pla_to_string(&pla),
+//!     ".i 2\n.o 2\n.ilb a b\n.ob x y\n.type fd\n.p 3\n11 10\n0- 01\n-0 0-\n.e\n"
+//! );
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::ast::{Blif, BlifEntry, Gate, LUT, Model, ModelAttr, ModelCmdKind};
+use crate::cube::{Cover, Cube, compose};
+use crate::netlist::{NetId, Netlist};
+use crate::topo::{TopoError, topo_order_cells};
+use crate::{GateMeta, ModelMeta, Str, Tristate};
+
+/// Which sets of every output the rows of a PLA describe, from `.type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PlaType {
+    /// The on-set; everything else is the off-set.
+    F,
+    /// The on-set and the don't-care set. This is the default of Espresso.
+    #[default]
+    Fd,
+    /// The on-set and the off-set; everything else is don't care.
+    Fr,
+    /// The on-set, the don't-care set and the off-set.
+    Fdr,
+}
+
+impl PlaType {
+    /// Whether rows can put cubes into the don't-care set.
+    pub fn has_dc(self) -> bool {
+        matches!(self, PlaType::Fd | PlaType::Fdr)
+    }
+
+    /// Whether rows can put cubes into the off-set.
+    pub fn has_off(self) -> bool {
+        matches!(self, PlaType::Fr | PlaType::Fdr)
+    }
+
+    /// The argument of `.type`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PlaType::F => "f",
+            PlaType::Fd => "fd",
+            PlaType::Fr => "fr",
+            PlaType::Fdr => "fdr",
+        }
+    }
+}
+
+/// An output of a [`Pla`]. All covers are over the inputs of the PLA.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlaOutput {
+    /// Name from `.ob`.
+    pub name: String,
+    /// The cubes where the output is 1.
+    pub on: Cover,
+    /// Only written if [`PlaType::has_dc`].
+    pub dc: Cover,
+    /// Only written if [`PlaType::has_off`].
+    pub off: Cover,
+}
+
+/// A multi-output function in two-level form.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pla {
+    /// Names from `.ilb`.
+    pub inputs: Vec<String>,
+    /// One per output, in the order of `.ob`.
+    pub outputs: Vec<PlaOutput>,
+    pub ty: PlaType,
+}
+
+/// Why a model can not be converted to a [`Pla`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PlaError {
+    /// A command that is not combinational logic.
+    Unsupported {
+        /// The command, like `.latch` or `.subckt`.
+        command: &'static str,
+        /// Its cell, model or output.
+        name: String,
+    },
+    /// A net that is read, but neither an input nor driven by a gate. For
+    /// `.exdc` gates, only inputs and earlier `.exdc` gates count.
+    Undriven {
+        /// The name of the net.
+        net: String,
+    },
+    /// A net that is driven more than once.
+    MultipleDrivers {
+        /// The name of the net.
+        net: String,
+    },
+    /// The logic has a combinational loop.
+    Loop(TopoError),
+}
+
+impl fmt::Display for PlaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlaError::Unsupported { command, name } => {
+                write!(f, "`{command} {name}` can not be collapsed into a PLA")
+            }
+            PlaError::Undriven { net } => write!(f, "net `{net}` is never driven"),
+            PlaError::MultipleDrivers { net } => write!(f, "net `{net}` has multiple drivers"),
+            PlaError::Loop(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for PlaError {}
+
+impl From<TopoError> for PlaError {
+    fn from(err: TopoError) -> Self {
+        PlaError::Loop(err)
+    }
+}
+
+impl Pla {
+    /// Collapse a flat, combinational model into two-level form: every
+    /// output becomes a cover over the primary inputs. The gates of the
+    /// `.exdc` network with the name of an output become its don't-care set.
+    ///
+    /// The covers are only freed of contained cubes, not minimized; see
+    /// [`minimize_cover`](crate::minimize::minimize_cover). The off-set is
+    /// only computed if `ty` has one. Collapsing can take exponential time
+    /// and space for functions like multipliers.
+    ///
+    /// # Errors
+    ///
+    /// Fails for latches, `.gate`, `.mlatch`, `.subckt` and FSMs, for nets
+    /// without or with multiple drivers, and for combinational loops.
+    pub fn from_model(model: &Model, ty: PlaType) -> Result<Pla, PlaError> {
+        for cmd in &model.commands {
+            let (command, name) = match &cmd.kind {
+                ModelCmdKind::FF(ff) => (".latch", ff.output.to_string()),
+                ModelCmdKind::LibGate(lg) => (".gate", lg.name.to_string()),
+                ModelCmdKind::LibFF(lf) => (".mlatch", lf.name.to_string()),
+                ModelCmdKind::SubModel { name, .. } => (".subckt", name.to_string()),
+                ModelCmdKind::FSM(_) => (".start_kiss", model.meta.name.to_string()),
+                _ => continue,
+            };
+            return Err(PlaError::Unsupported { command, name });
+        }
+
+        let netlist = Netlist::new(model);
+        if let Some((_, net)) = netlist.nets().find(|(_, net)| net.drivers.len() > 1) {
+            return Err(PlaError::MultipleDrivers {
+                net: net.name().to_string(),
+            });
+        }
+        let order = topo_order_cells(&netlist)?;
+
+        let vars = netlist.inputs().len();
+        let var_cover = |var: usize| {
+            let mut cube = Cube::universe(vars);
+            cube.set(var, Tristate::True);
+            Cover::from_cubes(vars, [cube])
+        };
+        let mut covers: Vec<Option<Cover>> = vec![None; netlist.net_count()];
+        let mut complements: Vec<Option<Cover>> = vec![None; netlist.net_count()];
+        let mut input_vars: HashMap<&str, usize> = HashMap::new();
+        for (var, &net) in netlist.inputs().iter().enumerate() {
+            covers[net.0] = Some(var_cover(var));
+            input_vars.insert(netlist.net(net).name(), var);
+        }
+
+        let undriven = |net: NetId| PlaError::Undriven {
+            net: netlist.net(net).name().to_string(),
+        };
+        for &id in &order {
+            let cell = netlist.cell(id);
+            let ModelCmdKind::Gate(gate) = &cell.cmd.kind else {
+                continue;
+            };
+            let (output, inputs) = cell.pins.split_last().unwrap();
+            let nets: Vec<NetId> = inputs.iter().map(|pin| pin.net).collect();
+            if let Some(&net) = nets.iter().find(|net| covers[net.0].is_none()) {
+                return Err(undriven(net));
+            }
+            let inputs: Vec<&Cover> = nets
+                .iter()
+                .map(|net| covers[net.0].as_ref().unwrap())
+                .collect();
+            let mut complement = |i: usize| {
+                let net = nets[i];
+                complements[net.0]
+                    .get_or_insert_with(|| covers[net.0].as_ref().unwrap().complement())
+                    .clone()
+            };
+            let on = compose(&gate.lut, vars, &inputs, &mut complement);
+            covers[output.net.0] = Some(on);
+        }
+
+        let mut exdc: HashMap<&str, Cover> = HashMap::new();
+        for cmd in &model.commands {
+            let ModelCmdKind::Gate(gate) = &cmd.kind else {
+                continue;
+            };
+            if !gate.meta.external_dc {
+                continue;
+            }
+            let inputs = gate
+                .meta
+                .inputs
+                .iter()
+                .map(
+                    |name| match (exdc.get(name.as_str()), input_vars.get(name.as_str())) {
+                        (Some(cover), _) => Ok(cover.clone()),
+                        (None, Some(&var)) => Ok(var_cover(var)),
+                        (None, None) => Err(PlaError::Undriven {
+                            net: name.to_string(),
+                        }),
+                    },
+                )
+                .collect::<Result<Vec<Cover>, _>>()?;
+            let refs: Vec<&Cover> = inputs.iter().collect();
+            let on = compose(&gate.lut, vars, &refs, &mut |i| inputs[i].complement());
+            exdc.insert(gate.meta.output.as_str(), on);
+        }
+
+        let mut outputs = vec![];
+        for &net in netlist.outputs() {
+            let name = netlist.net(net).name();
+            let on = covers[net.0].clone().ok_or_else(|| undriven(net))?;
+            let dc = exdc.remove(name).unwrap_or_else(|| Cover::new(vars));
+            let off = if ty.has_off() {
+                on.union(&dc).complement()
+            } else {
+                Cover::new(vars)
+            };
+            outputs.push(PlaOutput {
+                name: name.to_string(),
+                on,
+                dc,
+                off,
+            });
+        }
+
+        Ok(Pla {
+            inputs: netlist
+                .inputs()
+                .iter()
+                .map(|&net| netlist.net(net).name().to_string())
+                .collect(),
+            outputs,
+            ty,
+        })
+    }
+
+    /// Turn the PLA into a model with one `.names` per output, over the
+    /// inputs its cubes depend on.
+    ///
+    /// The gate has the on-set rows, or the off-set rows if there are only
+    /// those. Don't-care rows become an `.exdc` gate of the same name. If
+    /// the type has an off-set, the assignments that are in no set are not
+    /// added to the `.exdc` network, and are 0 or 1 like the gate says.
+    pub fn to_model(&self, name: &str) -> Model {
+        let mut commands = vec![];
+        for output in &self.outputs {
+            let (cover, value) = if output.on.is_empty() && !output.off.is_empty() {
+                (&output.off, false)
+            } else {
+                (&output.on, true)
+            };
+            commands.push(self.names(cover, value, &output.name, false).into());
+            if !output.dc.is_empty() {
+                commands.push(self.names(&output.dc, true, &output.name, true).into());
+            }
+        }
+
+        Model {
+            meta: ModelMeta {
+                name: name.into(),
+                inputs: Some(self.inputs.iter().map(|x| x.as_str().into()).collect()),
+                outputs: Some(
+                    self.outputs
+                        .iter()
+                        .map(|x| x.name.as_str().into())
+                        .collect(),
+                ),
+                clocks: vec![],
+            },
+            commands,
+            attr: ModelAttr::default(),
+        }
+    }
+
+    /// A `.names` with the rows of `cover` and the output `value`, over the
+    /// inputs that are not don't care in some cube.
+    fn names(&self, cover: &Cover, value: bool, output: &str, external_dc: bool) -> ModelCmdKind {
+        let support: Vec<usize> = (0..self.inputs.len())
+            .filter(|&var| {
+                cover
+                    .cubes()
+                    .iter()
+                    .any(|cube| !matches!(cube.get(var), Tristate::Ignored))
+            })
+            .collect();
+        let rows = cover
+            .cubes()
+            .iter()
+            .map(|cube| {
+                let row = support.iter().map(|&var| cube.get(var)).collect();
+                (row, Some(value))
+            })
+            .collect();
+        ModelCmdKind::Gate(Gate {
+            meta: GateMeta {
+                inputs: support
+                    .iter()
+                    .map(|&var| self.inputs[var].as_str().into())
+                    .collect(),
+                output: Str::from(output),
+                external_dc,
+                syntax: Default::default(),
+            },
+            lut: LUT(rows),
+        })
+    }
+}
+
+/// Write a PLA with `.i`, `.o`, `.ilb`, `.ob`, `.type` and `.p`.
+///
+/// Every cube is written once, with the character of every output that has
+/// it: `1` for the on-set, `-` for the don't-care set and `0` for the
+/// off-set. For the types without an off-set, outputs that do not have the
+/// cube get a `0`, otherwise a `~`. A cube that is in two sets of the same
+/// output is written in two rows.
+pub fn write_pla<W: fmt::Write>(pla: &Pla, w: &mut W) -> fmt::Result {
+    let none = if pla.ty.has_off() { '~' } else { '0' };
+    let mut rows: Vec<(&Cube, Vec<char>)> = vec![];
+    let mut index: HashMap<&Cube, usize> = HashMap::new();
+    for (i, output) in pla.outputs.iter().enumerate() {
+        let dc: &[Cube] = if pla.ty.has_dc() {
+            output.dc.cubes()
+        } else {
+            &[]
+        };
+        let off: &[Cube] = if pla.ty.has_off() {
+            output.off.cubes()
+        } else {
+            &[]
+        };
+        let sets = [(output.on.cubes(), '1'), (dc, '-'), (off, '0')];
+        for (cubes, value) in sets {
+            for cube in cubes {
+                match index.get(cube) {
+                    Some(&row) if rows[row].1[i] == none => rows[row].1[i] = value,
+                    _ => {
+                        let mut outputs = vec![none; pla.outputs.len()];
+                        outputs[i] = value;
+                        index.insert(cube, rows.len());
+                        rows.push((cube, outputs));
+                    }
+                }
+            }
+        }
+    }
+
+    writeln!(w, ".i {}", pla.inputs.len())?;
+    writeln!(w, ".o {}", pla.outputs.len())?;
+    if !pla.inputs.is_empty() {
+        writeln!(w, ".ilb {}", pla.inputs.join(" "))?;
+    }
+    if !pla.outputs.is_empty() {
+        let names: Vec<&str> = pla.outputs.iter().map(|x| x.name.as_str()).collect();
+        writeln!(w, ".ob {}", names.join(" "))?;
+    }
+    writeln!(w, ".type {}", pla.ty.as_str())?;
+    writeln!(w, ".p {}", rows.len())?;
+    for (cube, outputs) in rows {
+        let outputs: String = outputs.into_iter().collect();
+        writeln!(w, "{cube} {outputs}")?;
+    }
+    writeln!(w, ".e")
+}
+
+/// Convenience wrapper: write a PLA into a `String`.
+pub fn pla_to_string(pla: &Pla) -> String {
+    let mut out = String::new();
+    write_pla(pla, &mut out).expect("writing to a String never fails");
+    out
+}
+
+/// Why a PLA can not be read.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PlaParseError {
+    /// A malformed line.
+    Invalid {
+        /// The line, starting at 1.
+        line: usize,
+    },
+    /// A keyword that is not supported, like `.mv` or `.phase`.
+    Unsupported {
+        /// The line, starting at 1.
+        line: usize,
+        keyword: String,
+    },
+    /// A keyword or row that needs `.i` or `.o` came before it.
+    Missing {
+        /// The line, starting at 1.
+        line: usize,
+        /// `.i` or `.o`.
+        keyword: &'static str,
+    },
+    /// A row, `.ilb` or `.ob` has the wrong number of inputs or outputs.
+    Width {
+        /// The line, starting at 1.
+        line: usize,
+    },
+}
+
+impl fmt::Display for PlaParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlaParseError::Invalid { line } => write!(f, "line {line}: invalid PLA syntax"),
+            PlaParseError::Unsupported { line, keyword } => {
+                write!(f, "line {line}: `{keyword}` is not supported")
+            }
+            PlaParseError::Missing { line, keyword } => {
+                write!(f, "line {line}: expected `{keyword}` before this line")
+            }
+            PlaParseError::Width { line } => {
+                write!(f, "line {line}: wrong number of inputs or outputs")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PlaParseError {}
+
+/// Read a PLA in the format of Espresso.
+///
+/// `.i` and `.o` are required, `.ilb` and `.ob` default to `i0`, `i1`, …
+/// and `o0`, `o1`, …, `.type` defaults to `fd`, and `.p` is ignored. In the
+/// output part, `1` and `4` are the on-set, `-` and `2` the don't-care set
+/// and `0` the off-set, if the type has them, and `~` and `3` nothing. `#`
+/// starts a comment, and reading stops at `.e` or `.end`.
+///
+/// # Errors
+///
+/// Fails for malformed lines, rows of the wrong width, and keywords for
+/// multiple-valued or symbolic variables.
+pub fn parse_pla(source: &str) -> Result<Pla, PlaParseError> {
+    let mut inputs: Option<usize> = None;
+    let mut outputs: Option<usize> = None;
+    let mut input_names: Option<Vec<String>> = None;
+    let mut output_names: Option<Vec<String>> = None;
+    let mut ty = PlaType::default();
+    let mut rows: Vec<(usize, Cube, Vec<char>)> = vec![];
+
+    for (idx, line) in source.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let invalid = || PlaParseError::Invalid { line: line_no };
+        let number = |x: Option<&str>| x.and_then(|x| x.parse::<usize>().ok()).ok_or_else(invalid);
+        let mut words = line.split_whitespace();
+        let first = words.next().unwrap();
+
+        if first.starts_with('.') {
+            match first {
+                ".i" => inputs = Some(number(words.next())?),
+                ".o" => outputs = Some(number(words.next())?),
+                ".p" => {
+                    number(words.next())?;
+                }
+                ".ilb" | ".ob" => {
+                    let (count, keyword) = if first == ".ilb" {
+                        (inputs, ".i")
+                    } else {
+                        (outputs, ".o")
+                    };
+                    let count = count.ok_or(PlaParseError::Missing {
+                        line: line_no,
+                        keyword,
+                    })?;
+                    let names: Vec<String> = words.map(str::to_string).collect();
+                    if names.len() != count {
+                        return Err(PlaParseError::Width { line: line_no });
+                    }
+                    if first == ".ilb" {
+                        input_names = Some(names);
+                    } else {
+                        output_names = Some(names);
+                    }
+                }
+                ".type" => {
+                    ty = match words.next() {
+                        Some("f") => PlaType::F,
+                        Some("fd") => PlaType::Fd,
+                        Some("fr") => PlaType::Fr,
+                        Some("fdr") => PlaType::Fdr,
+                        _ => return Err(invalid()),
+                    }
+                }
+                ".e" | ".end" => break,
+                _ => {
+                    return Err(PlaParseError::Unsupported {
+                        line: line_no,
+                        keyword: first.to_string(),
+                    });
+                }
+            }
+            continue;
+        }
+
+        let missing = |keyword| PlaParseError::Missing {
+            line: line_no,
+            keyword,
+        };
+        let inputs = inputs.ok_or_else(|| missing(".i"))?;
+        let outputs = outputs.ok_or_else(|| missing(".o"))?;
+        let row: String = line.split_whitespace().collect();
+        if row.len() != inputs + outputs || !row.is_ascii() {
+            return Err(PlaParseError::Width { line: line_no });
+        }
+        let (input_part, output_part) = row.split_at(inputs);
+        if input_part.contains(|c| !matches!(c, '0' | '1' | '-')) {
+            return Err(invalid());
+        }
+        let cube = Cube::parse(input_part).ok_or_else(invalid)?;
+        let output_part: Vec<char> = output_part.chars().collect();
+        if output_part
+            .iter()
+            .any(|c| !matches!(c, '0' | '1' | '2' | '3' | '4' | '-' | '~'))
+        {
+            return Err(invalid());
+        }
+        rows.push((line_no, cube, output_part));
+    }
+
+    let inputs = inputs.ok_or(PlaParseError::Missing {
+        line: source.lines().count(),
+        keyword: ".i",
+    })?;
+    let outputs = outputs.ok_or(PlaParseError::Missing {
+        line: source.lines().count(),
+        keyword: ".o",
+    })?;
+    // `.i` or `.o` may have changed after the rows or names were read
+    for (line, cube, output_part) in &rows {
+        if cube.vars() != inputs || output_part.len() != outputs {
+            return Err(PlaParseError::Width { line: *line });
+        }
+    }
+    let input_names = input_names
+        .filter(|x| x.len() == inputs)
+        .unwrap_or_else(|| (0..inputs).map(|i| format!("i{i}")).collect());
+    let output_names = output_names
+        .filter(|x| x.len() == outputs)
+        .unwrap_or_else(|| (0..outputs).map(|i| format!("o{i}")).collect());
+
+    let outputs = output_names
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let mut output = PlaOutput {
+                name,
+                on: Cover::new(inputs),
+                dc: Cover::new(inputs),
+                off: Cover::new(inputs),
+            };
+            for (_, cube, output_part) in &rows {
+                let set = match output_part[i] {
+                    '1' | '4' => &mut output.on,
+                    '-' | '2' if ty.has_dc() => &mut output.dc,
+                    '0' if ty.has_off() => &mut output.off,
+                    _ => continue,
+                };
+                set.push(cube.clone());
+            }
+            output
+        })
+        .collect();
+
+    Ok(Pla {
+        inputs: input_names,
+        outputs,
+        ty,
+    })
+}
+
+/// Read a PLA as a model called `name`, see [`parse_pla`] and
+/// [`Pla::to_model`].
+///
+/// # Errors
+///
+/// See [`parse_pla`].
+pub fn parse_pla_to_ast(name: &str, source: &str) -> Result<Blif, PlaParseError> {
+    let mut blif = Blif::new();
+    blif.entries
+        .push(BlifEntry::Model(parse_pla(source)?.to_model(name)));
+    Ok(blif)
+}
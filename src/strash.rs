@@ -0,0 +1,175 @@
+//! Merging of duplicate gates.
+//!
+//! [`strash`] finds gates that compute the same function of the same nets,
+//! keeps one of them, and connects the fanouts of the others to it:
+//!
+//! ```rust
+//! use turbo_blif::ast::{BlifEntry, parse_str_blif_to_ast};
+//! use turbo_blif::strash::strash;
+//!
+//! let mut blif = parse_str_blif_to_ast(
+//!     "top.blif",
+//!     ".model top\n.inputs a b\n.outputs y\n.names a b t\n11 1\n.names b a u\n11 1\n\
+//!      .names t u y\n1- 1\n-1 1\n.end\n",
+//! )
+//! .unwrap();
+//! let BlifEntry::Model(model) = &mut blif.entries[0];
+//! assert_eq!(strash(model), 1);
+//! assert_eq!(model.commands.len(), 2);
+//! ```
+
+use std::collections::{HashMap, HashSet};
+
+use crate::Str;
+use crate::ast::{Gate, Model, ModelCmdKind};
+use crate::flatten::rename_nets;
+use crate::lut::{MAX_INPUTS, TruthTable};
+
+/// The function of a gate, independent of the order of its inputs and rows.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Function {
+    /// For gates with at most [`MAX_INPUTS`] inputs.
+    Table(TruthTable),
+    /// The sorted rows, for wider gates. Only finds identical covers.
+    Rows(Vec<(Vec<u8>, Option<bool>)>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Names {
+        /// Sorted.
+        inputs: Vec<Str<16>>,
+        function: Function,
+    },
+    Lib {
+        name: Str<16>,
+        /// The formal of the output pin.
+        output: Str<16>,
+        /// The other pins, sorted.
+        inputs: Vec<(Str<16>, Str<16>)>,
+    },
+}
+
+/// The key of a gate, with its inputs sorted by name and the columns of its
+/// rows permuted to match.
+fn gate_key(gate: &Gate) -> Option<Key> {
+    let width = gate.meta.inputs.len();
+    if gate.meta.external_dc || gate.lut.0.iter().any(|(row, _)| row.len() != width) {
+        return None;
+    }
+
+    let mut order: Vec<usize> = (0..width).collect();
+    order.sort_by(|&a, &b| gate.meta.inputs[a].cmp(&gate.meta.inputs[b]));
+    let inputs = order.iter().map(|&i| gate.meta.inputs[i].clone()).collect();
+
+    let function = if width <= MAX_INPUTS {
+        let tt = gate.lut.truth_table(width).ok()?;
+        Function::Table(TruthTable::from_fn(width, |m| {
+            // bit `j` of `m` is the sorted input `j`, i.e. `order[j]`
+            let orig = (0..width).fold(0, |acc, j| acc | (m >> j & 1) << order[j]);
+            tt.get(orig)
+        }))
+    } else {
+        let mut rows: Vec<(Vec<u8>, Option<bool>)> = gate
+            .lut
+            .0
+            .iter()
+            .map(|(row, out)| (order.iter().map(|&i| row[i].clone() as u8).collect(), *out))
+            .collect();
+        rows.sort();
+        rows.dedup();
+        Function::Rows(rows)
+    };
+    Some(Key::Names { inputs, function })
+}
+
+/// The key of a cell and its output net.
+fn cell_key(kind: &ModelCmdKind) -> Option<(Key, &Str<16>)> {
+    match kind {
+        ModelCmdKind::Gate(gate) => Some((gate_key(gate)?, &gate.meta.output)),
+        ModelCmdKind::LibGate(lg) => {
+            // the output is the last pin, like the spec requires
+            let ((formal, output), rest) = lg.maps.split_last()?;
+            let mut inputs = rest.to_vec();
+            inputs.sort();
+            let key = Key::Lib {
+                name: lg.name.clone(),
+                output: formal.clone(),
+                inputs,
+            };
+            Some((key, output))
+        }
+        _ => None,
+    }
+}
+
+/// Merge `.names` that compute the same function of the same nets, and
+/// `.gate` cells of the same library cell with the same inputs, until
+/// nothing changes. Returns the number of removed cells.
+///
+/// `.names` are compared by their truth table, so the order of inputs and
+/// rows does not matter, and neither does the choice between on-set and
+/// off-set rows. Gates with more than [`MAX_INPUTS`] inputs are only merged
+/// if their rows are the same up to order.
+///
+/// Of each group, the first cell is kept, with its `.cname` and other
+/// attributes, and the outputs of the others are renamed to its output.
+/// Cells that drive a port are never removed; one of them is kept instead of
+/// the first cell if there is one. `.exdc` gates are left alone.
+pub fn strash(model: &mut Model) -> usize {
+    let meta = &model.meta;
+    let ports: HashSet<Str<16>> = meta
+        .inputs
+        .iter()
+        .chain(&meta.outputs)
+        .flatten()
+        .chain(&meta.clocks)
+        .cloned()
+        .collect();
+
+    let mut removed_total = 0;
+    loop {
+        let mut groups: HashMap<Key, Vec<usize>> = HashMap::new();
+        let mut outputs: Vec<Option<Str<16>>> = vec![None; model.commands.len()];
+        for (idx, cmd) in model.commands.iter().enumerate() {
+            if let Some((key, output)) = cell_key(&cmd.kind) {
+                groups.entry(key).or_default().push(idx);
+                outputs[idx] = Some(output.clone());
+            }
+        }
+
+        let mut subst: HashMap<Str<16>, Str<16>> = HashMap::new();
+        let mut removed = vec![false; model.commands.len()];
+        for group in groups.values().filter(|g| g.len() > 1) {
+            let output = |idx: usize| outputs[idx].as_ref().unwrap();
+            let keep = group
+                .iter()
+                .copied()
+                .find(|&idx| ports.contains(output(idx)))
+                .unwrap_or(group[0]);
+            for &idx in group {
+                if idx == keep || ports.contains(output(idx)) {
+                    continue;
+                }
+                removed[idx] = true;
+                subst.insert(output(idx).clone(), output(keep).clone());
+            }
+        }
+        if subst.is_empty() {
+            return removed_total;
+        }
+        removed_total += subst.len();
+
+        let net = |name: &str| subst.get(name).cloned().unwrap_or_else(|| name.into());
+        let path = |name: &str| name.to_string();
+        let mut idx = 0;
+        model.commands.retain_mut(|cmd| {
+            idx += 1;
+            if removed[idx - 1] {
+                return false;
+            }
+            cmd.kind = rename_nets(&cmd.kind, &net, &path);
+            true
+        });
+    }
+}
@@ -0,0 +1,368 @@
+//! Removal of constant, redundant and dead logic.
+//!
+//! [`sweep`] cleans up a model the way the `sweep` command of SIS and ABC
+//! does:
+//!
+//! ```rust
+//! use turbo_blif::ast::{BlifEntry, parse_str_blif_to_ast};
+//! use turbo_blif::sweep::sweep;
+//!
+//! let mut blif = parse_str_blif_to_ast(
+//!     "top.blif",
+//!     ".model top\n.inputs a b\n.outputs y\n.names $true\n1\n\
+//!      .names a $true t\n11 1\n.names t y\n1 1\n.names b unused\n0 1\n.end\n",
+//! )
+//! .unwrap();
+//! let BlifEntry::Model(model) = &mut blif.entries[0];
+//! let stats = sweep(model);
+//! assert_eq!(stats.constant_inputs, 1);
+//!
+//! // only `.names a y` is left
+//! assert_eq!(model.commands.len(), 1);
+//! ```
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{Gate, LUT, Model, ModelCmd, ModelCmdKind};
+use crate::flatten::rename_nets;
+use crate::{FlipFlopInit, GateMeta, ModelDelayConstraint, Str, Tristate};
+
+/// What [`sweep`] did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SweepStats {
+    /// Number of gates with inputs that turned out to be constant.
+    pub constant_gates: usize,
+    /// Number of gate inputs that were removed because they are constant.
+    pub constant_inputs: usize,
+    /// Number of buffers and `.conn` that were collapsed.
+    pub buffers: usize,
+    /// Number of latches that were replaced by a constant.
+    pub constant_latches: usize,
+    /// Number of gates that were removed because nothing reads them.
+    pub dead_gates: usize,
+    /// Number of latches that were removed because nothing reads them.
+    pub dead_latches: usize,
+}
+
+/// Simplify `model` until nothing changes.
+///
+/// - Gates that are constant are rewritten to `.names y` (constant 0) or
+///   `.names y` with the row `1`, and their value is propagated into the
+///   `.names` reading them, which lose that input.
+/// - A latch whose input is its own output and which has a constant initial
+///   value is replaced by a constant gate.
+/// - Buffers (`.names a y` with the row `1 1`) and `.conn` are removed, and
+///   their output is renamed to their input. If the output is a primary
+///   output, the input is renamed instead, unless it is a port too. Nets
+///   named by timing constraints and `.clock_event` are never renamed
+///   either.
+/// - Gates and latches whose output is read by nothing are removed. Nets
+///   named by `.constraint`, `.onehot`, `.reset`, `.gateinit`, `.mv` and
+///   timing constraints count as read.
+///
+/// `.gate`, `.mlatch` and `.subckt` cells are never removed, because the
+/// direction of their pins may be unknown, and `.exdc` gates are left alone.
+/// Ports are never renamed.
+pub fn sweep(model: &mut Model) -> SweepStats {
+    let mut stats = SweepStats::default();
+    loop {
+        let before = stats;
+        constant_latches(model, &mut stats);
+        propagate_constants(model, &mut stats);
+        collapse_buffers(model, &mut stats);
+        remove_dead(model, &mut stats);
+        if stats == before {
+            return stats;
+        }
+    }
+}
+
+fn is_port(model: &Model, net: &str) -> bool {
+    let meta = &model.meta;
+    meta.inputs
+        .iter()
+        .chain(&meta.outputs)
+        .flatten()
+        .chain(&meta.clocks)
+        .any(|x| x == net)
+}
+
+fn constant_gate(output: Str<16>, value: bool) -> ModelCmdKind {
+    let rows = if value {
+        vec![(Default::default(), Some(true))]
+    } else {
+        vec![]
+    };
+    ModelCmdKind::Gate(Gate {
+        meta: GateMeta {
+            inputs: vec![],
+            output,
+            external_dc: false,
+            syntax: Default::default(),
+        },
+        lut: LUT(rows),
+    })
+}
+
+fn constant_latches(model: &mut Model, stats: &mut SweepStats) {
+    for cmd in &mut model.commands {
+        if let ModelCmdKind::FF(ff) = &cmd.kind
+            && ff.input == ff.output
+            && let FlipFlopInit::Const(value) = ff.init
+        {
+            cmd.kind = constant_gate(ff.output.clone(), value);
+            cmd.attrs.clear();
+            stats.constant_latches += 1;
+        }
+    }
+}
+
+/// The value of a constant gate.
+fn gate_constant(gate: &Gate) -> Option<bool> {
+    if gate.meta.external_dc {
+        return None;
+    }
+    if gate.meta.inputs.is_empty() {
+        return Some(gate.lut.eval(&[]));
+    }
+    gate.lut.is_constant()
+}
+
+fn propagate_constants(model: &mut Model, stats: &mut SweepStats) {
+    let mut constants: HashMap<Str<16>, bool> = HashMap::new();
+    for cmd in &mut model.commands {
+        let ModelCmdKind::Gate(gate) = &mut cmd.kind else {
+            continue;
+        };
+        let Some(value) = gate_constant(gate) else {
+            continue;
+        };
+        if !gate.meta.inputs.is_empty() {
+            cmd.kind = constant_gate(gate.meta.output.clone(), value);
+            stats.constant_gates += 1;
+        }
+        if let ModelCmdKind::Gate(gate) = &cmd.kind {
+            constants.insert(gate.meta.output.clone(), value);
+        }
+    }
+
+    for cmd in &mut model.commands {
+        let ModelCmdKind::Gate(gate) = &mut cmd.kind else {
+            continue;
+        };
+        if gate.meta.external_dc {
+            continue;
+        }
+        let width = gate.meta.inputs.len();
+        if gate.lut.0.iter().any(|(row, _)| row.len() != width) {
+            continue;
+        }
+        let off = gate.lut.0.iter().any(|(_, out)| *out == Some(false));
+
+        // from the back, so that the indices of the others stay valid
+        for idx in (0..width).rev() {
+            let Some(&value) = constants.get(&gate.meta.inputs[idx]) else {
+                continue;
+            };
+            let lit = if value {
+                Tristate::True
+            } else {
+                Tristate::False
+            };
+            gate.meta.inputs.remove(idx);
+            gate.lut.0.retain_mut(|(row, _)| {
+                let keep = row[idx] == lit || row[idx] == Tristate::Ignored;
+                row.remove(idx);
+                keep
+            });
+            stats.constant_inputs += 1;
+        }
+
+        // an off-set cover without rows is constant 1, not constant 0
+        if off && !gate.lut.0.iter().any(|(_, out)| *out == Some(false)) {
+            cmd.kind = constant_gate(gate.meta.output.clone(), true);
+            stats.constant_gates += 1;
+            continue;
+        }
+
+        let mut seen = HashSet::new();
+        gate.lut.0.retain(|(row, out)| {
+            let key: Vec<u8> = row.iter().map(|x| x.clone() as u8).collect();
+            seen.insert((key, *out))
+        });
+    }
+}
+
+/// The input of a buffer gate or `.conn`, and its output.
+fn buffer(cmd: &ModelCmd) -> Option<(&Str<16>, &Str<16>)> {
+    match &cmd.kind {
+        ModelCmdKind::Gate(gate) if !gate.meta.external_dc => (gate.lut.is_buffer() == Some(0)
+            && gate.meta.inputs.len() == 1)
+            .then(|| (&gate.meta.inputs[0], &gate.meta.output)),
+        ModelCmdKind::Connect { from, to } => Some((from, to)),
+        _ => None,
+    }
+}
+
+fn collapse_buffers(model: &mut Model, stats: &mut SweepStats) {
+    // `rename_nets` leaves timing constraints and clock events alone
+    let mut pinned: HashSet<&str> = HashSet::new();
+    for cmd in &model.commands {
+        match &cmd.kind {
+            ModelCmdKind::DelayConstraint(dc) => pinned.extend(delay_constraint_nets(dc)),
+            ModelCmdKind::ClockEvents(ev) => {
+                pinned.extend(ev.events.iter().map(|e| e.clock_name.as_str()));
+            }
+            _ => {}
+        }
+    }
+    let keep = |net: &Str<16>| is_port(model, net) || pinned.contains(net.as_str());
+
+    let mut subst: HashMap<Str<16>, Str<16>> = HashMap::new();
+    let mut touched: HashSet<Str<16>> = HashSet::new();
+    let mut removed = vec![false; model.commands.len()];
+    for (idx, cmd) in model.commands.iter().enumerate() {
+        let Some((input, output)) = buffer(cmd) else {
+            continue;
+        };
+        if input == output {
+            removed[idx] = true;
+            stats.buffers += 1;
+            continue;
+        }
+        if touched.contains(input) || touched.contains(output) {
+            continue;
+        }
+        let (from, to) = if !keep(output) {
+            (output, input)
+        } else if !keep(input) {
+            (input, output)
+        } else {
+            continue;
+        };
+        subst.insert(from.clone(), to.clone());
+        touched.insert(input.clone());
+        touched.insert(output.clone());
+        removed[idx] = true;
+        stats.buffers += 1;
+    }
+    if !removed.contains(&true) {
+        return;
+    }
+
+    let net = |name: &str| subst.get(name).cloned().unwrap_or_else(|| name.into());
+    let path = |name: &str| name.to_string();
+    let mut idx = 0;
+    model.commands.retain_mut(|cmd| {
+        idx += 1;
+        if removed[idx - 1] {
+            return false;
+        }
+        cmd.kind = rename_nets(&cmd.kind, &net, &path);
+        true
+    });
+}
+
+/// The nets that a cell reads, and the one it drives, for the cells that can
+/// be removed.
+fn removable(cmd: &ModelCmd) -> Option<(Vec<&Str<16>>, &Str<16>)> {
+    match &cmd.kind {
+        ModelCmdKind::Gate(gate) if !gate.meta.external_dc => {
+            Some((gate.meta.inputs.iter().collect(), &gate.meta.output))
+        }
+        ModelCmdKind::FF(ff) => {
+            let reads = std::iter::once(&ff.input).chain(&ff.clock).collect();
+            Some((reads, &ff.output))
+        }
+        _ => None,
+    }
+}
+
+/// The nets that a timing constraint refers to.
+fn delay_constraint_nets(dc: &ModelDelayConstraint) -> Vec<&str> {
+    match dc {
+        ModelDelayConstraint::Input(c) => vec![&c.input],
+        ModelDelayConstraint::InputArrivalTime(s) | ModelDelayConstraint::OutputRequiredTime(s) => {
+            vec![&s.signal]
+        }
+        ModelDelayConstraint::InputDrive(s) => vec![&s.signal],
+        ModelDelayConstraint::MaxInputLoad(s)
+        | ModelDelayConstraint::OutputLoad(s)
+        | ModelDelayConstraint::InputRequired(s)
+        | ModelDelayConstraint::OutputArrival(s) => vec![&s.signal],
+        ModelDelayConstraint::DelayPerPair {
+            in_sig, out_sig, ..
+        } => vec![in_sig, out_sig],
+        _ => vec![],
+    }
+}
+
+fn remove_dead(model: &mut Model, stats: &mut SweepStats) {
+    // cells that can be removed, by their output
+    let mut drivers: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut live: HashSet<&str> = HashSet::new();
+    let mut todo: Vec<&str> = vec![];
+    for (idx, cmd) in model.commands.iter().enumerate() {
+        if let Some((_, output)) = removable(cmd) {
+            drivers.entry(output.as_str()).or_default().push(idx);
+            continue;
+        }
+        // everything else keeps the nets it mentions alive
+        match &cmd.kind {
+            ModelCmdKind::LibGate(lg) => todo.extend(lg.maps.iter().map(|(_, x)| x.as_str())),
+            ModelCmdKind::LibFF(lf) => {
+                todo.extend(lf.maps.iter().map(|(_, x)| x.as_str()));
+                todo.extend(lf.clock.iter().map(|x| x.as_str()));
+            }
+            ModelCmdKind::SubModel { map, .. } => {
+                todo.extend(map.iter().map(|(_, x)| x.as_str()));
+            }
+            ModelCmdKind::Connect { from, to } => todo.extend([from.as_str(), to.as_str()]),
+            ModelCmdKind::Gate(gate) => todo.extend(gate.meta.inputs.iter().map(|x| x.as_str())),
+            ModelCmdKind::Constraint(signals) | ModelCmdKind::OneHot(signals) => {
+                todo.extend(signals.iter().map(|x| x.as_str()));
+            }
+            ModelCmdKind::Reset { signal, .. } | ModelCmdKind::GateInit { signal, .. } => {
+                todo.push(signal);
+            }
+            ModelCmdKind::Mv { variables, .. } => todo.extend(variables.iter().map(|x| x.as_str())),
+            ModelCmdKind::DelayConstraint(dc) => todo.extend(delay_constraint_nets(dc)),
+            _ => {}
+        }
+    }
+    todo.extend(model.meta.outputs.iter().flatten().map(|x| x.as_str()));
+
+    let mut live_cells = vec![false; model.commands.len()];
+    while let Some(net) = todo.pop() {
+        if !live.insert(net) {
+            continue;
+        }
+        for &idx in drivers.get(net).into_iter().flatten() {
+            if live_cells[idx] {
+                continue;
+            }
+            live_cells[idx] = true;
+            if let Some((reads, _)) = removable(&model.commands[idx]) {
+                todo.extend(reads.into_iter().map(|x| x.as_str()));
+            }
+        }
+    }
+
+    let dead: Vec<bool> = model
+        .commands
+        .iter()
+        .enumerate()
+        .map(|(idx, cmd)| removable(cmd).is_some() && !live_cells[idx])
+        .collect();
+    for (cmd, _) in model.commands.iter().zip(&dead).filter(|(_, d)| **d) {
+        match cmd.kind {
+            ModelCmdKind::FF(_) => stats.dead_latches += 1,
+            _ => stats.dead_gates += 1,
+        }
+    }
+    let mut idx = 0;
+    model.commands.retain(|_| {
+        idx += 1;
+        !dead[idx - 1]
+    });
+}
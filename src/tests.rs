@@ -35,6 +35,7 @@ fn simple_named() {
                         inputs: vec!["a".into(), "b".into(),],
                         output: "c".into(),
                         external_dc: false,
+                        syntax: LutSyntax::Names,
                     },
                     lut: LUT(vec![(
                         [Tristate::True, Tristate::True].into_iter().collect(),
@@ -75,6 +76,7 @@ fn simple_unnamed() {
                         inputs: vec!["a".into(), "b".into(),],
                         output: "c".into(),
                         external_dc: false,
+                        syntax: LutSyntax::Names,
                     },
                     lut: LUT(vec![(
                         [Tristate::True, Tristate::True].into_iter().collect(),
@@ -114,6 +116,7 @@ c
                         inputs: vec!["a".into(), "b".into(),],
                         output: "c".into(),
                         external_dc: false,
+                        syntax: LutSyntax::Names,
                     },
                     lut: LUT(vec![(
                         [Tristate::True, Tristate::True].into_iter().collect(),
@@ -154,6 +157,7 @@ c
                         inputs: vec!["a".into(), "b".into(),],
                         output: "c".into(),
                         external_dc: true,
+                        syntax: LutSyntax::Names,
                     },
                     lut: LUT(vec![(
                         [Tristate::True, Tristate::True].into_iter().collect(),
@@ -194,6 +198,7 @@ fn lut() {
                         inputs: vec!["v3".into(), "v6".into(), "j".into(), "u78".into()],
                         output: "v13.15".into(),
                         external_dc: false,
+                        syntax: LutSyntax::Names,
                     },
                     lut: LUT(vec![
                         (
@@ -287,7 +292,8 @@ fn submod() {
                         meta: GateMeta {
                             inputs: vec!["x".into()],
                             output: "j".into(),
-                            external_dc: true
+                            external_dc: true,
+                            syntax: LutSyntax::Names
                         },
                         lut: LUT(vec![([Tristate::True].into_iter().collect(), Some(true))])
                     })
@@ -307,7 +313,8 @@ fn submod() {
                         meta: GateMeta {
                             inputs: vec!["x".into(), "y".into()],
                             output: "j".into(),
-                            external_dc: false
+                            external_dc: false,
+                            syntax: LutSyntax::Names
                         },
                         lut: LUT(vec![(
                             [Tristate::True, Tristate::True].into_iter().collect(),
@@ -550,12 +557,16 @@ fn delay_cst__area() {
                         inputs: vec![],
                         output: "$true".into(),
                         external_dc: false,
+                        syntax: LutSyntax::Names,
                     },
                     lut: LUT(vec![([].into_iter().collect(), Some(true))])
                 }),
                 attrs: vec![],
             }],
-            attr: ModelAttr { area: Some(100.31) }
+            attr: ModelAttr {
+                area: Some(100.31),
+                blackbox: false
+            }
         })]
     );
 }
@@ -782,7 +793,7 @@ fn delay_cst__wire() {
 
 #[test]
 fn blackbox_directive() {
-    let _ast = parse_str_blif_to_ast(
+    let ast = parse_str_blif_to_ast(
         "top.blif",
         r#"
 .model top
@@ -793,6 +804,12 @@ fn blackbox_directive() {
 "#,
     )
     .unwrap();
+    let model = ast.model("top").unwrap();
+    assert!(model.attr.blackbox);
+    assert_eq!(
+        writer::blif_to_string(&ast, writer::BlifFlavor::ABC),
+        ".model top\n.inputs a\n.outputs z\n.blackbox\n.end\n"
+    );
 }
 
 #[test]
@@ -1110,7 +1127,8 @@ fn subckt_with_instance_name() {
                         meta: GateMeta {
                             inputs: vec!["a".into(), "b".into()],
                             output: "z".into(),
-                            external_dc: false
+                            external_dc: false,
+                            syntax: LutSyntax::Names
                         },
                         lut: LUT(vec![(
                             [Tristate::True, Tristate::True].into_iter().collect(),
@@ -1333,6 +1351,7 @@ fn delay_per_pair() {
                         inputs: vec!["a".into()],
                         output: "z".into(),
                         external_dc: false,
+                        syntax: LutSyntax::Names,
                     },
                     lut: LUT(vec![([Tristate::True].into_iter().collect(), Some(true))]),
                 })
@@ -1348,3 +1367,2526 @@ fn delay_per_pair() {
         })]
     );
 }
+
+#[test]
+fn model_boundary_scan() {
+    let lines = [
+        "# header comment",
+        ".model a",
+        ".names x \\",
+        "y",
+        "1 1",
+        ".end",
+        "",
+        ".search other.blif",
+        ".model b",
+        ".end # trailing",
+        "",
+    ];
+    assert_eq!(scan_model_boundaries(&lines), vec![0..6, 6..8, 8..11]);
+}
+
+#[test]
+fn model_boundary_scan_unnamed_first() {
+    let lines = [".inputs a", ".names a b", "1 1", ".end", ".model m", ".end"];
+    assert_eq!(scan_model_boundaries(&lines), vec![0..4, 4..6]);
+    assert_eq!(scan_chunks(&lines), vec![(0..4, true), (4..6, false)]);
+}
+
+#[test]
+fn parallel_matches_sequential() {
+    let source = r#"
+.names a b
+1 1
+.end
+
+.model top
+.inputs a b
+.outputs z
+.subckt adder|inst0 a=a b=b z=z
+.end
+
+.model adder
+.inputs a b
+.outputs z
+.names a b z
+11 1
+.end
+"#;
+    assert_eq!(
+        parse_blif_parallel("top.blif", source).unwrap(),
+        parse_str_blif_to_ast("top.blif", source).unwrap()
+    );
+
+    for file in ["C880.blif", "pj1.blif", "term1.blif"] {
+        let source = std::fs::read_to_string(format!("blif-examples-from-mvsis/{file}")).unwrap();
+        assert_eq!(
+            parse_blif_parallel(file, &source).unwrap(),
+            parse_str_blif_to_ast(file, &source).unwrap()
+        );
+    }
+}
+
+#[test]
+fn parallel_errors_match_sequential() {
+    let sources = [
+        ".model a\n.end\n.model b\n.frobnicate\n.end\n.model c\n.end\n",
+        ".model a\n.start_kiss\n.i 1\n.o 1\n.end\n.end_kiss\n.end\n",
+        ".model a\n.end\n.search x.blif\n",
+        // a leading `.search`
+        ".search x.blif\n.names a b\n1 1\n.end\n",
+        ".search x.blif\n.model a\n.end\n.model b\n.end\n",
+    ];
+    for source in sources {
+        assert_eq!(
+            format!("{:?}", parse_blif_parallel("t.blif", source)),
+            format!("{:?}", parse_str_blif_to_ast("t.blif", source))
+        );
+    }
+}
+
+#[test]
+fn writer_roundtrips_rows_and_attrs() {
+    // don't-care outputs used to be written as `-`, which the parser rejects
+    let source = ".model m\n.inputs a\n.outputs y\n.names a y\n1 1\n0 x\n.end\n";
+    let ast = parse_str_blif_to_ast("m.blif", source).unwrap();
+    let out = writer::blif_to_string(&ast, writer::BlifFlavor::Standard);
+    assert_eq!(out, source);
+    assert_eq!(parse_str_blif_to_ast("m.blif", &out).unwrap(), ast);
+
+    // rows of constant gates have no input plane, like SIS and ABC write them
+    let source = ".model m\n.outputs y\n.names y\n1\n.end\n";
+    let ast = parse_str_blif_to_ast("m.blif", source).unwrap();
+    assert_eq!(
+        writer::blif_to_string(&ast, writer::BlifFlavor::Standard),
+        source
+    );
+
+    // attributes of `.conn` and other commands used to be dropped
+    let source = ".model m\n.inputs a\n.outputs b\n.conn a b\n.cname c\n.end\n";
+    let ast = parse_str_blif_to_ast("m.blif", source).unwrap();
+    let out = writer::blif_to_string(&ast, writer::BlifFlavor::Yosys);
+    assert_eq!(out, source);
+    assert_eq!(parse_str_blif_to_ast("m.blif", &out).unwrap(), ast);
+}
+
+fn stream_blif(source: &str, flavor: writer::BlifFlavor) -> String {
+    let mut out = writer::StreamingWriter::new(String::new(), flavor);
+    parse_blif("top.blif", &mut out, source.split('\n')).unwrap();
+    out.finish().unwrap()
+}
+
+#[test]
+fn streaming_writer_matches_ast_writer() {
+    use writer::BlifFlavor;
+
+    let yosys = r#"
+.model top
+.inputs a b clk
+.outputs z q
+.names $true
+1
+.names a b n1
+11 1
+.cname and0
+.attr src "top.v:1.2-3.4"
+.subckt adder|u0 a=a b=n1 z=z
+.cname u0
+.param WIDTH 00000000000000000000000000000001
+.latch z q re clk 0
+.barbuf q q2
+.gateinit q=1
+.start_kiss
+.i 1
+.o 1
+.r s0
+0 s0 s0 0
+1 s0 s1 1
+1 s1 s0 0
+.end_kiss
+.code s0 0
+.code s1 1
+.end
+
+.model adder
+.inputs a b
+.outputs z
+.delay a z 1.5
+.exdc
+.names a b z
+00 1
+.end
+"#;
+    let sources = [
+        yosys.to_string(),
+        std::fs::read_to_string("blif-examples-from-mvsis/C880.blif").unwrap(),
+        std::fs::read_to_string("blif-examples-from-mvsis/frg2.blif").unwrap(),
+    ];
+
+    for source in &sources {
+        let ast = parse_str_blif_to_ast("top.blif", source).unwrap();
+        for flavor in [
+            BlifFlavor::Standard,
+            BlifFlavor::ABC,
+            BlifFlavor::Yosys,
+            BlifFlavor::Sis,
+            BlifFlavor::SisMV,
+        ] {
+            assert_eq!(
+                stream_blif(source, flavor),
+                writer::blif_to_string(&ast, flavor)
+            );
+        }
+    }
+}
+
+#[test]
+fn streaming_writer_converts_dialects() {
+    let out = stream_blif(
+        ".model top\n.inputs a\n.outputs b\n.short a b\n.end\n",
+        writer::BlifFlavor::Yosys,
+    );
+    assert_eq!(out, ".model top\n.inputs a\n.outputs b\n.conn a b\n.end\n");
+}
+
+#[test]
+fn write_blif_io_matches_string() {
+    let source = std::fs::read_to_string("blif-examples-from-mvsis/i9.blif").unwrap();
+    let ast = parse_str_blif_to_ast("i9.blif", &source).unwrap();
+    let mut out = vec![];
+    writer::write_blif_io(&ast, &mut out, writer::BlifFlavor::Standard).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        writer::blif_to_string(&ast, writer::BlifFlavor::Standard)
+    );
+}
+
+#[test]
+fn write_blif_io_propagates_errors() {
+    struct Full;
+    impl std::io::Write for Full {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(std::io::ErrorKind::StorageFull, "full"))
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let source = std::fs::read_to_string("blif-examples-from-mvsis/i9.blif").unwrap();
+    let ast = parse_str_blif_to_ast("i9.blif", &source).unwrap();
+    let err = writer::write_blif_io(&ast, Full, writer::BlifFlavor::Standard).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::StorageFull);
+}
+
+#[test]
+fn write_blif_split_roundtrip() {
+    let source = r#"
+.model top
+.inputs a b
+.outputs z
+.subckt adder|inst0 a=a b=b z=z
+.end
+
+.model adder
+.inputs a b
+.outputs z
+.names a b z
+11 1
+.end
+
+.model a/b
+.inputs x
+.outputs y
+.names x y
+0 1
+.end
+"#;
+    let ast = parse_str_blif_to_ast("top.blif", source).unwrap();
+
+    let dir = std::env::temp_dir().join(format!("turbo-blif-split-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let files =
+        writer::write_blif_split(&ast, &dir, "design.blif", writer::BlifFlavor::Standard).unwrap();
+    assert_eq!(
+        files,
+        ["design.blif", "top.blif", "adder.blif", "a_b.blif"].map(|x| dir.join(x))
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.join("design.blif")).unwrap(),
+        ".search top.blif\n.search adder.blif\n.search a_b.blif\n"
+    );
+
+    let back = parse_many_blif_to_ast(dir.join("design.blif").to_str().unwrap(), |p| {
+        std::fs::read_to_string(dir.join(p))
+            .map(|s| s.split('\n').map(|x| x.to_string()).collect::<Vec<_>>())
+    })
+    .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(back.entries, ast.entries);
+}
+
+#[test]
+fn search_before_first_model() {
+    let ast = parse_str_blif_to_ast("top.blif", ".search a.blif\n.names x\n1\n");
+    assert!(matches!(ast, Err(FullBlifErr::SearchPathsNotSupported)));
+
+    let lines = [
+        ".search a.blif",
+        ".names x",
+        "1",
+        ".end",
+        ".model m",
+        ".end",
+    ];
+    assert_eq!(scan_model_boundaries(&lines), vec![0..1, 1..4, 4..6]);
+    assert_eq!(
+        scan_chunks(&lines),
+        vec![(0..1, true), (1..4, true), (4..6, false)]
+    );
+
+    let back = parse_many_blif_to_ast("top.blif", |p| match p {
+        "top.blif" => Ok(lines.to_vec()),
+        "a.blif" => Ok(vec![".model a", ".end"]),
+        _ => Err(()),
+    })
+    .unwrap();
+    let names: Vec<_> = back
+        .entries
+        .iter()
+        .map(|BlifEntry::Model(m)| m.meta.name.as_str())
+        .collect();
+    assert_eq!(names, ["top.blif", "m", "a"]);
+
+    // referenced files are read in order
+    let back = parse_many_blif_to_ast("top.blif", |p| match p {
+        "top.blif" => Ok(vec![".search b.blif", ".search c.blif"]),
+        "b.blif" => Ok(vec![".model b", ".end"]),
+        "c.blif" => Ok(vec![".model c", ".end"]),
+        _ => Err(()),
+    })
+    .unwrap();
+    let names: Vec<_> = back
+        .entries
+        .iter()
+        .map(|BlifEntry::Model(m)| m.meta.name.as_str())
+        .collect();
+    assert_eq!(names, ["b", "c"]);
+}
+
+#[test]
+fn writer_wraps_long_lines() {
+    let ast = parse_str_blif_to_ast(
+        "top.blif",
+        ".model top\n.inputs a0 a1 a2 a3 a4 a5 a6 a7\n.outputs z\n.names a0 a1 a2 a3 z\n1111 1\n.end\n",
+    )
+    .unwrap();
+    let opts = writer::WriteOptions {
+        max_line_width: Some(16),
+        indent: 2,
+        ..writer::WriteOptions::new(writer::BlifFlavor::Standard)
+    };
+    let out = writer::blif_to_string(&ast, &opts);
+    assert_eq!(
+        out,
+        ".model top\n.inputs a0 a1 \\\n  a2 a3 a4 a5 \\\n  a6 a7\n.outputs z\n.names a0 a1 \\\n  a2 a3 z\n1111 1\n.end\n"
+    );
+    assert!(out.lines().all(|l| l.len() <= 16));
+    assert_eq!(
+        parse_str_blif_to_ast("top.blif", &out).unwrap().entries,
+        ast.entries
+    );
+}
+
+#[test]
+fn writer_aligns_lut_rows() {
+    let ast = parse_str_blif_to_ast(
+        "top.blif",
+        ".model top\n.inputs a bb\n.outputs out\n.names a bb out\n1- 1\n-1 1\n.names one\n1\n.end\n",
+    )
+    .unwrap();
+    let opts = writer::WriteOptions {
+        align_lut_rows: true,
+        ..writer::WriteOptions::new(writer::BlifFlavor::Standard)
+    };
+    let out = writer::blif_to_string(&ast, &opts);
+    assert_eq!(
+        out,
+        ".model top\n.inputs a bb\n.outputs out\n.names a bb out\n       1-   1\n       -1   1\n.names one\n1\n.end\n"
+    );
+    assert_eq!(
+        parse_str_blif_to_ast("top.blif", &out).unwrap().entries,
+        ast.entries
+    );
+}
+
+#[test]
+fn writer_canonical_mode() {
+    let a = parse_str_blif_to_ast(
+        "top.blif",
+        r#"
+.model top
+.inputs a b
+.outputs y z
+.names a b y
+01 1
+10 1
+.subckt sub x=a y=b z=z
+.delay a y 1.0
+.delay a y 2.0
+.end
+"#,
+    )
+    .unwrap();
+    let b = parse_str_blif_to_ast(
+        "top.blif",
+        r#"
+.model top
+.inputs a b
+.outputs y z
+.subckt sub z=z y=b x=a
+.delay a y 1.0
+.names a b y
+10 1
+01 1
+.delay a y 2.0
+.end
+"#,
+    )
+    .unwrap();
+    let opts = writer::WriteOptions {
+        canonical: true,
+        ..writer::WriteOptions::new(writer::BlifFlavor::ABC)
+    };
+    let out = writer::blif_to_string(&a, &opts);
+    assert_eq!(out, writer::blif_to_string(&b, &opts));
+    assert_eq!(
+        out,
+        ".model top\n.inputs a b\n.outputs y z\n.names a b y\n01 1\n10 1\n.subcircuit sub x=a y=b z=z\n.delay a y 1\n.delay a y 2\n.end\n"
+    );
+}
+
+#[test]
+fn cst_roundtrip_is_lossless() {
+    for file in ["C880.blif", "frg2.blif", "pj1.blif", "term1.blif"] {
+        let source = std::fs::read_to_string(format!("blif-examples-from-mvsis/{file}")).unwrap();
+        let cst = cst::Cst::parse(&source);
+        assert_eq!(cst.to_string(), source);
+    }
+
+    // edits are seen by `to_ast`
+    let mut cst =
+        cst::Cst::parse(".model top\n.inputs a b\n.outputs y\n.names a b y\n10 1\n.end\n");
+    let stmt = cst
+        .stmts
+        .iter_mut()
+        .find(|s| s.keyword() == Some(".names"))
+        .unwrap();
+    stmt.set_text(".names b a y");
+    let ast = cst.to_ast("top.blif").unwrap();
+    let BlifEntry::Model(model) = &ast.entries[0];
+    let ModelCmdKind::Gate(gate) = &model.commands[0].kind else {
+        panic!()
+    };
+    assert_eq!(gate.meta.inputs, vec!["b", "a"]);
+    assert_eq!(gate.meta.output, "y");
+
+    let source = "# header\r\n\r\n  .model top # comment\r\n.inputs a \\  # first\r\n  b\r\n.outputs c\r\n.barbuf a c\r\n.end\r\n# tail";
+    let cst = cst::Cst::parse(source);
+    assert_eq!(cst.to_string(), source);
+    assert_eq!(cst.stmts.len(), 5);
+    assert_eq!(cst.stmts[0].leading, "# header\r\n\r\n  ");
+    assert_eq!(cst.stmts[0].trailing, " # comment\r\n");
+    assert_eq!(cst.stmts[1].tokens(), vec![".inputs", "a", "b"]);
+    assert_eq!(cst.stmts[3].keyword(), Some(".barbuf"));
+    assert_eq!(cst.stmts[3].canonical_keyword(), Some(".conn"));
+    assert_eq!(cst.trailing, "# tail");
+    assert_eq!(
+        cst.models(),
+        vec![cst::CstModel {
+            name: Some("top".to_string()),
+            stmts: 0..5
+        }]
+    );
+}
+
+#[test]
+fn cst_edit_only_changes_edited_lines() {
+    let source = std::fs::read_to_string("blif-examples-from-mvsis/C880.blif").unwrap();
+    let mut cst = cst::Cst::parse(&source);
+    let stmt = cst
+        .stmts
+        .iter_mut()
+        .find(|s| s.keyword() == Some(".outputs"))
+        .unwrap();
+    let old = stmt.text().to_string();
+    stmt.set_text(".outputs renamed");
+    cst.stmts.insert(1, cst::Stmt::new("# inserted"));
+
+    let out = cst.to_string();
+    let expected = source.replacen(&old, ".outputs renamed", 1);
+    let mut out_lines = out.lines().collect::<Vec<_>>();
+    let removed = out_lines.iter().position(|l| *l == "# inserted").unwrap();
+    out_lines.remove(removed);
+    assert_eq!(out_lines, expected.lines().collect::<Vec<_>>());
+}
+
+#[test]
+fn writer_keeps_lut_syntax() {
+    use writer::BlifFlavor;
+
+    let sis = ".model top\n.inputs a b\n.outputs y z\n.cover 2 1 2\na b y\n1- 1\n-1 1\n.names a z\n0 1\n.end\n";
+    let ast = parse_str_blif_to_ast("top.blif", sis).unwrap();
+    let BlifEntry::Model(model) = &ast.entries[0];
+    let ModelCmdKind::Gate(gate) = &model.commands[0].kind else {
+        panic!()
+    };
+    assert_eq!(gate.meta.syntax, LutSyntax::Cover);
+    assert_eq!(writer::blif_to_string(&ast, BlifFlavor::Sis), sis);
+    assert_eq!(stream_blif(sis, BlifFlavor::Sis), sis);
+    assert_eq!(
+        writer::blif_to_string(&ast, BlifFlavor::Standard),
+        ".model top\n.inputs a b\n.outputs y z\n.names a b y\n1- 1\n-1 1\n.names a z\n0 1\n.end\n"
+    );
+
+    // a single term is written as `.names` even under Sis
+    let single = ".model top\n.inputs a b\n.outputs y\n.cover 2 1 1\na b y\n11 1\n.end\n";
+    let expected = single.replace(".cover 2 1 1\na b y", ".names a b y");
+    let ast = parse_str_blif_to_ast("top.blif", single).unwrap();
+    assert_eq!(writer::blif_to_string(&ast, BlifFlavor::Sis), expected);
+    assert_eq!(stream_blif(single, BlifFlavor::Sis), expected);
+
+    let mv = ".model top\n.inputs a b\n.outputs y\n.table a b -> y\n11 1\n.end\n";
+    let ast = parse_str_blif_to_ast("top.blif", mv).unwrap();
+    assert_eq!(writer::blif_to_string(&ast, BlifFlavor::SisMV), mv);
+    assert_eq!(stream_blif(mv, BlifFlavor::SisMV), mv);
+    assert_eq!(
+        writer::blif_to_string(&ast, BlifFlavor::Sis),
+        mv.replace(".table a b -> y", ".names a b y")
+    );
+}
+
+#[test]
+fn name_escaping() {
+    use names::*;
+    use writer::{BlifFlavor, WriteOptions};
+
+    let mut ast = parse_str_blif_to_ast(
+        "top.blif",
+        ".model top\n.inputs a b\n.outputs y\n.names a b y\n11 1\n.subckt sub x=a\n.end\n",
+    )
+    .unwrap();
+    fn rename(s: &str) -> &str {
+        match s {
+            "a" => "a#1",
+            "b" => "b<2> c",
+            "y" => "y%\\",
+            "sub" => "sub|x",
+            x => x,
+        }
+    }
+    let BlifEntry::Model(model) = &mut ast.entries[0];
+    for x in model.meta.inputs.iter_mut().flatten() {
+        *x = rename(x).into();
+    }
+    for x in model.meta.outputs.iter_mut().flatten() {
+        *x = rename(x).into();
+    }
+    for cmd in &mut model.commands {
+        match &mut cmd.kind {
+            ModelCmdKind::Gate(gate) => {
+                for x in gate.meta.inputs.iter_mut() {
+                    *x = rename(x).into();
+                }
+                gate.meta.output = rename(&gate.meta.output).into();
+            }
+            ModelCmdKind::SubModel { name, map, .. } => {
+                *name = rename(name).into();
+                map[0].1 = rename(&map[0].1).into();
+            }
+            _ => {}
+        }
+    }
+
+    let problems = validate_names(&ast, BlifFlavor::Yosys);
+    assert_eq!(
+        problems
+            .iter()
+            .map(|x| (x.name.as_str(), x.problem))
+            .collect::<Vec<_>>(),
+        vec![
+            ("a#1", NameProblem::Char('#')),
+            ("b<2> c", NameProblem::Char('<')),
+            ("y%\\", NameProblem::TrailingBackslash),
+            ("sub|x", NameProblem::Char('|')),
+        ]
+    );
+    assert_eq!(
+        problems[0].to_string(),
+        "model `top`: name `a#1` contains '#'"
+    );
+    assert_eq!(
+        validate_names(&ast, BlifFlavor::Standard)[1].problem,
+        NameProblem::Char(' ')
+    );
+
+    let opts = |name_escaping| WriteOptions {
+        name_escaping,
+        ..WriteOptions::new(BlifFlavor::Yosys)
+    };
+    assert_eq!(
+        writer::blif_to_string(&ast, opts(NameEscaping::Yosys)),
+        ".model top\n.inputs a?1 b?2??c\n.outputs y%?\n.names a?1 b?2??c y%?\n11 1\n.subckt sub?x x=a?1\n.end\n"
+    );
+    assert_eq!(
+        writer::blif_to_string(&ast, opts(NameEscaping::Vtr)),
+        ".model top\n.inputs a_1 b_2__c\n.outputs y%_\n.names a_1 b_2__c y%_\n11 1\n.subckt sub_x x=a_1\n.end\n"
+    );
+    assert!(writer::write_blif(&ast, &mut String::new(), opts(NameEscaping::Error)).is_err());
+
+    let mangled = writer::blif_to_string(&ast, opts(NameEscaping::Mangle));
+    assert_eq!(
+        mangled,
+        ".model top\n.inputs a%231 b%3C2%3E%20c\n.outputs y%25%5C\n.names a%231 b%3C2%3E%20c y%25%5C\n11 1\n.subckt sub%7Cx x=a%231\n.end\n"
+    );
+    let back = parse_str_blif_to_ast("top.blif", &mangled).unwrap();
+    let BlifEntry::Model(back) = &back.entries[0];
+    let BlifEntry::Model(model) = &ast.entries[0];
+    let demangled = back
+        .meta
+        .inputs
+        .iter()
+        .flatten()
+        .chain(back.meta.outputs.iter().flatten())
+        .map(|x| demangle_name(x).unwrap().into_owned())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        demangled,
+        model
+            .meta
+            .inputs
+            .iter()
+            .flatten()
+            .chain(model.meta.outputs.iter().flatten())
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+    );
+
+    assert_eq!(
+        check_name("->", NameKind::Net, BlifFlavor::SisMV),
+        Err(NameProblem::Reserved)
+    );
+    assert_eq!(mangle_name("->", NameKind::Net, BlifFlavor::SisMV), "%2D>");
+    assert_eq!(mangle_name("", NameKind::Net, BlifFlavor::SisMV), "%");
+    assert_eq!(demangle_name("%").unwrap(), "");
+    assert_eq!(demangle_name("%2"), None);
+}
+
+#[test]
+fn netlist_connectivity() {
+    use netlist::*;
+
+    let blif = parse_str_blif_to_ast(
+        "top.blif",
+        r#"
+.model top
+.inputs a b
+.outputs y z
+.clock clk
+.subckt sub i=a o=t
+.gate AND2 A=t B=b Y=u
+.conn u v
+.latch v y re clk 0
+.names a b z
+11 1
+.names b z
+0 1
+.exdc
+.names a y
+1 1
+.end
+
+.model sub
+.inputs i
+.outputs o
+.names i o
+0 1
+.end
+"#,
+    )
+    .unwrap();
+    let top = blif.model("top").unwrap();
+
+    let netlist = Netlist::in_blif(top, &blif);
+    let net = |name| netlist.net(netlist.net_id(name).unwrap());
+    let cell_of = |driver: Option<&Driver>| match driver {
+        Some(Driver::Pin(pin)) => netlist.cell(pin.cell).cmd_index,
+        x => panic!("{x:?}"),
+    };
+
+    assert_eq!(netlist.inputs().len(), 3);
+    assert_eq!(netlist.outputs().len(), 2);
+    assert_eq!(net("a").drivers, vec![Driver::Input]);
+    assert_eq!(net("a").fanout.len(), 2);
+    assert_eq!(cell_of(net("t").driver()), 0);
+    assert_eq!(net("u").names, vec!["u", "v"]);
+    assert_eq!(netlist.net_id("u"), netlist.net_id("v"));
+    assert_eq!(cell_of(net("v").driver()), 1);
+    assert_eq!(cell_of(net("y").driver()), 3);
+    assert!(net("y").is_output);
+    assert_eq!(net("clk").fanout.len(), 1);
+    // the .exdc gate is not a cell
+    assert_eq!(netlist.cells().len(), 5);
+    assert_eq!(net("z").drivers.len(), 2);
+
+    // without the library, subckt pins have no direction
+    let netlist = Netlist::new(top);
+    let t = netlist.net(netlist.net_id("t").unwrap());
+    assert!(t.drivers.is_empty());
+    assert_eq!(t.unknown.len(), 1);
+    assert_eq!(
+        netlist.pin(t.unknown[0]),
+        &Pin {
+            formal: Some("o"),
+            net: netlist.net_id("t").unwrap(),
+            dir: PinDir::Unknown
+        }
+    );
+
+    let source = std::fs::read_to_string("blif-examples-from-mvsis/C880.blif").unwrap();
+    let blif = parse_str_blif_to_ast("C880.blif", &source).unwrap();
+    let netlist = Netlist::new(blif.models().next().unwrap());
+    for (_, net) in netlist.nets() {
+        assert_eq!(net.drivers.len(), 1, "{}", net.name());
+    }
+}
+
+#[test]
+fn validate_finds_broken_designs() {
+    use validate::*;
+
+    let blif = parse_str_blif_to_ast(
+        "top.blif",
+        r#"
+.model top
+.inputs a b unused
+.outputs y z never
+.clock clk
+.names a b y
+11 1
+00 0
+.names a y
+1 1
+.names a t
+10 1
+.latch t z re clk 0
+.latch t w re clk2 0
+.names missing w2
+1 1
+.subckt box x=a q=b
+.end
+
+.model box
+.inputs x
+.outputs o
+.blackbox
+.end
+"#,
+    )
+    .unwrap();
+
+    let diags = validate(&blif)
+        .into_iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        diags,
+        vec![
+            "error: model `top`, command 1, net `y`: net has 2 drivers",
+            "error: model `top`, net `never`: output is never driven",
+            "error: model `top`, command 4, net `clk2`: net is read but never driven",
+            "error: model `top`, command 5, net `missing`: net is read but never driven",
+            "warning: model `top`, net `unused`: input is never used",
+            "error: model `top`, command 0, net `y`: truth table mixes on-set and off-set rows",
+            "error: model `top`, command 2, net `t`: truth-table row has 2 input values, but the gate has 1 inputs",
+            "warning: model `top`, command 4, net `clk2`: latch clock is not declared in `.clock`",
+            "error: model `top`, command 6: model `box` has no port `q`",
+        ]
+    );
+
+    // omitted ports are inferred, like `Hierarchy` does
+    let blif = parse_str_blif_to_ast(
+        "top.blif",
+        ".model top\n.inputs a\n.outputs y\n.subckt inv i=a o=y x=a\n.end\n\
+         .model inv\n.names i o\n0 1\n.end\n",
+    )
+    .unwrap();
+    let diags = validate(&blif)
+        .into_iter()
+        .filter(|x| matches!(x.kind, DiagnosticKind::UnknownFormal { .. }))
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        diags,
+        vec!["error: model `top`, command 0: model `inv` has no port `x`"]
+    );
+    let hier = hierarchy::Hierarchy::new(&blif);
+    assert_eq!(
+        hier.errors(),
+        &[hierarchy::HierarchyError::UnknownFormal {
+            parent: "top".into(),
+            command: 0,
+            model: "inv".into(),
+            formal: "x".into(),
+        }]
+    );
+
+    for file in ["C880.blif", "frg2.blif", "pj1.blif"] {
+        let source = std::fs::read_to_string(format!("blif-examples-from-mvsis/{file}")).unwrap();
+        let blif = parse_str_blif_to_ast(file, &source).unwrap();
+        let errors = validate(&blif)
+            .into_iter()
+            .filter(|x| x.severity == Severity::Error)
+            .collect::<Vec<_>>();
+        assert_eq!(errors, vec![], "{file}");
+    }
+}
+
+#[test]
+fn infer_omitted_ports() {
+    let source = r#"
+.model sub
+.inputs i
+.outputs o
+.names i o
+0 1
+.end
+
+.model top
+.names b a t
+11 1
+.latch t q re clk 0
+.subckt sub i=q o=r
+.names r s
+1 1
+.conn s y
+.exdc
+.names dc y
+1 1
+.end
+"#;
+    let names = |x: &Option<Vec<Str<16>>>| {
+        x.as_ref()
+            .unwrap()
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let mut blif = ast::Blif::with_port_inference();
+    parse_blif("top.blif", &mut blif, source.split('\n')).unwrap();
+    let top = blif.model("top").unwrap();
+    assert_eq!(names(&top.meta.inputs), vec!["b", "a", "clk"]);
+    assert_eq!(names(&top.meta.outputs), vec!["y"]);
+    assert_eq!(names(&blif.model("sub").unwrap().meta.inputs), vec!["i"]);
+
+    // the same after parsing, for all models
+    let mut parsed = parse_str_blif_to_ast("top.blif", source).unwrap();
+    assert!(parsed.model("top").unwrap().meta.inputs.is_none());
+    parsed.infer_ports();
+    assert_eq!(parsed.entries, blif.entries);
+
+    // without the other models, subckt pins have no known direction
+    let mut top = parse_str_blif_to_ast("top.blif", source)
+        .unwrap()
+        .model("top")
+        .unwrap()
+        .clone();
+    top.infer_ports();
+    assert_eq!(names(&top.meta.inputs), vec!["b", "a", "clk"]);
+    assert_eq!(names(&top.meta.outputs), vec!["y"]);
+    let BlifEntry::Model(first) =
+        &parse_str_blif_to_ast("top.blif", ".model m\n.subckt sub i=a o=b\n.end\n")
+            .unwrap()
+            .entries[0];
+    let mut first = first.clone();
+    first.infer_ports();
+    assert_eq!(names(&first.meta.inputs), Vec::<String>::new());
+    assert_eq!(names(&first.meta.outputs), Vec::<String>::new());
+}
+
+#[test]
+fn hierarchy_resolution() {
+    use hierarchy::{Hierarchy, HierarchyError};
+
+    let blif = parse_str_blif_to_ast(
+        "top.blif",
+        r#"
+.model top
+.inputs a b
+.outputs y
+.subckt half|u0 x=a y=b s=t
+.subckt half|u1 x=t s=y q=a
+.subckt ram d=a
+.subckt ram d=b
+.subckt bb i=a o=y
+.end
+
+.model half
+.inputs x y
+.outputs s
+.names x y s
+01 1
+10 1
+.end
+
+.model bb
+.inputs i
+.outputs o
+.blackbox
+.end
+
+.model loop_a
+.subckt loop_b
+.end
+
+.model loop_b
+.subckt loop_a
+.end
+
+.model unused
+.end
+"#,
+    )
+    .unwrap();
+    let hier = Hierarchy::new(&blif);
+
+    let top = |x: Vec<&ast::Model>| {
+        x.iter()
+            .map(|m| m.meta.name.to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(top(hier.tops()), vec!["top", "unused"]);
+    assert!(hier.top().is_none());
+    assert_eq!(hier.missing(), vec!["ram"]);
+    assert_eq!(top(hier.bottom_up("top")), vec!["half", "bb", "top"]);
+
+    let insts: Vec<_> = hier.instances_in("top").collect();
+    assert_eq!(insts.len(), 5);
+    assert_eq!(insts[1].instance_name, Some("u1"));
+    assert!(insts[4].target.unwrap().attr.blackbox);
+
+    assert_eq!(
+        hier.errors(),
+        &[
+            HierarchyError::UnknownFormal {
+                parent: "top".into(),
+                command: 1,
+                model: "half".into(),
+                formal: "q".into(),
+            },
+            HierarchyError::UnconnectedInput {
+                parent: "top".into(),
+                command: 1,
+                model: "half".into(),
+                formal: "y".into(),
+            },
+            HierarchyError::MissingModel {
+                parent: "top".into(),
+                command: 2,
+                model: "ram".into(),
+            },
+            HierarchyError::MissingModel {
+                parent: "top".into(),
+                command: 3,
+                model: "ram".into(),
+            },
+            HierarchyError::Recursion {
+                cycle: vec!["loop_a".into(), "loop_b".into(), "loop_a".into()],
+            },
+        ]
+    );
+    assert_eq!(
+        hier.errors()[4].to_string(),
+        "recursive instantiation: loop_a -> loop_b -> loop_a"
+    );
+}
+
+#[test]
+fn flatten_hierarchy() {
+    use flatten::{FlattenError, FlattenOptions, flatten, flatten_with};
+    use hierarchy::HierarchyError;
+
+    let blif = parse_str_blif_to_ast(
+        "top.blif",
+        r#"
+.model top
+.inputs a b
+.outputs y z
+.subckt half|h0 x=a y=b s=t
+.subckt half x=t y=a s=y
+.cname second
+.subckt ram d=t q=z
+.end
+
+.model half
+.inputs x y
+.outputs s
+.subckt xor a=x b=y o=s
+.end
+
+.model xor
+.inputs a b
+.outputs o
+.names a n
+0 1
+.attr src "xor.v:3"
+.cname inv
+.names n b o
+01 1
+10 1
+.end
+
+.model ram
+.inputs d
+.outputs q
+.blackbox
+.end
+"#,
+    )
+    .unwrap();
+
+    let opts = FlattenOptions {
+        separator: "/".to_string(),
+    };
+    let flat = flatten_with(&blif, "top", &opts).unwrap();
+    assert_eq!(flat.meta, blif.model("top").unwrap().meta);
+
+    let gate = |idx: usize| match &flat.commands[idx].kind {
+        ModelCmdKind::Gate(gate) => {
+            let mut nets: Vec<_> = gate.meta.inputs.iter().map(|x| x.to_string()).collect();
+            nets.push(gate.meta.output.to_string());
+            nets
+        }
+        x => panic!("{x:?}"),
+    };
+    assert_eq!(flat.commands.len(), 5);
+    assert_eq!(gate(0), vec!["a", "h0/xor_0/n"]);
+    assert_eq!(gate(1), vec!["h0/xor_0/n", "b", "t"]);
+    assert_eq!(gate(2), vec!["t", "second/xor_0/n"]);
+    assert_eq!(gate(3), vec!["second/xor_0/n", "a", "y"]);
+    assert_eq!(
+        flat.commands[0].attrs,
+        vec![
+            CellAttrAst::Attr {
+                key: "src".into(),
+                val: "\"xor.v:3\"".into()
+            },
+            CellAttrAst::CellName("h0/xor_0/inv".into()),
+        ]
+    );
+    assert_eq!(
+        flat.commands[4].kind,
+        ModelCmdKind::SubModel {
+            name: "ram".into(),
+            map: vec![("d".into(), "t".into()), ("q".into(), "z".into())],
+            instance_name: None,
+        }
+    );
+
+    // the default separator
+    let flat = flatten(&blif, "half").unwrap();
+    assert_eq!(
+        flat.commands[0].kind,
+        ModelCmdKind::Gate(ast::Gate {
+            meta: GateMeta {
+                inputs: vec!["x".into()],
+                output: "xor_0.n".into(),
+                external_dc: false,
+                syntax: LutSyntax::Names,
+            },
+            lut: ast::LUT(vec![(smallvec::smallvec![Tristate::False], Some(true))]),
+        })
+    );
+
+    assert_eq!(
+        flatten(&blif, "nope"),
+        Err(FlattenError::MissingModel {
+            parent: None,
+            model: "nope".into()
+        })
+    );
+
+    let blif = parse_str_blif_to_ast(
+        "loop.blif",
+        ".model a\n.subckt b\n.end\n.model b\n.subckt c\n.end\n.model c\n.subckt b\n.end\n",
+    )
+    .unwrap();
+    let err = flatten(&blif, "a").unwrap_err();
+    assert_eq!(
+        err,
+        FlattenError::Hierarchy(HierarchyError::Recursion {
+            cycle: vec!["b".into(), "c".into(), "b".into()],
+        })
+    );
+    assert_eq!(err.to_string(), "recursive instantiation: b -> c -> b");
+
+    // a cycle that `top` doesn't reach is fine
+    let blif = parse_str_blif_to_ast(
+        "loop.blif",
+        ".model top\n.inputs a\n.outputs y\n.names a y\n1 1\n.end\n\
+         .model b\n.subckt c\n.end\n.model c\n.subckt b\n.end\n",
+    )
+    .unwrap();
+    assert!(flatten(&blif, "top").is_ok());
+
+    // inlined nets and instance labels don't clash with existing ones
+    let blif = parse_str_blif_to_ast(
+        "clash.blif",
+        r#"
+.model top
+.inputs a
+.outputs x y z
+.subckt inv|u0 i=a o=x
+.names a u0.t
+1 1
+.names u0.t y
+0 1
+.subckt inv i=a o=y2
+.subckt inv i=a o=z
+.cname inv_3
+.end
+
+.model inv
+.inputs i
+.outputs o
+.names i t
+0 1
+.names t o
+0 1
+.end
+"#,
+    )
+    .unwrap();
+    let flat = flatten(&blif, "top").unwrap();
+    let outputs: Vec<&str> = flat
+        .commands
+        .iter()
+        .map(|cmd| match &cmd.kind {
+            ModelCmdKind::Gate(gate) => gate.meta.output.as_str(),
+            _ => panic!(),
+        })
+        .collect();
+    assert_eq!(
+        outputs,
+        vec![
+            "u0.t_1",
+            "x",
+            "u0.t",
+            "y",
+            "inv_3_1.t",
+            "y2",
+            "inv_3.t",
+            "z"
+        ]
+    );
+}
+
+#[test]
+fn uniquify_and_dedupe() {
+    use hierarchy::{HierarchyError, dedupe, uniquify};
+
+    let source = r#"
+.model top
+.inputs a b c
+.outputs x y z
+.subckt alu|alu0 a=a b=b o=x
+.subckt alu|alu1 a=b b=c o=y
+.subckt inv i=a o=z
+.subckt bb i=a
+.end
+
+.model alu
+.inputs a b
+.outputs o
+.subckt adder|u0 a=a b=b s=t
+.subckt inv i=t o=o
+.end
+
+.model adder
+.inputs a b
+.outputs s
+.names a b s
+01 1
+10 1
+.end
+
+.model inv
+.inputs i
+.outputs o
+.names i o
+0 1
+.end
+
+.model bb
+.inputs i
+.blackbox
+.end
+"#;
+    let names = |blif: &ast::Blif| {
+        blif.models()
+            .map(|m| m.meta.name.to_string())
+            .collect::<Vec<_>>()
+    };
+    let targets = |blif: &ast::Blif, model: &str| {
+        blif.model(model)
+            .unwrap()
+            .commands
+            .iter()
+            .filter_map(|cmd| match &cmd.kind {
+                ModelCmdKind::SubModel { name, .. } => Some(name.to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let original = parse_str_blif_to_ast("top.blif", source).unwrap();
+    let mut blif = parse_str_blif_to_ast("top.blif", source).unwrap();
+    uniquify(&mut blif).unwrap();
+    assert_eq!(
+        names(&blif),
+        vec![
+            "top",
+            "alu__alu0",
+            "alu__alu1",
+            "adder__alu0__u0",
+            "adder__alu1__u0",
+            "inv__alu0__inv_1",
+            "inv__alu1__inv_1",
+            "inv__inv_2",
+            "bb",
+        ]
+    );
+    assert_eq!(
+        targets(&blif, "top"),
+        vec!["alu__alu0", "alu__alu1", "inv__inv_2", "bb"]
+    );
+    assert_eq!(
+        targets(&blif, "alu__alu1"),
+        vec!["adder__alu1__u0", "inv__alu1__inv_1"]
+    );
+    assert_eq!(
+        blif.model("adder__alu0__u0").unwrap().commands,
+        original.model("adder").unwrap().commands
+    );
+
+    // dedupe restores the original design, up to the order of the models
+    assert_eq!(dedupe(&mut blif), 4);
+    assert_eq!(names(&blif), vec!["top", "alu", "adder", "inv", "bb"]);
+    for model in original.models() {
+        assert_eq!(blif.model(&model.meta.name), Some(model));
+    }
+    assert_eq!(dedupe(&mut blif), 0);
+
+    // order of cells, rows and pins and names of internal nets don't matter
+    let mut blif = parse_str_blif_to_ast(
+        "same.blif",
+        r#"
+.model x1
+.inputs a b
+.outputs y
+.names a b t
+01 1
+10 1
+.subckt inv i=t o=y
+.end
+
+.model x2
+.inputs a b
+.outputs y
+.subckt inv o=y i=n
+.names a b n
+10 1
+01 1
+.end
+
+.model x3
+.inputs a b
+.outputs y
+.names b a t
+01 1
+10 1
+.subckt inv i=t o=y
+.end
+"#,
+    )
+    .unwrap();
+    assert_eq!(dedupe(&mut blif), 1);
+    assert_eq!(names(&blif), vec!["x1", "x3"]);
+
+    // models reached once keep their name
+    let mut blif = parse_str_blif_to_ast(
+        "once.blif",
+        ".model top\n.subckt mid\n.end\n.model mid\n.subckt leaf\n.end\n.model leaf\n.end\n",
+    )
+    .unwrap();
+    uniquify(&mut blif).unwrap();
+    assert_eq!(names(&blif), vec!["top", "mid", "leaf"]);
+
+    let mut blif =
+        parse_str_blif_to_ast("loop.blif", ".model a\n.subckt a\n.end\n.model b\n.end\n").unwrap();
+    assert_eq!(
+        uniquify(&mut blif),
+        Err(HierarchyError::Recursion {
+            cycle: vec!["a".into(), "a".into()]
+        })
+    );
+}
+
+#[test]
+fn topological_order() {
+    use topo::{CombinationalLoop, topo_order, topo_order_cells};
+
+    let source = r#"
+.model loops
+.inputs a clk
+.outputs y z
+.clock clk
+.names x a y
+11 1
+.names y x
+1 1
+.latch y q re clk 0
+.names q a z
+10 1
+.names w w
+0 1
+.names z v
+1 1
+.end
+"#;
+    let blif = parse_str_blif_to_ast("loops.blif", source).unwrap();
+    let err = topo_order(blif.model("loops").unwrap()).unwrap_err();
+    assert_eq!(
+        err.loops,
+        vec![
+            CombinationalLoop {
+                commands: vec![0, 1],
+                nets: vec!["y".into(), "x".into()],
+            },
+            CombinationalLoop {
+                commands: vec![4],
+                nets: vec!["w".into()],
+            },
+        ]
+    );
+    assert_eq!(
+        err.to_string(),
+        "model `loops`: combinational loop through nets `y`, `x` (commands 0, 1); \
+         combinational loop through net `w` (command 4)"
+    );
+
+    // cutting the loop with the latch
+    let fixed = source.replace(".names y x", ".names q x");
+    let blif = parse_str_blif_to_ast("loops.blif", &fixed).unwrap();
+    let mut fixed = blif.model("loops").unwrap().clone();
+    fixed.commands.remove(4);
+    assert_eq!(topo_order(&fixed).unwrap(), vec![2, 1, 0, 3, 4]);
+
+    for file in ["C880.blif", "apex6.blif", "frg2.blif", "pj1.blif"] {
+        let source = std::fs::read_to_string(format!("blif-examples-from-mvsis/{file}")).unwrap();
+        let blif = parse_str_blif_to_ast(file, &source).unwrap();
+        for model in blif.models() {
+            let netlist = netlist::Netlist::new(model);
+            let order = topo_order_cells(&netlist).unwrap();
+            assert_eq!(order.len(), netlist.cells().len());
+
+            let mut done = vec![false; netlist.net_count()];
+            for &net in netlist.inputs() {
+                done[net.0] = true;
+            }
+            for cell in order.iter().map(|&c| netlist.cell(c)) {
+                if !matches!(cell.cmd.kind, ModelCmdKind::FF(_) | ModelCmdKind::LibFF(_)) {
+                    for pin in cell.pins.iter().filter(|p| p.dir == netlist::PinDir::Input) {
+                        let net = netlist.net(pin.net);
+                        assert!(done[pin.net.0] || net.drivers.is_empty(), "{file}");
+                    }
+                }
+                for pin in cell
+                    .pins
+                    .iter()
+                    .filter(|p| p.dir == netlist::PinDir::Output)
+                {
+                    done[pin.net.0] = true;
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn lut_truth_tables() {
+    use lut::{TruthTable, TruthTableError};
+
+    fn lut(rows: &[&str]) -> ast::LUT {
+        ast::LUT(
+            rows.iter()
+                .map(|row| {
+                    let (ins, out) = row.split_once(' ').unwrap();
+                    let ins = ins.chars().map(|c| c.to_string().parse().unwrap());
+                    (ins.collect(), Some(out == "1"))
+                })
+                .collect(),
+        )
+    }
+
+    // off-set rows: a NAND
+    let nand = lut(&["11 0"]);
+    assert!(nand.eval(&[false, true]));
+    assert!(!nand.eval(&[true, true]));
+    assert_eq!(nand.truth_table(2).unwrap().words(), &[0b0111]);
+
+    // no rows is constant 0, a single empty row constant 1
+    assert!(!lut(&[]).eval(&[]));
+    assert_eq!(lut(&[]).is_constant(), Some(false));
+    assert_eq!(
+        ast::LUT(vec![(SmallVec::new(), Some(true))]).is_constant(),
+        Some(true)
+    );
+    assert_eq!(lut(&["-- 1"]).is_constant(), Some(true));
+    assert_eq!(lut(&["1- 1", "0- 1"]).is_constant(), Some(true));
+    assert_eq!(nand.is_constant(), None);
+
+    assert_eq!(lut(&["-1 1"]).is_buffer(), Some(1));
+    assert_eq!(lut(&["-0 1"]).is_inverter(), Some(1));
+    assert_eq!(lut(&["1 0"]).is_inverter(), Some(0));
+    assert_eq!(lut(&["1 0"]).is_buffer(), None);
+    assert_eq!(nand.is_buffer(), None);
+    assert_eq!(lut(&["-- 1"]).is_buffer(), None);
+
+    assert_eq!(
+        lut(&["1 1"]).truth_table(2),
+        Err(TruthTableError::RowWidth {
+            expected: 2,
+            found: 1
+        })
+    );
+    assert_eq!(
+        lut(&[]).truth_table(17),
+        Err(TruthTableError::TooManyInputs(17))
+    );
+
+    // the biggest supported table
+    let and16 = lut(&["1111111111111111 1"]).truth_table(16).unwrap();
+    assert_eq!(and16.count_ones(), 1);
+    assert!(and16.get(0xffff));
+    assert_eq!(ast::LUT::from_truth_table(&and16).0.len(), 1);
+
+    // covers are exact, made of primes, and no bigger than the on- or off-set
+    for bits in 0..256u64 {
+        let tt = TruthTable::from_fn(3, |m| bits >> m & 1 == 1);
+        let cover = ast::LUT::from_truth_table(&tt);
+        assert_eq!(cover.truth_table(3).unwrap(), tt, "{bits:08b}");
+        assert!(cover.0.len() <= 4, "{bits:08b}: {cover}");
+    }
+    let xor = TruthTable::from_fn(2, |m| m == 1 || m == 2);
+    assert_eq!(ast::LUT::from_truth_table(&xor), lut(&["10 1", "01 1"]));
+    let or = TruthTable::from_fn(2, |m| m != 0);
+    assert_eq!(ast::LUT::from_truth_table(&or), lut(&["00 0"]));
+}
+
+#[test]
+fn cube_cover_algebra() {
+    use cube::{Cover, Cube};
+    use lut::TruthTable;
+
+    let c = |s: &str| Cube::parse(s).unwrap();
+
+    assert_eq!(c("1-0").intersect(&c("-10")), Some(c("110")));
+    assert_eq!(c("1-0").intersect(&c("0-1")), None);
+    assert_eq!(c("1-0").distance(&c("0-1")), 2);
+    assert!(c("1--").contains(&c("1-0")));
+    assert!(!c("1-0").contains(&c("1--")));
+    assert_eq!(c("1-0").cofactor(&c("1--")), Some(c("--0")));
+    assert_eq!(c("1-0").supercube(&c("011")), c("---"));
+    assert_eq!(c("1-0").literals(), 2);
+    assert_eq!(c("1-0").to_string(), "1-0");
+
+    // more than one word
+    let wide = "1-0".repeat(30);
+    assert_eq!(c(&wide).to_string(), wide);
+    assert_eq!(c(&wide).literals(), 60);
+    assert_eq!(c(&wide).distance(&c(&"0-1".repeat(30))), 60);
+    assert!(Cube::universe(90).contains(&c(&wide)));
+
+    let to_tt = |cover: &Cover| {
+        TruthTable::from_fn(cover.vars(), |m| {
+            let inputs: Vec<bool> = (0..cover.vars()).map(|i| m >> i & 1 == 1).collect();
+            cover.eval(&inputs)
+        })
+    };
+
+    // every cover of up to 3 cubes over 3 inputs, against truth tables
+    let rows: Vec<Cube> = (0..27)
+        .map(|mut x| {
+            let row: String = (0..3)
+                .map(|_| {
+                    let ch = ["0", "1", "-"][x % 3];
+                    x /= 3;
+                    ch
+                })
+                .collect();
+            c(&row)
+        })
+        .collect();
+    for a in &rows {
+        for b in &rows {
+            for d in rows.iter().step_by(4) {
+                let f = Cover::from_cubes(3, [a.clone(), b.clone(), d.clone()]);
+                let tt = to_tt(&f);
+                let not_f = f.complement();
+                assert_eq!(to_tt(&not_f), !tt.clone(), "{f}");
+                assert_eq!(f.is_tautology(), tt.constant() == Some(true), "{f}");
+                assert!(f.union(&not_f).is_tautology());
+                assert!(f.intersect(&not_f).is_empty());
+                assert!(f.contains_cube(a));
+                let g = Cover::from_cubes(3, [b.clone()]);
+                assert_eq!(
+                    to_tt(&f.cofactor(b)).count_ones() > 0,
+                    !f.intersect(&g).is_empty()
+                );
+            }
+        }
+    }
+
+    // off-set LUTs are complemented
+    let nand = ast::LUT(vec![(
+        smallvec::smallvec![Tristate::True, Tristate::True],
+        Some(false),
+    )]);
+    assert_eq!(
+        Cover::on_set(&nand, 2),
+        Cover::from_cubes(2, [c("0-"), c("-0")])
+    );
+
+    // the biggest gate of pj1
+    let source = std::fs::read_to_string("blif-examples-from-mvsis/pj1.blif").unwrap();
+    let blif = parse_str_blif_to_ast("pj1.blif", &source).unwrap();
+    let gate = blif
+        .models()
+        .flat_map(|m| &m.commands)
+        .filter_map(|cmd| match &cmd.kind {
+            ModelCmdKind::Gate(gate) => Some(gate),
+            _ => None,
+        })
+        .max_by_key(|g| g.lut.0.len())
+        .unwrap();
+    let f = Cover::on_set(&gate.lut, gate.meta.inputs.len());
+    let not_f = f.complement();
+    assert!(f.union(&not_f).is_tautology());
+    assert!(f.intersect(&not_f).is_empty());
+    assert!(f.equivalent(&not_f.complement()));
+}
+
+#[test]
+fn minimize_covers() {
+    use cube::{Cover, Cube};
+    use minimize::{MinimizeMode, minimize_cover, minimize_model};
+
+    let cover = |n: usize, bits: u32| {
+        let cubes = (0..1usize << n).filter(|m| bits >> m & 1 == 1).map(|m| {
+            let row: String = (0..n)
+                .map(|i| if m >> i & 1 == 1 { '1' } else { '0' })
+                .collect();
+            Cube::parse(&row).unwrap()
+        });
+        Cover::from_cubes(n, cubes)
+    };
+    let cost = |c: &Cover| (c.len(), c.cubes().iter().map(Cube::literals).sum::<usize>());
+
+    // every 3-input function, with a few don't-care sets
+    for on_bits in 0..256u32 {
+        for dc_bits in [0u32, 0b1000_0001, 0b0110_0000] {
+            let dc_bits = dc_bits & !on_bits;
+            let on = cover(3, on_bits);
+            let dc = cover(3, dc_bits);
+            let upper = on.union(&dc);
+
+            let heuristic = minimize_cover(&on, &dc, MinimizeMode::Heuristic);
+            let exact = minimize_cover(&on, &dc, MinimizeMode::Exact);
+            for min in [&heuristic, &exact] {
+                assert!(min.contains(&on), "{on_bits:08b} {dc_bits:08b}: {min}");
+                assert!(upper.contains(min), "{on_bits:08b} {dc_bits:08b}: {min}");
+            }
+            assert!(cost(&exact) <= cost(&heuristic), "{on_bits:08b}");
+        }
+    }
+
+    // the exact minimum of a function where greedy choices go wrong
+    let f = Cover::from_cubes(
+        3,
+        ["00-", "-01", "1-1", "11-", "-10", "0-0"].map(|c| Cube::parse(c).unwrap()),
+    );
+    assert_eq!(
+        minimize_cover(&f, &Cover::new(3), MinimizeMode::Exact).len(),
+        3
+    );
+
+    // pseudo-random gates with many inputs finish in time
+    for n in [8, 10] {
+        let minterm = |m: usize| {
+            let row: String = (0..n)
+                .map(|i| if m >> i & 1 == 1 { '1' } else { '0' })
+                .collect();
+            Cube::parse(&row).unwrap()
+        };
+        let hash = |m: usize| m.wrapping_mul(0x9e37_79b9) >> 11 & 7;
+        let on = Cover::from_cubes(n, (0..1 << n).filter(|&m| hash(m) < 4).map(minterm));
+        let dc = Cover::from_cubes(n, (0..1 << n).filter(|&m| hash(m) == 4).map(minterm));
+
+        let heuristic = minimize_cover(&on, &dc, MinimizeMode::Heuristic);
+        let exact = minimize_cover(&on, &dc, MinimizeMode::Exact);
+        assert!(exact.contains(&on), "{n}");
+        assert!(on.union(&dc).contains(&exact), "{n}");
+        assert!(cost(&exact) <= cost(&heuristic), "{n}");
+    }
+
+    // the external don't-care network
+    let mut blif = parse_str_blif_to_ast(
+        "dc.blif",
+        r#"
+.model dc
+.inputs a b c
+.outputs y
+.names a b c y
+111 1
+110 1
+100 1
+.exdc
+.names a c y
+11 1
+.end
+"#,
+    )
+    .unwrap();
+    let ast::BlifEntry::Model(model) = &mut blif.entries[0];
+    let stats = minimize_model(model, MinimizeMode::Exact);
+    assert_eq!((stats.gates, stats.rewritten), (1, 1));
+    assert_eq!((stats.cubes_after, stats.literals_after), (1, 1));
+    let ModelCmdKind::Gate(gate) = &model.commands[0].kind else {
+        panic!()
+    };
+    assert_eq!(gate.lut.to_string(), "1-- 1\n");
+
+    // the same with the don't-cares spread over several `.exdc` gates
+    let mut blif = parse_str_blif_to_ast(
+        "exdc.blif",
+        r#"
+.model exdc
+.inputs a b c
+.outputs y
+.names a b c y
+111 1
+110 1
+100 1
+.exdc
+.names a t
+1 1
+.exdc
+.names t c y
+11 1
+.end
+"#,
+    )
+    .unwrap();
+    let ast::BlifEntry::Model(model) = &mut blif.entries[0];
+    minimize_model(model, MinimizeMode::Exact);
+    let ModelCmdKind::Gate(gate) = &model.commands[0].kind else {
+        panic!()
+    };
+    assert_eq!(gate.lut.to_string(), "1-- 1\n");
+
+    // real designs stay equivalent
+    for file in ["C880.blif", "pj1.blif"] {
+        let source = std::fs::read_to_string(format!("blif-examples-from-mvsis/{file}")).unwrap();
+        let original = parse_str_blif_to_ast(file, &source).unwrap();
+        let mut blif = parse_str_blif_to_ast(file, &source).unwrap();
+        let ast::BlifEntry::Model(model) = &mut blif.entries[0];
+        let stats = minimize_model(model, MinimizeMode::Heuristic);
+        assert!(
+            stats.literals_after <= stats.literals_before,
+            "{file}: {stats}"
+        );
+        assert!(stats.cubes_after <= stats.cubes_before, "{file}: {stats}");
+
+        let before = original.models().next().unwrap();
+        for (a, b) in before.commands.iter().zip(&model.commands) {
+            if let (ModelCmdKind::Gate(a), ModelCmdKind::Gate(b)) = (&a.kind, &b.kind) {
+                let n = a.meta.inputs.len();
+                assert!(Cover::on_set(&a.lut, n).equivalent(&Cover::on_set(&b.lut, n)));
+            }
+        }
+    }
+}
+
+#[test]
+fn sweep_cleans_up() {
+    use sweep::{SweepStats, sweep};
+
+    let mut blif = parse_str_blif_to_ast(
+        "sweep.blif",
+        r#"
+.model sweep
+.inputs a b c clk
+.outputs y z w
+.clock clk
+.names $false
+.names $true
+1
+.names a $false $true t0
+-01 1
+1-- 1
+.names t0 b t1
+11 1
+.names $true c t2
+10 0
+.names t1 t2 t3
+11 1
+.names t3 y
+1 1
+.latch q q re clk 1
+.names q b z
+11 1
+.latch t3 dead re clk 0
+.names dead b unused
+11 1
+.conn c w
+.subckt blackbox i=$false
+.end
+"#,
+    )
+    .unwrap();
+    let ast::BlifEntry::Model(model) = &mut blif.entries[0];
+    let stats = sweep(model);
+    assert_eq!(
+        stats,
+        SweepStats {
+            constant_gates: 1,
+            constant_inputs: 5,
+            buffers: 3,
+            constant_latches: 1,
+            dead_gates: 4,
+            dead_latches: 1,
+        }
+    );
+
+    let mut out = String::new();
+    writer::write_blif(&blif, &mut out, writer::BlifFlavor::Yosys).unwrap();
+    assert_eq!(
+        out,
+        r#".model sweep
+.inputs a b c clk
+.outputs y z w
+.clock clk
+.names $false
+.names b c y
+11 1
+.names b z
+1 1
+.conn c w
+.subckt blackbox i=$false
+.end
+"#
+    );
+
+    // cofactoring an off-set cover can leave no rows, which is constant 1
+    let mut blif = parse_str_blif_to_ast(
+        "nand.blif",
+        ".model nand\n.inputs b\n.outputs y\n.names z\n.names z b y\n11 0\n.end\n",
+    )
+    .unwrap();
+    let ast::BlifEntry::Model(model) = &mut blif.entries[0];
+    sweep(model);
+    let mut out = String::new();
+    writer::write_blif(&blif, &mut out, writer::BlifFlavor::Standard).unwrap();
+    assert_eq!(
+        out,
+        ".model nand\n.inputs b\n.outputs y\n.names y\n1\n.end\n"
+    );
+
+    // nets named by directives are read, only `g` is dead
+    let mut blif = parse_str_blif_to_ast(
+        "directives.blif",
+        r#"
+.model directives
+.inputs a b
+.outputs y
+.names a b y
+11 1
+.latch a q 0
+.gateinit q=1
+.names a b c
+10 1
+.constraint c
+.names b d
+0 1
+.onehot d
+.names a e
+0 1
+.reset e
+1
+.names a b f
+01 1
+.output_arrival f 1.0
+.names a b g
+00 1
+.end
+"#,
+    )
+    .unwrap();
+    let ast::BlifEntry::Model(model) = &mut blif.entries[0];
+    let stats = sweep(model);
+    assert_eq!(stats.dead_gates, 1);
+    assert_eq!(stats.dead_latches, 0);
+
+    // buffers are kept if timing constraints name the net they would remove
+    let source = ".model timed\n.inputs a\n.outputs y\n.names a t\n1 1\n.names t y\n0 1\n\
+                  .input_arrival t 1 1\n.end\n";
+    let mut blif = parse_str_blif_to_ast("timed.blif", source).unwrap();
+    let ast::BlifEntry::Model(model) = &mut blif.entries[0];
+    assert_eq!(sweep(model), SweepStats::default());
+    let mut out = String::new();
+    writer::write_blif(&blif, &mut out, writer::BlifFlavor::Standard).unwrap();
+    assert_eq!(out, source);
+
+    // clean designs stay valid
+    for file in ["C880.blif", "frg2.blif"] {
+        let source = std::fs::read_to_string(format!("blif-examples-from-mvsis/{file}")).unwrap();
+        let mut blif = parse_str_blif_to_ast(file, &source).unwrap();
+        let ast::BlifEntry::Model(model) = &mut blif.entries[0];
+        sweep(model);
+        let errors = validate::validate(&blif)
+            .into_iter()
+            .filter(|d| d.severity == validate::Severity::Error)
+            .count();
+        assert_eq!(errors, 0, "{file}");
+    }
+}
+
+#[test]
+fn strash_merges_duplicates() {
+    use strash::strash;
+
+    let mut blif = parse_str_blif_to_ast(
+        "strash.blif",
+        r#"
+.model strash
+.inputs a b c
+.outputs y z o1 o2
+.names a b t1
+11 1
+.cname first
+.names b a t2
+0- 0
+-0 0
+.cname second
+.names t1 c u1
+11 1
+.names c t2 u2
+11 1
+.names u1 u2 y
+1- 1
+-1 1
+.gate nand2 A=a B=b Y=n1
+.gate nand2 B=b A=a Y=n2
+.names n1 n2 z
+11 1
+.names a c o1
+11 1
+.names c a o2
+11 1
+.end
+"#,
+    )
+    .unwrap();
+    let ast::BlifEntry::Model(model) = &mut blif.entries[0];
+    // `t2`, then `u2` once it reads `t1`, and `n2`; `o2` drives a port
+    assert_eq!(strash(model), 3);
+    assert_eq!(strash(model), 0);
+
+    let mut out = String::new();
+    writer::write_blif(&blif, &mut out, writer::BlifFlavor::Yosys).unwrap();
+    assert_eq!(
+        out,
+        r#".model strash
+.inputs a b c
+.outputs y z o1 o2
+.names a b t1
+11 1
+.cname first
+.names t1 c u1
+11 1
+.names u1 u1 y
+1- 1
+-1 1
+.gate nand2 A=a B=b Y=n1
+.names n1 n1 z
+11 1
+.names a c o1
+11 1
+.names c a o2
+11 1
+.end
+"#
+    );
+}
+
+#[test]
+fn aiger_export() {
+    use aiger::{Aig, AigerError, write_aag, write_aig};
+
+    let blif = parse_str_blif_to_ast(
+        "seq.blif",
+        r#"
+.model seq
+.inputs a b clk
+.outputs y z
+.names a b x
+01 1
+10 1
+.latch x q re clk 2
+.latch q r 0
+.names q r a y
+111 0
+.conn y z
+.end
+"#,
+    )
+    .unwrap();
+    let aig = Aig::from_model(blif.model("seq").unwrap()).unwrap();
+    let mut aag = String::new();
+    write_aag(&aig, &mut aag).unwrap();
+    assert_eq!(
+        aag,
+        "aag 10 3 2 2 5
+2
+4
+6
+8 17 8
+10 8
+21
+21
+12 4 3
+14 5 2
+16 15 13
+18 10 8
+20 18 2
+i0 a
+i1 b
+i2 clk
+l0 q
+l1 r
+o0 y
+o1 z
+"
+    );
+    let mut aig_bytes = vec![];
+    write_aig(&aig, &mut aig_bytes).unwrap();
+    assert_eq!(aig_bytes[..28], b"aig 10 3 2 2 5\n17 8\n8\n21\n21\n"[..]);
+    assert_eq!(aig_bytes[28..38], [8, 1, 9, 3, 1, 2, 8, 2, 2, 16][..]);
+    assert_eq!(aig_bytes[38..], aag.as_bytes()[aag.find("i0").unwrap()..]);
+
+    // the AIG computes what the gates compute
+    let source = std::fs::read_to_string("blif-examples-from-mvsis/C880.blif").unwrap();
+    let blif = parse_str_blif_to_ast("C880.blif", &source).unwrap();
+    let model = &blif.models().next().unwrap();
+    let aig = Aig::from_model(model).unwrap();
+    assert_eq!(aig.inputs.len(), model.meta.inputs.as_ref().unwrap().len());
+    let order = topo::topo_order(model).unwrap();
+    let mut seed = 0x2545f4914f6cdd1du64;
+    for _ in 0..32 {
+        let mut nets = std::collections::HashMap::new();
+        let mut vars = vec![false; aig.max_var as usize + 1];
+        for (name, input) in model.meta.inputs.iter().flatten().zip(&aig.inputs) {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            nets.insert(name.to_string(), seed & 1 == 1);
+            vars[input.lit as usize / 2] = seed & 1 == 1;
+        }
+        for &idx in &order {
+            let ModelCmdKind::Gate(gate) = &model.commands[idx].kind else {
+                continue;
+            };
+            let inputs: Vec<bool> = gate.meta.inputs.iter().map(|x| nets[x.as_str()]).collect();
+            nets.insert(gate.meta.output.to_string(), gate.lut.eval(&inputs));
+        }
+        let lit = |vars: &[bool], lit: u32| vars[lit as usize / 2] ^ (lit & 1 == 1);
+        for and in &aig.ands {
+            vars[and.lhs as usize / 2] = lit(&vars, and.rhs0) && lit(&vars, and.rhs1);
+        }
+        for output in &aig.outputs {
+            let name = output.name.as_ref().unwrap();
+            assert_eq!(lit(&vars, output.lit), nets[name], "{name}");
+        }
+    }
+
+    let errors = [
+        (
+            ".model m\n.inputs a\n.outputs y\n.gate inv A=a Y=y\n.end\n",
+            "library cell `inv`",
+        ),
+        (
+            ".model m\n.outputs y\n.subckt sub o=y\n.end\n",
+            "flattened first",
+        ),
+        (
+            ".model m\n.inputs a\n.outputs y\n.names b y\n1 1\n.end\n",
+            "`b` is read",
+        ),
+        (
+            ".model m\n.inputs a\n.outputs y\n.names a y\n1 1\n.names a y\n0 1\n.end\n",
+            "more than one",
+        ),
+        (
+            ".model m\n.outputs y\n.names y x\n1 1\n.names x y\n1 1\n.end\n",
+            "loop",
+        ),
+    ];
+    for (source, message) in errors {
+        let blif = parse_str_blif_to_ast("m.blif", source).unwrap();
+        let err = Aig::from_model(blif.model("m").unwrap()).unwrap_err();
+        assert!(err.to_string().contains(message), "{err}");
+        if message == "loop" {
+            assert!(matches!(err, AigerError::Loop(_)));
+        }
+    }
+}
+
+#[test]
+fn aiger_import() {
+    use aiger::{Aig, AigerParseError, parse_aiger, parse_aiger_to_ast, write_aag, write_aig};
+
+    let source = "aag 7 2 2 1 3 1 1 1 1
+2
+4
+6 13 1
+8 9 8
+12
+10
+14
+2
+6
+4
+8
+10 6 5
+12 11 8
+14 11 2
+i0 req
+i1 ack
+l0 busy
+o0 done
+b0 overflow
+j0 live
+c
+made by hand
+";
+    let aig = parse_aiger(source.as_bytes()).unwrap();
+    assert_eq!(aig.latches[0].init, FlipFlopInit::Const(true));
+    assert_eq!(aig.latches[1].init, FlipFlopInit::Unknown);
+    assert_eq!(aig.bad[0].name.as_deref(), Some("overflow"));
+    assert_eq!(aig.constraints[0].lit, 14);
+    assert_eq!(aig.justice[0].lits, vec![6, 4]);
+    assert_eq!(aig.fairness[0].lit, 8);
+    assert_eq!(aig.comments, vec!["made by hand"]);
+    let mut out = String::new();
+    write_aag(&aig, &mut out).unwrap();
+    assert_eq!(out, source);
+
+    // justice sizes are counts, not literals
+    let wide = "aag 1 1 0 0 0 0 0 1\n2\n4\n2\n3\n2\n3\n";
+    let aig = parse_aiger(wide.as_bytes()).unwrap();
+    assert_eq!(aig.justice[0].lits, vec![2, 3, 2, 3]);
+
+    let blif = parse_aiger_to_ast("hand", source.as_bytes()).unwrap();
+    let mut out = String::new();
+    writer::write_blif(&blif, &mut out, writer::BlifFlavor::SisMV).unwrap();
+    assert_eq!(
+        out,
+        r#".model hand
+.inputs req ack
+.outputs done overflow live_0 live_1 f0
+.latch done_n busy 1
+.latch l1_n l1
+.names busy ack overflow
+10 1
+.names overflow l1 done
+01 1
+.names overflow req c0
+01 1
+.names busy live_0
+1 1
+.names ack live_1
+1 1
+.names l1 f0
+1 1
+.names done done_n
+0 1
+.names l1 l1_n
+0 1
+.constraint c0
+.end
+"#
+    );
+
+    // binary and ASCII files of the same AIG
+    let source = std::fs::read_to_string("blif-examples-from-mvsis/C880.blif").unwrap();
+    let blif = parse_str_blif_to_ast("C880.blif", &source).unwrap();
+    let aig = Aig::from_model(blif.models().next().unwrap()).unwrap();
+    let mut aag = String::new();
+    write_aag(&aig, &mut aag).unwrap();
+    let mut bytes = vec![];
+    write_aig(&aig, &mut bytes).unwrap();
+    assert_eq!(parse_aiger(aag.as_bytes()).unwrap(), aig);
+    assert_eq!(parse_aiger(&bytes).unwrap(), aig);
+    let model = aig.to_model("C880");
+    assert_eq!(Aig::from_model(&model).unwrap(), aig);
+
+    let errors = [
+        ("aig 1 1 0 0\n", AigerParseError::Header),
+        (
+            "aag 3 1 0 1 1\n2\n6\n6 2 4\n",
+            AigerParseError::Undefined { lit: 4 },
+        ),
+        (
+            "aag 3 2 0 1 1\n2\n4\n9\n6 2\n",
+            AigerParseError::Invalid { line: 4 },
+        ),
+        (
+            "aag 3 2 0 1 1\n2\n2\n",
+            AigerParseError::Invalid { line: 3 },
+        ),
+        ("aag 3 2 0 1 1\n2\n4\n", AigerParseError::UnexpectedEnd),
+        ("aig 3 2 0 1 1\n6\n\x02", AigerParseError::Binary),
+    ];
+    for (source, err) in errors {
+        assert_eq!(parse_aiger(source.as_bytes()), Err(err), "{source}");
+    }
+}
+
+#[test]
+fn verilog_export() {
+    use verilog::{VerilogOptions, verilog_identifier, verilog_to_string};
+
+    assert_eq!(verilog_identifier("abc_1$"), "abc_1$");
+    assert_eq!(verilog_identifier("$abc$7"), "\\$abc$7 ");
+    assert_eq!(verilog_identifier("a[0]"), "\\a[0] ");
+    assert_eq!(verilog_identifier("wire"), "\\wire ");
+    assert_eq!(verilog_identifier("1a"), "\\1a ");
+
+    let blif = parse_str_blif_to_ast(
+        "top.blif",
+        r#"
+.model top
+.inputs a b c clk
+.outputs y z q[0] w
+.names a b c y
+11- 0
+--0 0
+.names a z
+0 1
+.names a b c $wide
+1-1 1
+01- 1
+.gate nand2 A=a B=$wide Y=n1
+.cname u_nand
+.attr src "top.v:3"
+.param WIDTH 00000001
+.gate nand2 A=n1 B=b Y=n2
+.latch n2 q[0] fe clk 1
+.latch n1 l1 ah clk
+.latch n1 l2 0
+.mlatch dff D=n2 Q=m clk 0
+.subckt sub|u_sub i=m o=s
+.conn s w
+.end
+
+.model sub
+.inputs i
+.outputs o
+.blackbox
+.end
+"#,
+    )
+    .unwrap();
+    let out = verilog_to_string(&blif, &VerilogOptions { case_min_inputs: 3 });
+    assert_eq!(
+        out,
+        r#"module top(a, b, c, clk, y, z, \q[0] , w);
+  input a;
+  input b;
+  input c;
+  input clk;
+  output y;
+  reg y;
+  output z;
+  output \q[0] ;
+  reg \q[0] ;
+  output w;
+  reg \$wide ;
+  wire n1;
+  wire n2;
+  reg l1;
+  reg l2;
+  wire m;
+  wire s;
+  (* gclk *) reg global_clock;
+
+  always @*
+    casez ({a, b, c})
+      3'b11?: y = 1'b0;
+      3'b??0: y = 1'b0;
+      default: y = 1'b1;
+    endcase
+  assign z = ~a;
+  always @*
+    casez ({a, b, c})
+      3'b1?1: \$wide  = 1'b1;
+      3'b01?: \$wide  = 1'b1;
+      default: \$wide  = 1'b0;
+    endcase
+  (* src = "top.v:3" *)
+  nand2 #(.WIDTH(8'b00000001)) u_nand (
+    .A(a),
+    .B(\$wide ),
+    .Y(n1)
+  );
+  nand2 nand2_4 (
+    .A(n1),
+    .B(b),
+    .Y(n2)
+  );
+  initial \q[0]  = 1'b1;
+  always @(negedge clk)
+    \q[0]  <= n2;
+  always @*
+    if (clk)
+      l1 <= n1;
+  initial l2 = 1'b0;
+  always @(posedge global_clock)
+    l2 <= n1;
+  // .mlatch control: clk, init: 0
+  dff dff_8 (
+    .D(n2),
+    .Q(m)
+  );
+  sub u_sub (
+    .i(m),
+    .o(s)
+  );
+  assign w = s;
+endmodule
+
+(* blackbox *)
+module sub(i, o);
+  input i;
+  output o;
+endmodule
+"#
+    );
+
+    let out = verilog_to_string(&blif, &VerilogOptions::default());
+    assert!(out.contains("  assign y = ~((a & b) | ~c);\n"), "{out}");
+    assert!(
+        out.contains("  assign \\$wide  = (a & c) | (~a & b);\n"),
+        "{out}"
+    );
+}
+
+#[test]
+fn bench_import_export() {
+    use bench::{BenchError, BenchOptions, BenchParseError, bench_to_string, parse_bench};
+
+    // ISCAS'85 c17, with every other gate type added
+    let source = "# c17
+INPUT(1)
+INPUT(2)
+INPUT(3)
+INPUT(6)
+INPUT(7)
+
+OUTPUT(22)
+OUTPUT(23)
+OUTPUT(30)
+
+10 = NAND(1, 3)
+11 = NAND(3, 6)
+16 = NAND(2, 11)
+19 = NAND(11, 7)
+22 = NAND(10, 16)
+23 = NAND(16, 19)
+24 = AND(1, 2, 3)
+25 = OR(24, 6)
+26 = NOR(25, 7)
+27 = XOR(1, 2, 3)
+28 = XNOR(26, 27)
+29 = NOT(28)
+30 = BUFF(31)
+31 = DFF(29)
+";
+    let model = parse_bench("c17", source).unwrap();
+    assert_eq!(model.commands.len(), 14);
+    assert_eq!(
+        bench_to_string(&model, &BenchOptions::default()).unwrap(),
+        source
+    );
+
+    let blif = parse_str_blif_to_ast(
+        "covers.blif",
+        r#"
+.model covers
+.inputs a b c
+.outputs y1 y2 y3 y4
+.names a b y1
+11 0
+.names a b c y2
+1-- 1
+-1- 1
+--1 1
+.names a b y3
+1- 1
+.names a b y4
+10 1
+01 1
+.names a b c y5
+11- 1
+--0 1
+.end
+"#,
+    )
+    .unwrap();
+    let model = blif.models().next().unwrap();
+    let err = bench_to_string(model, &BenchOptions::default()).unwrap_err();
+    assert_eq!(err, BenchError::Function { net: "y5".into() });
+    let decompose = BenchOptions { decompose: true };
+    assert_eq!(
+        bench_to_string(model, &decompose).unwrap(),
+        "# covers
+INPUT(a)
+INPUT(b)
+INPUT(c)
+
+OUTPUT(y1)
+OUTPUT(y2)
+OUTPUT(y3)
+OUTPUT(y4)
+
+y1 = NAND(a, b)
+y2 = OR(a, b, c)
+y3 = BUFF(a)
+y4 = XOR(a, b)
+c_not = NOT(c)
+y5_p0 = AND(a, b)
+y5 = OR(y5_p0, c_not)
+"
+    );
+
+    let parsed = parse_str_blif_to_ast(
+        "gate.blif",
+        ".model g\n.inputs a\n.outputs y\n.gate inv A=a Y=y\n.end\n",
+    )
+    .unwrap();
+    let err = bench_to_string(parsed.models().next().unwrap(), &decompose).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            BenchError::Unsupported {
+                command: ".gate",
+                ..
+            }
+        ),
+        "{err}"
+    );
+
+    let errors = [
+        (
+            "INPUT(a)\ny = FOO(a)\n",
+            BenchParseError::UnknownGate {
+                line: 2,
+                gate: "FOO".into(),
+            },
+        ),
+        (
+            "INPUT(a)\ny = NOT(a, a)\n",
+            BenchParseError::Arity {
+                line: 2,
+                gate: "NOT".into(),
+            },
+        ),
+        (
+            "INPUT(a)\n\ny = AND(a\n",
+            BenchParseError::Invalid { line: 3 },
+        ),
+    ];
+    for (source, err) in errors {
+        assert_eq!(parse_bench("m", source), Err(err));
+    }
+}
+
+#[test]
+fn pla_import_export() {
+    use pla::{Pla, PlaError, PlaParseError, PlaType, parse_pla, pla_to_string};
+
+    let blif = parse_str_blif_to_ast(
+        "collapse.blif",
+        r#"
+.model collapse
+.inputs a b c
+.outputs y z
+.names a b t
+11 1
+.names t c y
+1- 1
+-1 1
+.names t c z
+10 0
+.exdc
+.names a c z
+11 1
+.end
+"#,
+    )
+    .unwrap();
+    let model = blif.models().next().unwrap();
+    let pla = Pla::from_model(model, PlaType::Fdr).unwrap();
+    assert_eq!(pla.inputs, ["a", "b", "c"]);
+    for m in 0..8usize {
+        let x: Vec<bool> = (0..3).map(|i| m >> i & 1 == 1).collect();
+        let t = x[0] && x[1];
+        let [y, z] = &pla.outputs[..] else { panic!() };
+        assert_eq!(y.on.eval(&x), t || x[2]);
+        assert_eq!(y.off.eval(&x), !(t || x[2]));
+        assert!(!y.dc.eval(&x));
+        assert_eq!(z.on.eval(&x), !t || x[2]);
+        assert_eq!(z.dc.eval(&x), x[0] && x[2]);
+        assert_eq!(z.off.eval(&x), t && !x[2]);
+    }
+
+    // the written PLA reads back the same, and so does the model
+    let text = pla_to_string(&pla);
+    assert!(text.starts_with(".i 3\n.o 2\n.ilb a b c\n.ob y z\n.type fdr\n"));
+    assert_eq!(pla_to_string(&parse_pla(&text).unwrap()), text);
+    let back = Pla::from_model(&pla.to_model("collapse"), PlaType::Fdr).unwrap();
+    for (a, b) in back.outputs.iter().zip(&pla.outputs) {
+        assert!(a.on.equivalent(&b.on));
+        assert!(a.dc.equivalent(&b.dc));
+        assert!(a.off.equivalent(&b.off));
+    }
+
+    // without `.ilb`/`.ob`, with the off-set only and both output notations
+    let pla = parse_pla(".i 2\n.o 2\n.type fr\n11 0~\n0-\t3 0\n.e\n").unwrap();
+    assert_eq!(pla.inputs, ["i0", "i1"]);
+    assert!(pla.outputs[0].on.is_empty());
+    let blif = pla::parse_pla_to_ast("off", ".i 2\n.o 2\n.type fr\n11 0~\n0-\t3 0\n.e\n").unwrap();
+    let mut out = String::new();
+    writer::write_blif(&blif, &mut out, writer::BlifFlavor::Standard).unwrap();
+    assert!(out.contains(".names i0 i1 o0\n11 0\n"));
+    assert!(out.contains(".names i0 o1\n0 0\n"));
+
+    let sequential = parse_str_blif_to_ast(
+        "seq.blif",
+        ".model seq\n.inputs d\n.outputs q\n.latch d q 0\n.end\n",
+    )
+    .unwrap();
+    assert_eq!(
+        Pla::from_model(sequential.models().next().unwrap(), PlaType::F).unwrap_err(),
+        PlaError::Unsupported {
+            command: ".latch",
+            name: "q".into()
+        }
+    );
+    assert_eq!(
+        parse_pla("11 1\n").unwrap_err(),
+        PlaParseError::Missing {
+            line: 1,
+            keyword: ".i"
+        }
+    );
+    assert_eq!(
+        parse_pla(".i 2\n.o 1\n1 1\n").unwrap_err(),
+        PlaParseError::Width { line: 3 }
+    );
+    assert_eq!(
+        parse_pla(".i 2\n.o 1\n.mv 3 2 2 2\n").unwrap_err(),
+        PlaParseError::Unsupported {
+            line: 3,
+            keyword: ".mv".into()
+        }
+    );
+}
+
+#[test]
+fn kiss_import_export() {
+    use kiss::{KissParseError, fsm_model, kiss_to_string, parse_kiss, parse_kiss_to_ast};
+
+    // in the style of the MCNC benchmarks, with tabs and a comment
+    let source = "# lion-like\n.i 2\n.o 1\n.p 5\n.s 3\n.r st0\n-0\tst0\tst0\t0\n11 st0 st1 0\n\
+                  0- st1 st0 1\n1- st1 st2 -\n-- st2 st0 1\n.code st0 00\n.code st1 01\n\
+                  .code st2 10\n.e\n";
+    let fsm = parse_kiss(source).unwrap();
+    assert_eq!((fsm.inputs, fsm.outputs), (2, 1));
+    assert_eq!(fsm.states.len(), 5);
+    assert_eq!(fsm.states[3].next_state, "st2");
+    assert_eq!(fsm.states[3].output[..], [Tristate::Ignored]);
+    assert_eq!(fsm.state_assignments.as_ref().unwrap().len(), 3);
+    let text = kiss_to_string(&fsm);
+    assert_eq!(
+        text,
+        ".i 2\n.o 1\n.p 5\n.s 3\n.r st0\n-0 st0 st0 0\n11 st0 st1 0\n0- st1 st0 1\n\
+         1- st1 st2 -\n-- st2 st0 1\n.code st0 00\n.code st1 01\n.code st2 10\n"
+    );
+    assert_eq!(parse_kiss(&text).unwrap(), fsm);
+
+    // the wrapped model survives a round trip through BLIF
+    let blif = parse_kiss_to_ast("lion", source).unwrap();
+    let mut out = String::new();
+    writer::write_blif(&blif, &mut out, writer::BlifFlavor::Standard).unwrap();
+    assert!(out.starts_with(".model lion\n.inputs i0 i1\n.outputs o0\n.start_kiss\n"));
+    let back = parse_str_blif_to_ast("lion.blif", &out).unwrap();
+    let model = back.model("lion").unwrap();
+    assert_eq!(model.commands[0].kind, ModelCmdKind::FSM(fsm.clone()));
+    assert_eq!(*model, fsm_model("lion", fsm));
+
+    // without inputs, the input field is left out
+    let fsm = parse_kiss(".i 0\n.o 1\na b 1\nb a 0\n").unwrap();
+    assert!(fsm.states[0].input.is_empty());
+    assert_eq!(fsm.reset_state, None);
+    assert_eq!(
+        kiss_to_string(&fsm),
+        ".i 0\n.o 1\n.p 2\n.s 2\na b 1\nb a 0\n"
+    );
+
+    assert_eq!(
+        parse_kiss("0 a b 1\n").unwrap_err(),
+        KissParseError::Missing {
+            line: 1,
+            keyword: ".i"
+        }
+    );
+    assert_eq!(
+        parse_kiss(".i 2\n.o 1\n0 a b 1\n").unwrap_err(),
+        KissParseError::Width { line: 3 }
+    );
+    assert_eq!(
+        parse_kiss(".i 1\n.o 1\n0 a b\n").unwrap_err(),
+        KissParseError::Invalid { line: 3 }
+    );
+    assert_eq!(
+        parse_kiss(".i 1\n.o 1\n.ilb x\n").unwrap_err(),
+        KissParseError::Unsupported {
+            line: 3,
+            keyword: ".ilb".into()
+        }
+    );
+}
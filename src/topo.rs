@@ -0,0 +1,269 @@
+//! Topological ordering of the cells of a model, and detection of
+//! combinational loops.
+//!
+//! Latches and primary inputs are sources: the output of a latch does not
+//! depend on its input in the same cycle, so only loops through
+//! combinational cells are reported:
+//!
+//! ```rust
+//! use turbo_blif::ast::parse_str_blif_to_ast;
+//! use turbo_blif::topo::topo_order;
+//!
+//! let blif = parse_str_blif_to_ast(
+//!     "top.blif",
+//!     ".model top\n.inputs a\n.outputs y\n.names t y\n1 1\n.names a q t\n11 1\n\
+//!      .latch y q re clk 0\n.end\n",
+//! )
+//! .unwrap();
+//! let model = blif.model("top").unwrap();
+//! assert_eq!(topo_order(model).unwrap(), vec![2, 1, 0]);
+//! ```
+
+use std::fmt;
+
+use crate::ast::{Blif, Model, ModelCmdKind};
+use crate::netlist::{CellId, Driver, Netlist, PinDir};
+
+/// A cycle of combinational cells.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CombinationalLoop {
+    /// Indices into [`Model::commands`] of the cells of the loop, ascending.
+    pub commands: Vec<usize>,
+    /// The nets that connect the cells of the loop, in order of their first
+    /// appearance in the model.
+    pub nets: Vec<String>,
+}
+
+impl fmt::Display for CombinationalLoop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "combinational loop through net")?;
+        if self.nets.len() > 1 {
+            write!(f, "s")?;
+        }
+        for (i, net) in self.nets.iter().enumerate() {
+            let sep = if i == 0 { "" } else { "," };
+            write!(f, "{sep} `{net}`")?;
+        }
+        write!(f, " (command")?;
+        if self.commands.len() > 1 {
+            write!(f, "s")?;
+        }
+        for (i, cmd) in self.commands.iter().enumerate() {
+            let sep = if i == 0 { "" } else { "," };
+            write!(f, "{sep} {cmd}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// The combinational loops that prevent a topological order.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TopoError {
+    /// Name of the model.
+    pub model: String,
+    /// Every loop, as strongly connected component of the combinational
+    /// cells. Ordered by their smallest command index.
+    pub loops: Vec<CombinationalLoop>,
+}
+
+impl fmt::Display for TopoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "model `{}`: ", self.model)?;
+        for (i, lp) in self.loops.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{lp}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TopoError {}
+
+/// The cells of `model` in topological order, as indices into
+/// [`Model::commands`]. See [`topo_order_cells`].
+///
+/// `.subckt` pins have unknown directions here; use [`topo_order_in`] to take
+/// them from the instantiated models.
+///
+/// # Errors
+///
+/// Returns all combinational loops if there are any.
+pub fn topo_order(model: &Model) -> Result<Vec<usize>, TopoError> {
+    cmd_indices(&Netlist::new(model))
+}
+
+/// Like [`topo_order`], but with the directions of `.gate`, `.mlatch` and
+/// `.subckt` pins taken from the models in `blif`.
+///
+/// # Errors
+///
+/// Returns all combinational loops if there are any.
+pub fn topo_order_in(model: &Model, blif: &Blif) -> Result<Vec<usize>, TopoError> {
+    cmd_indices(&Netlist::in_blif(model, blif))
+}
+
+fn cmd_indices(netlist: &Netlist) -> Result<Vec<usize>, TopoError> {
+    let order = topo_order_cells(netlist)?;
+    Ok(order
+        .into_iter()
+        .map(|cell| netlist.cell(cell).cmd_index)
+        .collect())
+}
+
+/// Whether the outputs of a cell don't depend combinationally on its inputs.
+fn is_sequential(kind: &ModelCmdKind) -> bool {
+    matches!(kind, ModelCmdKind::FF(_) | ModelCmdKind::LibFF(_))
+}
+
+/// All cells of `netlist`, ordered such that every combinational cell comes
+/// after the cells that drive its inputs.
+///
+/// Latches (`.latch`, `.flop`, `.mlatch`) come first, in the order of their
+/// commands, because their outputs are sources. Among the combinational
+/// cells, the order of the commands is kept where the dependencies allow it.
+/// Pins of unknown direction are ignored; a `.subckt` is treated as
+/// combinational from all its inputs to all its outputs.
+///
+/// # Errors
+///
+/// Returns all combinational loops if there are any.
+pub fn topo_order_cells(netlist: &Netlist) -> Result<Vec<CellId>, TopoError> {
+    let cells = netlist.cells();
+    let seq: Vec<bool> = cells.iter().map(|c| is_sequential(&c.cmd.kind)).collect();
+
+    // edges from a combinational cell to the combinational cells reading it
+    let mut succs: Vec<Vec<usize>> = vec![vec![]; cells.len()];
+    let mut preds = vec![0usize; cells.len()];
+    // the last cell that got an edge to each cell, to skip duplicate edges
+    let mut seen = vec![usize::MAX; cells.len()];
+    for (idx, cell) in cells.iter().enumerate() {
+        if seq[idx] {
+            continue;
+        }
+        for pin in cell.pins.iter().filter(|p| p.dir == PinDir::Output) {
+            for reader in &netlist.net(pin.net).fanout {
+                let to = reader.cell.0;
+                if !seq[to] && seen[to] != idx {
+                    seen[to] = idx;
+                    succs[idx].push(to);
+                    preds[to] += 1;
+                }
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..cells.len()).filter(|&i| seq[i]).collect();
+    // Kahn's algorithm, always taking the ready cell with the smallest index
+    let mut ready: std::collections::BTreeSet<usize> = (0..cells.len())
+        .filter(|&i| !seq[i] && preds[i] == 0)
+        .collect();
+    while let Some(idx) = ready.pop_first() {
+        order.push(idx);
+        for &to in &succs[idx] {
+            preds[to] -= 1;
+            if preds[to] == 0 {
+                ready.insert(to);
+            }
+        }
+    }
+
+    if order.len() == cells.len() {
+        return Ok(order.into_iter().map(CellId).collect());
+    }
+
+    let mut loops: Vec<CombinationalLoop> = strongly_connected(&succs)
+        .into_iter()
+        .filter(|scc| scc.len() > 1 || succs[scc[0]].contains(&scc[0]))
+        .map(|mut scc| {
+            scc.sort();
+            let mut nets: Vec<_> = scc
+                .iter()
+                .flat_map(|&idx| &cells[idx].pins)
+                .filter(|pin| pin.dir == PinDir::Input)
+                .map(|pin| pin.net)
+                .filter(|&net| {
+                    netlist.net(net).drivers.iter().any(|d| match d {
+                        Driver::Pin(p) => scc.contains(&p.cell.0),
+                        Driver::Input => false,
+                    })
+                })
+                .collect();
+            nets.sort();
+            nets.dedup();
+            CombinationalLoop {
+                commands: scc.iter().map(|&idx| cells[idx].cmd_index).collect(),
+                nets: nets
+                    .into_iter()
+                    .map(|net| netlist.net(net).name().to_string())
+                    .collect(),
+            }
+        })
+        .collect();
+    loops.sort_by_key(|lp| lp.commands[0]);
+
+    Err(TopoError {
+        model: netlist.model().meta.name.to_string(),
+        loops,
+    })
+}
+
+/// Tarjan's algorithm, without recursion.
+fn strongly_connected(succs: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    const UNVISITED: usize = usize::MAX;
+    let mut index = vec![UNVISITED; succs.len()];
+    let mut lowlink = vec![0; succs.len()];
+    let mut on_stack = vec![false; succs.len()];
+    let mut stack = vec![];
+    let mut next_index = 0;
+    let mut out = vec![];
+
+    for root in 0..succs.len() {
+        if index[root] != UNVISITED {
+            continue;
+        }
+        // (node, next successor to visit)
+        let mut call = vec![(root, 0)];
+        index[root] = next_index;
+        lowlink[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some(&mut (node, ref mut child)) = call.last_mut() {
+            if let Some(&to) = succs[node].get(*child) {
+                *child += 1;
+                if index[to] == UNVISITED {
+                    index[to] = next_index;
+                    lowlink[to] = next_index;
+                    next_index += 1;
+                    stack.push(to);
+                    on_stack[to] = true;
+                    call.push((to, 0));
+                } else if on_stack[to] {
+                    lowlink[node] = lowlink[node].min(index[to]);
+                }
+                continue;
+            }
+
+            call.pop();
+            if let Some(&(parent, _)) = call.last() {
+                lowlink[parent] = lowlink[parent].min(lowlink[node]);
+            }
+            if lowlink[node] == index[node] {
+                let mut scc = vec![];
+                loop {
+                    let x = stack.pop().unwrap();
+                    on_stack[x] = false;
+                    scc.push(x);
+                    if x == node {
+                        break;
+                    }
+                }
+                out.push(scc);
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,276 @@
+//! Semantic checks of parsed designs.
+//!
+//! The parser only checks the syntax of a file. [`validate`] looks for
+//! designs that parse fine but make no sense, like nets without a driver or
+//! truth tables whose rows don't match their inputs:
+//!
+//! ```rust
+//! use turbo_blif::ast::parse_str_blif_to_ast;
+//! use turbo_blif::validate::{DiagnosticKind, validate};
+//!
+//! let blif = parse_str_blif_to_ast(
+//!     "top.blif",
+//!     ".model top\n.inputs a b\n.outputs y\n.names a y\n1 1\n.end\n",
+//! )
+//! .unwrap();
+//! let diags = validate(&blif);
+//! assert_eq!(diags.len(), 1);
+//! assert_eq!(diags[0].kind, DiagnosticKind::UnusedInput);
+//! assert_eq!(diags[0].to_string(), "warning: model `top`, net `b`: input is never used");
+//! ```
+
+use std::fmt;
+
+use crate::Str;
+use crate::ast::{Blif, Model, ModelCmdKind};
+use crate::hierarchy::check_ports;
+use crate::netlist::{Driver, Netlist};
+
+/// How bad a finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The design is probably fine, but unusual.
+    Warning,
+    /// The design is broken.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Where a finding is.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Location {
+    /// Name of the model.
+    pub model: String,
+    /// Index into [`Model::commands`] of the offending command, if any.
+    pub command: Option<usize>,
+    /// Name of the offending net, if any.
+    pub net: Option<String>,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "model `{}`", self.model)?;
+        if let Some(command) = self.command {
+            write!(f, ", command {command}")?;
+        }
+        if let Some(net) = &self.net {
+            write!(f, ", net `{net}`")?;
+        }
+        Ok(())
+    }
+}
+
+/// What is wrong.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DiagnosticKind {
+    /// The net is driven by more than one cell or port.
+    MultipleDrivers {
+        /// Number of drivers.
+        count: usize,
+    },
+    /// The net is read, but nothing drives it.
+    Undriven,
+    /// A net in `.outputs` is never driven.
+    UndrivenOutput,
+    /// A net in `.inputs` is never read.
+    UnusedInput,
+    /// A truth-table row does not have one value per gate input.
+    LutRowWidth {
+        /// Number of inputs of the gate.
+        expected: usize,
+        /// Number of values in the row.
+        found: usize,
+    },
+    /// A `.names` block has both on-set (`1`) and off-set (`0`) rows.
+    MixedLutRows,
+    /// A latch is clocked by a net that is not declared with `.clock`.
+    UndeclaredClock,
+    /// A `.subckt` connects a formal that the instantiated model does not
+    /// have.
+    UnknownFormal {
+        /// The instantiated model.
+        model: String,
+        /// The formal pin.
+        formal: String,
+    },
+}
+
+impl DiagnosticKind {
+    /// The severity of this kind of finding.
+    pub fn severity(&self) -> Severity {
+        match self {
+            DiagnosticKind::UnusedInput | DiagnosticKind::UndeclaredClock => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiagnosticKind::MultipleDrivers { count } => write!(f, "net has {count} drivers"),
+            DiagnosticKind::Undriven => write!(f, "net is read but never driven"),
+            DiagnosticKind::UndrivenOutput => write!(f, "output is never driven"),
+            DiagnosticKind::UnusedInput => write!(f, "input is never used"),
+            DiagnosticKind::LutRowWidth { expected, found } => write!(
+                f,
+                "truth-table row has {found} input values, but the gate has {expected} inputs"
+            ),
+            DiagnosticKind::MixedLutRows => {
+                write!(f, "truth table mixes on-set and off-set rows")
+            }
+            DiagnosticKind::UndeclaredClock => write!(f, "latch clock is not declared in `.clock`"),
+            DiagnosticKind::UnknownFormal { model, formal } => {
+                write!(f, "model `{model}` has no port `{formal}`")
+            }
+        }
+    }
+}
+
+/// One finding of [`validate`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Diagnostic {
+    /// How bad it is.
+    pub severity: Severity,
+    /// What is wrong.
+    pub kind: DiagnosticKind,
+    /// Where it is.
+    pub location: Location,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.severity, self.location, self.kind)
+    }
+}
+
+/// Check every model of `blif`.
+///
+/// Findings are ordered by model. Models marked with `.blackbox` have no
+/// implementation, so their outputs are not expected to be driven and their
+/// inputs not expected to be used.
+pub fn validate(blif: &Blif) -> Vec<Diagnostic> {
+    let mut out = vec![];
+    for model in blif.models() {
+        validate_model(model, blif, &mut out);
+    }
+    out
+}
+
+fn validate_model(model: &Model, blif: &Blif, out: &mut Vec<Diagnostic>) {
+    let mut report = |kind: DiagnosticKind, command: Option<usize>, net: Option<&str>| {
+        out.push(Diagnostic {
+            severity: kind.severity(),
+            kind,
+            location: Location {
+                model: model.meta.name.to_string(),
+                command,
+                net: net.map(|x| x.to_string()),
+            },
+        })
+    };
+
+    let netlist = Netlist::in_blif(model, blif);
+    let cmd_of = |driver: &Driver| match driver {
+        Driver::Input => None,
+        Driver::Pin(pin) => Some(netlist.cell(pin.cell).cmd_index),
+    };
+
+    for (_, net) in netlist.nets() {
+        if net.drivers.len() > 1 {
+            report(
+                DiagnosticKind::MultipleDrivers {
+                    count: net.drivers.len(),
+                },
+                cmd_of(&net.drivers[1]),
+                Some(net.name()),
+            );
+        }
+
+        // pins of unknown direction might drive the net
+        if net.drivers.is_empty() && net.unknown.is_empty() && !model.attr.blackbox {
+            if net.is_output {
+                report(DiagnosticKind::UndrivenOutput, None, Some(net.name()));
+            } else if let Some(pin) = net.fanout.first() {
+                let cmd = netlist.cell(pin.cell).cmd_index;
+                report(DiagnosticKind::Undriven, Some(cmd), Some(net.name()));
+            }
+        }
+    }
+
+    if !model.attr.blackbox {
+        for input in model.meta.inputs.iter().flatten() {
+            let net = netlist.net(netlist.net_id(input).unwrap());
+            if net.fanout.is_empty() && net.unknown.is_empty() && !net.is_output {
+                report(DiagnosticKind::UnusedInput, None, Some(input));
+            }
+        }
+    }
+
+    let declared_clock = |clock: &Str<16>| model.meta.clocks.contains(clock);
+
+    for (idx, cmd) in model.commands.iter().enumerate() {
+        match &cmd.kind {
+            ModelCmdKind::Gate(gate) => {
+                let expected = gate.meta.inputs.len();
+                if let Some((ins, _)) = gate.lut.0.iter().find(|(ins, _)| ins.len() != expected) {
+                    report(
+                        DiagnosticKind::LutRowWidth {
+                            expected,
+                            found: ins.len(),
+                        },
+                        Some(idx),
+                        Some(&gate.meta.output),
+                    );
+                }
+
+                let has = |v| gate.lut.0.iter().any(|(_, out)| *out == Some(v));
+                if has(true) && has(false) {
+                    report(
+                        DiagnosticKind::MixedLutRows,
+                        Some(idx),
+                        Some(&gate.meta.output),
+                    );
+                }
+            }
+            ModelCmdKind::FF(ff) => {
+                if let Some(clock) = &ff.clock
+                    && !declared_clock(clock)
+                {
+                    report(DiagnosticKind::UndeclaredClock, Some(idx), Some(clock));
+                }
+            }
+            ModelCmdKind::LibFF(lf) => {
+                if let Some(clock) = &lf.clock
+                    && !declared_clock(clock)
+                {
+                    report(DiagnosticKind::UndeclaredClock, Some(idx), Some(clock));
+                }
+            }
+            ModelCmdKind::SubModel { name, map, .. } => {
+                let Some(target) = blif.model(name) else {
+                    continue;
+                };
+                for formal in check_ports(blif, target, map).0 {
+                    report(
+                        DiagnosticKind::UnknownFormal {
+                            model: name.to_string(),
+                            formal: formal.to_string(),
+                        },
+                        Some(idx),
+                        None,
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+}
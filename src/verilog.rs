@@ -0,0 +1,651 @@
+//! Structural Verilog output.
+//!
+//! [`write_verilog`] writes every model of a [`Blif`] as a Verilog module,
+//! for reading netlists in tools and reviews that don't understand BLIF:
+//!
+//! ```rust
+//! use turbo_blif::ast::parse_str_blif_to_ast;
+//! use turbo_blif::verilog::{VerilogOptions, verilog_to_string};
+//!
+//! let blif = parse_str_blif_to_ast(
+//!     "top.blif",
+//!     ".model top\n.inputs a b clk\n.outputs q\n.names a b d\n1- 1\n-0 1\n\
+//!      .latch d q re clk 0\n.end\n",
+//! )
+//! .unwrap();
+//!
+//! assert_eq!(
+//!     verilog_to_string(&blif, &VerilogOptions::default()),
+//!     "module top(a, b, clk, q);
+//!   input a;
+//!   input b;
+//!   input clk;
+//!   output q;
+//!   reg q;
+//!   wire d;
+//!
+//!   assign d = a | ~b;
+//!   initial q = 1'b0;
+//!   always @(posedge clk)
+//!     q <= d;
+//! endmodule
+//! "
+//! );
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt::{self, Write as _};
+
+use crate::ast::{Blif, BlifEntry, CellAttrAst, Gate, Model, ModelCmd, ModelCmdKind};
+use crate::hierarchy::instance_label;
+use crate::netlist::model_ports;
+use crate::{FlipFlop, FlipFlopInit, FlipFlopType, Str, Tristate};
+
+/// Formatting options of [`write_verilog`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerilogOptions {
+    /// `.names` with at least this many inputs are written as a `casez` over
+    /// their rows instead of a sum of products. The default is 6.
+    pub case_min_inputs: usize,
+}
+
+impl Default for VerilogOptions {
+    fn default() -> Self {
+        Self { case_min_inputs: 6 }
+    }
+}
+
+/// The reserved words of Verilog-2005, which can only be used as escaped
+/// identifiers.
+const KEYWORDS: &[&str] = &[
+    "always",
+    "and",
+    "assign",
+    "automatic",
+    "begin",
+    "buf",
+    "bufif0",
+    "bufif1",
+    "case",
+    "casex",
+    "casez",
+    "cell",
+    "cmos",
+    "config",
+    "deassign",
+    "default",
+    "defparam",
+    "design",
+    "disable",
+    "edge",
+    "else",
+    "end",
+    "endcase",
+    "endconfig",
+    "endfunction",
+    "endgenerate",
+    "endmodule",
+    "endprimitive",
+    "endspecify",
+    "endtable",
+    "endtask",
+    "event",
+    "for",
+    "force",
+    "forever",
+    "fork",
+    "function",
+    "generate",
+    "genvar",
+    "highz0",
+    "highz1",
+    "if",
+    "ifnone",
+    "incdir",
+    "include",
+    "initial",
+    "inout",
+    "input",
+    "instance",
+    "integer",
+    "join",
+    "large",
+    "liblist",
+    "library",
+    "localparam",
+    "macromodule",
+    "medium",
+    "module",
+    "nand",
+    "negedge",
+    "nmos",
+    "nor",
+    "noshowcancelled",
+    "not",
+    "notif0",
+    "notif1",
+    "or",
+    "output",
+    "parameter",
+    "pmos",
+    "posedge",
+    "primitive",
+    "pull0",
+    "pull1",
+    "pulldown",
+    "pullup",
+    "pulsestyle_ondetect",
+    "pulsestyle_onevent",
+    "rcmos",
+    "real",
+    "realtime",
+    "reg",
+    "release",
+    "repeat",
+    "rnmos",
+    "rpmos",
+    "rtran",
+    "rtranif0",
+    "rtranif1",
+    "scalared",
+    "showcancelled",
+    "signed",
+    "small",
+    "specify",
+    "specparam",
+    "strong0",
+    "strong1",
+    "supply0",
+    "supply1",
+    "table",
+    "task",
+    "time",
+    "tran",
+    "tranif0",
+    "tranif1",
+    "tri",
+    "tri0",
+    "tri1",
+    "triand",
+    "trior",
+    "trireg",
+    "unsigned",
+    "use",
+    "uwire",
+    "vectored",
+    "wait",
+    "wand",
+    "weak0",
+    "weak1",
+    "while",
+    "wire",
+    "wor",
+    "xnor",
+    "xor",
+];
+
+/// `name` as a Verilog identifier: unchanged if it is a simple identifier,
+/// and an escaped identifier (`\` followed by the name and a space)
+/// otherwise, for example for `$abc$42$n7`, `a[0]` or `module`.
+pub fn verilog_identifier(name: &str) -> Cow<'_, str> {
+    let mut chars = name.chars();
+    let simple = matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+        && !KEYWORDS.contains(&name);
+    if simple {
+        Cow::Borrowed(name)
+    } else {
+        Cow::Owned(format!("\\{name} "))
+    }
+}
+
+/// The value of a `.attr` or `.param` as a Verilog constant.
+///
+/// Yosys writes strings in quotes and bit vectors as strings of `0` and `1`;
+/// those become sized binary constants. Numbers are kept, and everything
+/// else is quoted.
+fn verilog_value(val: &str) -> Cow<'_, str> {
+    if val.len() >= 2 && val.starts_with('"') && val.ends_with('"') {
+        return Cow::Borrowed(val);
+    }
+    if !val.is_empty() && val.chars().all(|c| matches!(c, '0' | '1' | 'x' | 'z')) {
+        return Cow::Owned(format!("{}'b{val}", val.len()));
+    }
+    if val.parse::<f64>().is_ok() {
+        return Cow::Borrowed(val);
+    }
+    let escaped = val.replace('\\', "\\\\").replace('"', "\\\"");
+    Cow::Owned(format!("\"{escaped}\""))
+}
+
+/// Write every model of `blif` as a Verilog module.
+///
+/// - Ports come from `.inputs`, `.clock` and `.outputs`, or are inferred
+///   like [`model_ports`] does if they are omitted.
+/// - `.names` become `assign` statements with a sum of products, or an
+///   `always @*` block with a `casez` for wide ones, see
+///   [`VerilogOptions::case_min_inputs`]. Rows with a don't-care output are
+///   left out, and `.exdc` gates are not written.
+/// - `.latch` become `always` blocks for their edge or level, with an
+///   `initial` statement for a constant initial value. Latches without a
+///   clock use a `(* gclk *)` register as global clock, which Yosys reads as
+///   the global clock of formal verification.
+/// - `.gate`, `.mlatch` and `.subckt` become instances with named port
+///   connections and the `.param` of the cell as parameters. The control
+///   signal and initial value of `.mlatch` have no pin, so they are written
+///   as a comment.
+/// - `.conn` become `assign` statements.
+///
+/// Instances are named after their `.cname`, or the BLIF-MV instance name of
+/// `.subckt`, or else `<cell>_<command index>`. `.attr` of cells become
+/// `(* *)` attributes. Names are written as escaped identifiers where needed,
+/// see [`verilog_identifier`]. FSMs are written as a comment, and timing
+/// constraints are left out.
+///
+/// # Errors
+///
+/// Returns [`fmt::Error`] if the underlying writer fails.
+pub fn write_verilog<W: fmt::Write>(blif: &Blif, w: &mut W, opts: &VerilogOptions) -> fmt::Result {
+    for (i, entry) in blif.entries.iter().enumerate() {
+        match entry {
+            BlifEntry::Model(model) => {
+                if i > 0 {
+                    writeln!(w)?;
+                }
+                write_module(model, blif, w, opts)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Convenience wrapper: write a `Blif` AST as Verilog into a `String`.
+pub fn verilog_to_string(blif: &Blif, opts: &VerilogOptions) -> String {
+    let mut out = String::new();
+    write_verilog(blif, &mut out, opts).expect("writing to a String never fails");
+    out
+}
+
+/// The nets that a command connects to, in order.
+fn command_nets(kind: &ModelCmdKind) -> Vec<&Str<16>> {
+    match kind {
+        ModelCmdKind::Gate(gate) if !gate.meta.external_dc => {
+            gate.meta.inputs.iter().chain([&gate.meta.output]).collect()
+        }
+        ModelCmdKind::FF(ff) => [&ff.input, &ff.output]
+            .into_iter()
+            .chain(&ff.clock)
+            .collect(),
+        ModelCmdKind::LibGate(lg) => lg.maps.iter().map(|(_, x)| x).collect(),
+        ModelCmdKind::LibFF(lf) => lf.maps.iter().map(|(_, x)| x).chain(&lf.clock).collect(),
+        ModelCmdKind::SubModel { map, .. } => map.iter().map(|(_, x)| x).collect(),
+        ModelCmdKind::Connect { from, to } => vec![from, to],
+        _ => vec![],
+    }
+}
+
+/// Whether a gate is written as a `casez`, which needs its output to be a
+/// `reg`.
+fn is_case(gate: &Gate, opts: &VerilogOptions) -> bool {
+    gate.meta.inputs.len() >= opts.case_min_inputs
+}
+
+/// Module-wide state: the names that are taken, for naming instances and
+/// the global clock.
+struct ModuleWriter<'a> {
+    model: &'a Model,
+    opts: &'a VerilogOptions,
+    used: HashSet<String>,
+    global_clock: Option<String>,
+}
+
+impl ModuleWriter<'_> {
+    /// `name`, or `name` with a suffix if it is taken.
+    fn unique(&mut self, name: &str) -> String {
+        let mut candidate = name.to_string();
+        let mut n = 0;
+        while !self.used.insert(candidate.clone()) {
+            n += 1;
+            candidate = format!("{name}_{n}");
+        }
+        candidate
+    }
+}
+
+fn write_module<W: fmt::Write>(
+    model: &Model,
+    blif: &Blif,
+    w: &mut W,
+    opts: &VerilogOptions,
+) -> fmt::Result {
+    let (inputs, outputs) = model_ports(model, Some(blif));
+    let mut input_set: HashSet<&str> = HashSet::new();
+    let inputs: Vec<&str> = inputs
+        .iter()
+        .chain(&model.meta.clocks)
+        .map(|x| x.as_str())
+        .filter(|x| input_set.insert(x))
+        .collect();
+    let mut output_set: HashSet<&str> = HashSet::new();
+    let outputs: Vec<&str> = outputs
+        .iter()
+        .map(|x| x.as_str())
+        .filter(|x| !input_set.contains(x) && output_set.insert(x))
+        .collect();
+
+    let mut regs: HashSet<&str> = HashSet::new();
+    let mut internal: Vec<&str> = vec![];
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut needs_global_clock = false;
+    for cmd in &model.commands {
+        match &cmd.kind {
+            ModelCmdKind::Gate(gate) if !gate.meta.external_dc && is_case(gate, opts) => {
+                regs.insert(&gate.meta.output);
+            }
+            ModelCmdKind::FF(ff) => {
+                regs.insert(&ff.output);
+                needs_global_clock |= ff.clock.is_none();
+            }
+            _ => {}
+        }
+        for net in command_nets(&cmd.kind) {
+            let net = net.as_str();
+            if !input_set.contains(net) && !output_set.contains(net) && seen.insert(net) {
+                internal.push(net);
+            }
+        }
+    }
+
+    let mut mw = ModuleWriter {
+        model,
+        opts,
+        used: inputs
+            .iter()
+            .chain(&outputs)
+            .chain(&internal)
+            .map(|x| x.to_string())
+            .collect(),
+        global_clock: None,
+    };
+    if needs_global_clock {
+        mw.global_clock = Some(mw.unique("global_clock"));
+    }
+
+    if model.attr.blackbox {
+        writeln!(w, "(* blackbox *)")?;
+    }
+    write!(w, "module {}(", verilog_identifier(&model.meta.name))?;
+    for (i, port) in inputs.iter().chain(&outputs).enumerate() {
+        let sep = if i == 0 { "" } else { ", " };
+        write!(w, "{sep}{}", verilog_identifier(port))?;
+    }
+    writeln!(w, ");")?;
+    for input in &inputs {
+        writeln!(w, "  input {};", verilog_identifier(input))?;
+    }
+    for output in &outputs {
+        writeln!(w, "  output {};", verilog_identifier(output))?;
+        if regs.contains(output) {
+            writeln!(w, "  reg {};", verilog_identifier(output))?;
+        }
+    }
+    for net in &internal {
+        let kind = if regs.contains(net) { "reg" } else { "wire" };
+        writeln!(w, "  {kind} {};", verilog_identifier(net))?;
+    }
+    if let Some(clock) = &mw.global_clock {
+        writeln!(w, "  (* gclk *) reg {};", verilog_identifier(clock))?;
+    }
+
+    let mut body = String::new();
+    for (idx, cmd) in model.commands.iter().enumerate() {
+        mw.write_command(idx, cmd, &mut body)?;
+    }
+    if !body.is_empty() {
+        writeln!(w)?;
+        w.write_str(&body)?;
+    }
+    writeln!(w, "endmodule")
+}
+
+impl ModuleWriter<'_> {
+    fn write_command<W: fmt::Write>(
+        &mut self,
+        idx: usize,
+        cmd: &ModelCmd,
+        w: &mut W,
+    ) -> fmt::Result {
+        match &cmd.kind {
+            ModelCmdKind::Gate(gate) if !gate.meta.external_dc => {
+                write_attrs(cmd, w)?;
+                if is_case(gate, self.opts) {
+                    write_case(gate, w)
+                } else {
+                    write_assign(gate, w)
+                }
+            }
+            ModelCmdKind::FF(ff) => {
+                write_attrs(cmd, w)?;
+                self.write_latch(ff, w)
+            }
+            ModelCmdKind::LibGate(lg) => {
+                let name = self.instance_name(idx, cmd, &lg.name);
+                write_attrs(cmd, w)?;
+                write_instance(&lg.name, &name, &lg.maps, cmd, w)
+            }
+            ModelCmdKind::LibFF(lf) => {
+                let name = self.instance_name(idx, cmd, &lf.name);
+                write!(w, "  // .mlatch control: ")?;
+                match &lf.clock {
+                    Some(clock) => write!(w, "{clock}")?,
+                    None => write!(w, "global clock")?,
+                }
+                match lf.init {
+                    FlipFlopInit::Const(value) => writeln!(w, ", init: {}", value as u8)?,
+                    FlipFlopInit::DontCare => writeln!(w, ", init: don't care")?,
+                    FlipFlopInit::Unknown => writeln!(w)?,
+                }
+                write_attrs(cmd, w)?;
+                write_instance(&lf.name, &name, &lf.maps, cmd, w)
+            }
+            ModelCmdKind::SubModel {
+                name: model, map, ..
+            } => {
+                let label = instance_label(idx, cmd).unwrap();
+                let name = self.unique(&label);
+                write_attrs(cmd, w)?;
+                write_instance(model, &name, map, cmd, w)
+            }
+            ModelCmdKind::Connect { from, to } => {
+                write_attrs(cmd, w)?;
+                writeln!(
+                    w,
+                    "  assign {} = {};",
+                    verilog_identifier(to),
+                    verilog_identifier(from)
+                )
+            }
+            ModelCmdKind::FSM(_) => writeln!(
+                w,
+                "  // the .start_kiss FSM of model {} is not written",
+                self.model.meta.name
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// The `.cname` of a `.gate` or `.mlatch`, or `<cell>_<idx>`, made
+    /// unique.
+    fn instance_name(&mut self, idx: usize, cmd: &ModelCmd, cell: &str) -> String {
+        let cname = cmd.attrs.iter().find_map(|attr| match attr {
+            CellAttrAst::CellName(n) => Some(n.clone()),
+            _ => None,
+        });
+        self.unique(&cname.unwrap_or_else(|| format!("{cell}_{idx}")))
+    }
+
+    fn write_latch<W: fmt::Write>(&self, ff: &FlipFlop, w: &mut W) -> fmt::Result {
+        let q = verilog_identifier(&ff.output);
+        let d = verilog_identifier(&ff.input);
+        if let FlipFlopInit::Const(value) = ff.init {
+            writeln!(w, "  initial {q} = 1'b{};", value as u8)?;
+        }
+        let clock = match (&ff.clock, &self.global_clock) {
+            (Some(clock), _) => verilog_identifier(clock),
+            (None, Some(clock)) => verilog_identifier(clock),
+            (None, None) => unreachable!("latches without clock use the global clock"),
+        };
+        match ff.ty {
+            None | Some(FlipFlopType::RisingEdge) => {
+                writeln!(w, "  always @(posedge {clock})\n    {q} <= {d};")
+            }
+            Some(FlipFlopType::FallingEdge) => {
+                writeln!(w, "  always @(negedge {clock})\n    {q} <= {d};")
+            }
+            Some(FlipFlopType::ActiveHigh) => {
+                writeln!(w, "  always @*\n    if ({clock})\n      {q} <= {d};")
+            }
+            Some(FlipFlopType::ActiveLow) => {
+                writeln!(w, "  always @*\n    if (!{clock})\n      {q} <= {d};")
+            }
+            Some(FlipFlopType::Asynchronous) => writeln!(w, "  always @*\n    {q} <= {d};"),
+        }
+    }
+}
+
+/// The `.attr` of a cell as `(* *)` attributes.
+fn write_attrs<W: fmt::Write>(cmd: &ModelCmd, w: &mut W) -> fmt::Result {
+    for attr in &cmd.attrs {
+        if let CellAttrAst::Attr { key, val } = attr {
+            writeln!(
+                w,
+                "  (* {} = {} *)",
+                verilog_identifier(key),
+                verilog_value(val)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn write_instance<W: fmt::Write>(
+    cell: &str,
+    name: &str,
+    map: &[(Str<16>, Str<16>)],
+    cmd: &ModelCmd,
+    w: &mut W,
+) -> fmt::Result {
+    write!(w, "  {}", verilog_identifier(cell))?;
+    let params: Vec<String> = cmd
+        .attrs
+        .iter()
+        .filter_map(|attr| match attr {
+            CellAttrAst::Param { key, val } => Some(format!(
+                ".{}({})",
+                verilog_identifier(key),
+                verilog_value(val)
+            )),
+            _ => None,
+        })
+        .collect();
+    if !params.is_empty() {
+        write!(w, " #({})", params.join(", "))?;
+    }
+    write!(w, " {} (", verilog_identifier(name))?;
+    for (i, (formal, actual)) in map.iter().enumerate() {
+        let sep = if i == 0 { "" } else { "," };
+        write!(
+            w,
+            "{sep}\n    .{}({})",
+            verilog_identifier(formal),
+            verilog_identifier(actual)
+        )?;
+    }
+    writeln!(w, "\n  );")
+}
+
+/// Whether the rows of `gate` describe its off-set, like
+/// [`LUT::eval`](crate::ast::LUT::eval) decides.
+fn is_off_set(gate: &Gate) -> bool {
+    gate.lut.0.iter().any(|(_, out)| *out == Some(false))
+}
+
+/// The rows that `gate` is made of: the on-set or off-set ones.
+fn cover_rows(gate: &Gate) -> impl Iterator<Item = &[Tristate]> {
+    let off = is_off_set(gate);
+    gate.lut
+        .0
+        .iter()
+        .filter(move |(_, out)| *out == Some(!off))
+        .map(|(row, _)| &row[..])
+}
+
+fn write_assign<W: fmt::Write>(gate: &Gate, w: &mut W) -> fmt::Result {
+    let products: Vec<Vec<String>> = cover_rows(gate)
+        .map(|row| {
+            row.iter()
+                .zip(&gate.meta.inputs)
+                .filter_map(|(value, input)| match value {
+                    Tristate::False => Some(format!("~{}", verilog_identifier(input))),
+                    Tristate::True => Some(verilog_identifier(input).into_owned()),
+                    Tristate::Ignored => None,
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut sum = String::new();
+    for (i, product) in products.iter().enumerate() {
+        if i > 0 {
+            sum += " | ";
+        }
+        match product.len() {
+            0 => sum += "1'b1",
+            1 => sum += &product[0],
+            _ if products.len() == 1 => sum += &product.join(" & "),
+            _ => write!(sum, "({})", product.join(" & "))?,
+        }
+    }
+    if products.is_empty() {
+        sum += "1'b0";
+    }
+    if is_off_set(gate) {
+        sum = if products.len() == 1 && products[0].len() <= 1 && !sum.starts_with('~') {
+            format!("~{sum}")
+        } else {
+            format!("~({sum})")
+        };
+    }
+    writeln!(
+        w,
+        "  assign {} = {sum};",
+        verilog_identifier(&gate.meta.output)
+    )
+}
+
+fn write_case<W: fmt::Write>(gate: &Gate, w: &mut W) -> fmt::Result {
+    let off = is_off_set(gate);
+    let y = verilog_identifier(&gate.meta.output);
+    let width = gate.meta.inputs.len();
+    writeln!(w, "  always @*")?;
+    write!(w, "    casez ({{")?;
+    for (i, input) in gate.meta.inputs.iter().enumerate() {
+        let sep = if i == 0 { "" } else { ", " };
+        write!(w, "{sep}{}", verilog_identifier(input))?;
+    }
+    writeln!(w, "}})")?;
+    for row in cover_rows(gate) {
+        let bits: String = row
+            .iter()
+            .map(|value| match value {
+                Tristate::False => '0',
+                Tristate::True => '1',
+                Tristate::Ignored => '?',
+            })
+            .collect();
+        writeln!(w, "      {width}'b{bits}: {y} = 1'b{};", (!off) as u8)?;
+    }
+    writeln!(w, "      default: {y} = 1'b{};", off as u8)?;
+    writeln!(w, "    endcase")
+}
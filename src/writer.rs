@@ -1,9 +1,20 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashSet;
 use std::fmt;
+use std::fs::File;
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use smallvec::SmallVec;
 
 use crate::ast::{Blif, BlifEntry, CellAttrAst, FSM, Gate, Model, ModelCmd, ModelCmdKind};
+use crate::names::{NameEscaping, NameKind, escape_name};
 use crate::{
-    BeforeAfter, ClockEdgeKind, ClockEvents, DelayConstraintPhase, FlipFlop, FlipFlopInit,
-    FlipFlopType, GateMeta, LibFlipFlop, LibGate, ModelDelayConstraint, Str, Tristate,
+    BeforeAfter, CellAttr, ClockEdgeKind, ClockEvents, CommandConsumer, DelayConstraintPhase,
+    FlipFlop, FlipFlopInit, FlipFlopType, GateLutConsumer, GateMeta, LibFlipFlop, LibGate,
+    LutSyntax, ModelConsumer, ModelDelayConstraint, ModelMeta, Str, Tristate,
 };
 
 /// Flavor of BLIF to emit.
@@ -35,143 +46,470 @@ pub enum BlifFlavor {
     Yosys,
     /// SIS extensions.
     ///
-    /// Like Standard, but may emit `.cover` as an alternative to `.names`.
+    /// Like Standard, but gates that were read from a `.cover` are written
+    /// as `.cover` again.
     Sis,
     /// BLIF-MV (SIS-MV) extensions.
     ///
     /// Adds: `.mv`, `.table`, `.short`, `.constraint`, `.onehot`, `.reset`,
-    /// `.ltlformula`, `.spec`, `.gateinit`.
+    /// `.ltlformula`, `.spec`, `.gateinit`. Gates that were read from a
+    /// `.table` are written as `.table` again.
     SisMV,
 }
 
+/// Formatting options of the writer.
+///
+/// Every function of this module that takes options accepts either a
+/// [`BlifFlavor`], which uses the default formatting, or a full
+/// `WriteOptions`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Flavor of BLIF to emit.
+    pub flavor: BlifFlavor,
+    /// Maximum width of a line.
+    ///
+    /// Longer `.inputs`, `.outputs`, `.clock`, `.names`, `.gate`, `.mlatch`
+    /// and `.subckt` statements are continued on the next line with a
+    /// trailing `\`, like ABC and SIS do. A single name that is longer than
+    /// the limit is never split. `None` (the default) never wraps.
+    pub max_line_width: Option<usize>,
+    /// Number of spaces that continuation lines are indented with.
+    pub indent: usize,
+    /// Align the truth-table rows of `.names` with their header, so that the
+    /// input plane starts below the first input, and the output column is
+    /// below the output signal.
+    pub align_lut_rows: bool,
+    /// Canonical output: commands, truth-table rows and `.subckt` pin maps
+    /// are sorted, so that equivalent ASTs produce byte-identical output.
+    ///
+    /// Cells are sorted by their textual representation, and `.exdc` gates
+    /// are kept at the end of the model. Timing constraints and BLIF-MV
+    /// directives keep their relative order, because later ones may override
+    /// earlier ones. Port lists and `.gate` pin maps are order-sensitive and
+    /// therefore never sorted.
+    pub canonical: bool,
+    /// What to do with names that can not be written as-is in
+    /// [`flavor`](Self::flavor). See the [`names`](crate::names) module.
+    pub name_escaping: NameEscaping,
+}
+
+impl WriteOptions {
+    /// Default formatting for the given flavor.
+    pub fn new(flavor: BlifFlavor) -> Self {
+        Self {
+            flavor,
+            max_line_width: None,
+            indent: 0,
+            align_lut_rows: false,
+            canonical: false,
+            name_escaping: NameEscaping::Verbatim,
+        }
+    }
+}
+
+impl From<BlifFlavor> for WriteOptions {
+    fn from(flavor: BlifFlavor) -> Self {
+        Self::new(flavor)
+    }
+}
+
+impl From<&WriteOptions> for WriteOptions {
+    fn from(opts: &WriteOptions) -> Self {
+        opts.clone()
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Public API
 // ---------------------------------------------------------------------------
 
-/// Write the full `Blif` AST to `w` using the given `flavor`.
+/// Write the full `Blif` AST to `w` using the given flavor or [`WriteOptions`].
 ///
 /// # Errors
 ///
 /// Returns [`fmt::Error`] if the underlying writer fails.
-pub fn write_blif<W: fmt::Write>(blif: &Blif, w: &mut W, flavor: BlifFlavor) -> fmt::Result {
+pub fn write_blif<W: fmt::Write>(
+    blif: &Blif,
+    w: &mut W,
+    opts: impl Into<WriteOptions>,
+) -> fmt::Result {
+    let opts = opts.into();
     for entry in &blif.entries {
         match entry {
-            BlifEntry::Model(model) => write_model(model, w, flavor)?,
+            BlifEntry::Model(model) => write_model(model, w, &opts)?,
         }
     }
     Ok(())
 }
 
 /// Convenience wrapper: write a `Blif` AST into a `String`.
-pub fn blif_to_string(blif: &Blif, flavor: BlifFlavor) -> String {
+pub fn blif_to_string(blif: &Blif, opts: impl Into<WriteOptions>) -> String {
     let mut out = String::new();
-    write_blif(blif, &mut out, flavor).expect("writing to a String never fails");
+    write_blif(blif, &mut out, opts).expect("writing to a String never fails");
     out
 }
 
+/// Write the full `Blif` AST to an [`io::Write`] using the given flavor or
+/// [`WriteOptions`].
+///
+/// The output is buffered internally, so `w` does not need to be buffered.
+///
+/// # Errors
+///
+/// Returns the first I/O error of the underlying writer.
+pub fn write_blif_io<W: io::Write>(
+    blif: &Blif,
+    w: W,
+    opts: impl Into<WriteOptions>,
+) -> io::Result<()> {
+    let mut out = IoWriter::new(w);
+    let res = write_blif(blif, &mut out, opts);
+    out.finish(res)
+}
+
+/// Write every model of `blif` into its own file inside `dir`, plus a
+/// top-level file called `top` that references all of them with `.search`,
+/// in the order of [`Blif::entries`].
+///
+/// Model files are named after their model, with characters that are not
+/// safe in file names replaced by `_`. Reading the top-level file with
+/// [`parse_many_blif_to_ast`](crate::ast::parse_many_blif_to_ast) gives back
+/// the models in their original order.
+///
+/// Returns the paths of all written files, the top-level file first.
+///
+/// # Errors
+///
+/// Returns the first I/O error.
+pub fn write_blif_split(
+    blif: &Blif,
+    dir: impl AsRef<Path>,
+    top: &str,
+    opts: impl Into<WriteOptions>,
+) -> io::Result<Vec<PathBuf>> {
+    let opts = opts.into();
+    let dir = dir.as_ref();
+    let mut used = HashSet::new();
+    used.insert(top.to_string());
+
+    let mut files = vec![];
+    for entry in &blif.entries {
+        match entry {
+            BlifEntry::Model(model) => {
+                let file_name = unique_file_name(&model.meta.name, &mut used);
+                let mut out = IoWriter::new(File::create(dir.join(&file_name))?);
+                let res = write_model(model, &mut out, &opts);
+                out.finish(res)?;
+                files.push(file_name);
+            }
+        }
+    }
+
+    let mut out = IoWriter::new(File::create(dir.join(top))?);
+    let res = files.iter().try_for_each(|file_name| {
+        fmt::Write::write_fmt(&mut out, format_args!(".search {file_name}\n"))
+    });
+    out.finish(res)?;
+
+    Ok(std::iter::once(top)
+        .chain(files.iter().map(|x| x.as_str()))
+        .map(|x| dir.join(x))
+        .collect())
+}
+
+fn unique_file_name(model: &str, used: &mut HashSet<String>) -> String {
+    let stem: String = model
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    let mut name = format!("{stem}.blif");
+    let mut n = 1;
+    while !used.insert(name.clone()) {
+        name = format!("{stem}_{n}.blif");
+        n += 1;
+    }
+    name
+}
+
+/// Adapter that lets the `fmt::Write` based writer functions write into a
+/// buffered [`io::Write`], remembering the I/O error that made them fail.
+struct IoWriter<W: io::Write> {
+    inner: io::BufWriter<W>,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> IoWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner: io::BufWriter::new(inner),
+            error: None,
+        }
+    }
+
+    /// Turn the result of the writer functions into the I/O result, and flush.
+    fn finish(mut self, res: fmt::Result) -> io::Result<()> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        res.map_err(|_| io::Error::other("formatter error"))?;
+        self.inner.flush()
+    }
+}
+
+impl<W: io::Write> fmt::Write for IoWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Model-level
 // ---------------------------------------------------------------------------
 
-fn write_model<W: fmt::Write>(model: &Model, w: &mut W, flavor: BlifFlavor) -> fmt::Result {
-    writeln!(w, ".model {}", model.meta.name)?;
+fn write_model<W: fmt::Write>(model: &Model, w: &mut W, opts: &WriteOptions) -> fmt::Result {
+    write_model_header(&model.meta, w, opts)?;
+
+    // .area
+    if let Some(area) = model.attr.area {
+        writeln!(w, ".area {area}")?;
+    }
+
+    if model.attr.blackbox {
+        write_blackbox(w, opts.flavor)?;
+    }
+
+    // Commands
+    if opts.canonical {
+        write_canonical_cmds(&model.commands, w, opts)?;
+    } else {
+        for cmd in &model.commands {
+            write_model_cmd(cmd, w, opts)?;
+        }
+    }
+
+    writeln!(w, ".end")?;
+    Ok(())
+}
+
+fn write_model_header<W: fmt::Write>(
+    meta: &ModelMeta,
+    w: &mut W,
+    opts: &WriteOptions,
+) -> fmt::Result {
+    writeln!(w, ".model {}", model_name(&meta.name, opts)?)?;
 
     // .inputs
-    if let Some(inputs) = &model.meta.inputs
+    if let Some(inputs) = &meta.inputs
         && !inputs.is_empty()
     {
-        write!(w, ".inputs")?;
-        for input in inputs {
-            write!(w, " {input}")?;
-        }
-        writeln!(w)?;
+        write_stmt(".inputs", nets(inputs, opts)?, w, opts)?;
     }
 
     // .outputs
-    if let Some(outputs) = &model.meta.outputs
+    if let Some(outputs) = &meta.outputs
         && !outputs.is_empty()
     {
-        write!(w, ".outputs")?;
-        for output in outputs {
-            write!(w, " {output}")?;
-        }
-        writeln!(w)?;
+        write_stmt(".outputs", nets(outputs, opts)?, w, opts)?;
     }
 
     // .clock
-    if !model.meta.clocks.is_empty() {
-        write!(w, ".clock")?;
-        for clock in &model.meta.clocks {
-            write!(w, " {clock}")?;
-        }
-        writeln!(w)?;
+    if !meta.clocks.is_empty() {
+        write_stmt(".clock", nets(&meta.clocks, opts)?, w, opts)?;
     }
 
-    // .area
-    if let Some(area) = model.attr.area {
-        writeln!(w, ".area {area}")?;
+    Ok(())
+}
+
+/// Apply [`WriteOptions::name_escaping`] to a net name.
+fn net<'a>(name: &'a str, opts: &WriteOptions) -> Result<Cow<'a, str>, fmt::Error> {
+    escape_name(name, NameKind::Net, opts.name_escaping, opts.flavor).map_err(|_| fmt::Error)
+}
+
+/// Apply [`WriteOptions::name_escaping`] to a model name.
+fn model_name<'a>(name: &'a str, opts: &WriteOptions) -> Result<Cow<'a, str>, fmt::Error> {
+    escape_name(name, NameKind::Model, opts.name_escaping, opts.flavor).map_err(|_| fmt::Error)
+}
+
+/// Apply [`WriteOptions::name_escaping`] to a list of net names.
+fn nets<'a>(
+    names: impl IntoIterator<Item = &'a Str<16>>,
+    opts: &WriteOptions,
+) -> Result<Vec<Cow<'a, str>>, fmt::Error> {
+    names.into_iter().map(|x| net(x, opts)).collect()
+}
+
+/// Write a statement consisting of a keyword followed by space-separated
+/// arguments, continuing it with `\` before exceeding the line width.
+///
+/// Returns whether the statement was wrapped.
+fn write_stmt<W: fmt::Write>(
+    kw: &str,
+    args: impl IntoIterator<Item = impl AsRef<str>>,
+    w: &mut W,
+    opts: &WriteOptions,
+) -> Result<bool, fmt::Error> {
+    write!(w, "{kw}")?;
+    let mut col = kw.len();
+    let mut on_line = 0;
+    let mut wrapped = false;
+
+    for arg in args {
+        let arg = arg.as_ref();
+        // leave room for the trailing ` \`, and never wrap before the first argument
+        if let Some(max) = opts.max_line_width
+            && on_line > 0
+            && col + 1 + arg.len() + 2 > max
+        {
+            writeln!(w, " \\")?;
+            write!(w, "{:1$}", "", opts.indent)?;
+            col = opts.indent;
+            on_line = 0;
+            wrapped = true;
+        } else {
+            write!(w, " ")?;
+            col += 1;
+        }
+        write!(w, "{arg}")?;
+        col += arg.len();
+        on_line += 1;
     }
 
-    // Commands
-    for cmd in &model.commands {
-        write_model_cmd(cmd, w, flavor)?;
+    writeln!(w)?;
+    Ok(wrapped)
+}
+
+/// Canonical mode: render every command on its own and sort them.
+fn write_canonical_cmds<W: fmt::Write>(
+    cmds: &[ModelCmd],
+    w: &mut W,
+    opts: &WriteOptions,
+) -> fmt::Result {
+    let mut cells = vec![];
+    let mut directives = vec![];
+    let mut exdc = vec![];
+
+    for cmd in cmds {
+        let mut cmd = cmd.clone();
+        match &mut cmd.kind {
+            ModelCmdKind::Gate(gate) => {
+                gate.lut.0.sort_by(|a, b| {
+                    let key = |(ins, out): &(SmallVec<[Tristate; 8]>, Option<bool>)| {
+                        (ins.iter().map(|x| x.to_string()).collect::<String>(), *out)
+                    };
+                    key(a).cmp(&key(b))
+                });
+            }
+            ModelCmdKind::SubModel { map, .. } => map.sort(),
+            _ => {}
+        }
+
+        let mut text = String::new();
+        write_model_cmd(&cmd, &mut text, opts)?;
+
+        match &cmd.kind {
+            ModelCmdKind::Gate(gate) if gate.meta.external_dc => exdc.push(text),
+            ModelCmdKind::Gate(_)
+            | ModelCmdKind::FF(_)
+            | ModelCmdKind::LibGate(_)
+            | ModelCmdKind::LibFF(_)
+            | ModelCmdKind::FSM(_)
+            | ModelCmdKind::SubModel { .. }
+            | ModelCmdKind::Connect { .. } => cells.push(text),
+            _ => directives.push(text),
+        }
     }
 
-    writeln!(w, ".end")?;
+    cells.sort();
+    exdc.sort();
+
+    for text in cells.iter().chain(&directives).chain(&exdc) {
+        w.write_str(text)?;
+    }
     Ok(())
 }
 
+fn write_blackbox<W: fmt::Write>(w: &mut W, flavor: BlifFlavor) -> fmt::Result {
+    if matches!(flavor, BlifFlavor::ABC | BlifFlavor::Yosys) {
+        writeln!(w, ".blackbox")
+    } else {
+        writeln!(w, "# .blackbox  (ABC/Yosys extension)")
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Per-command dispatch
 // ---------------------------------------------------------------------------
 
-fn write_model_cmd<W: fmt::Write>(cmd: &ModelCmd, w: &mut W, flavor: BlifFlavor) -> fmt::Result {
+fn write_model_cmd<W: fmt::Write>(cmd: &ModelCmd, w: &mut W, opts: &WriteOptions) -> fmt::Result {
+    let flavor = opts.flavor;
     match &cmd.kind {
-        ModelCmdKind::Gate(gate) => write_gate(gate, cmd, w, flavor),
-        ModelCmdKind::FF(ff) => write_ff(ff, cmd, w, flavor),
-        ModelCmdKind::LibGate(lg) => write_lib_gate(lg, cmd, w, flavor),
-        ModelCmdKind::LibFF(lf) => write_lib_ff(lf, cmd, w, flavor),
-        ModelCmdKind::FSM(fsm) => write_fsm(fsm, cmd, w, flavor),
+        ModelCmdKind::Gate(gate) => write_gate(gate, w, opts),
+        ModelCmdKind::FF(ff) => write_ff(ff, w, opts),
+        ModelCmdKind::LibGate(lg) => write_lib_gate(lg, w, opts),
+        ModelCmdKind::LibFF(lf) => write_lib_ff(lf, w, opts),
+        ModelCmdKind::FSM(fsm) => write_fsm(fsm, w, opts),
         ModelCmdKind::SubModel {
             name,
             map,
             instance_name,
-        } => write_submodel(name, map, instance_name.as_deref(), cmd, w, flavor),
-        ModelCmdKind::Connect { from, to } => write_connect(from, to, w, flavor),
+        } => write_submodel(name, map, instance_name.as_deref(), w, opts),
+        ModelCmdKind::Connect { from, to } => write_connect(from, to, w, opts),
         ModelCmdKind::CycleTime(t) => writeln!(w, ".cycle {t}"),
-        ModelCmdKind::ClockEvents(ev) => write_clock_events(ev, w, flavor),
-        ModelCmdKind::DelayConstraint(dc) => write_delay_constraint(dc, w, flavor),
-        ModelCmdKind::Constraint(signals) => write_constraint(signals, w, flavor),
-        ModelCmdKind::OneHot(signals) => write_onehot(signals, w, flavor),
-        ModelCmdKind::Reset { signal, value } => write_reset(signal, value, w, flavor),
+        ModelCmdKind::ClockEvents(ev) => write_clock_events(ev, w, opts),
+        ModelCmdKind::DelayConstraint(dc) => write_delay_constraint(dc, w, opts),
+        ModelCmdKind::Constraint(signals) => write_constraint(signals, w, opts),
+        ModelCmdKind::OneHot(signals) => write_onehot(signals, w, opts),
+        ModelCmdKind::Reset { signal, value } => write_reset(signal, value, w, opts),
         ModelCmdKind::LtlFormula(formula) => write_ltlformula(formula, w, flavor),
         ModelCmdKind::Spec(filename) => write_spec(filename, w, flavor),
-        ModelCmdKind::GateInit { signal, value } => write_gateinit(signal, value, w, flavor),
+        ModelCmdKind::GateInit { signal, value } => write_gateinit(signal, value, w, opts),
         ModelCmdKind::Mv {
             variables,
             nvalues,
             value_names,
-        } => write_mv(variables, *nvalues, value_names, w, flavor),
-    }
+        } => write_mv(variables, *nvalues, value_names, w, opts),
+    }?;
+
+    write_attrs(cmd, w, opts)
 }
 
 // ---------------------------------------------------------------------------
 // Helper: emit trailing attributes for Yosys flavor
 // ---------------------------------------------------------------------------
 
-fn write_attrs<W: fmt::Write>(cmd: &ModelCmd, w: &mut W, flavor: BlifFlavor) -> fmt::Result {
-    if matches!(flavor, BlifFlavor::Yosys) {
-        for attr in &cmd.attrs {
-            match attr {
-                CellAttrAst::CellName(n) => {
-                    writeln!(w, ".cname {n}")?;
-                }
-                CellAttrAst::Attr { key, val } => {
-                    writeln!(w, ".attr {key} {val}")?;
-                }
-                CellAttrAst::Param { key, val } => {
-                    writeln!(w, ".param {key} {val}")?;
-                }
+fn write_attrs<W: fmt::Write>(cmd: &ModelCmd, w: &mut W, opts: &WriteOptions) -> fmt::Result {
+    for attr in &cmd.attrs {
+        let attr = match attr {
+            CellAttrAst::CellName(n) => CellAttr::CellName(n),
+            CellAttrAst::Attr { key, val } => CellAttr::Attr { key, val },
+            CellAttrAst::Param { key, val } => CellAttr::Param { key, val },
+        };
+        write_attr(&attr, w, opts)?;
+    }
+    Ok(())
+}
+
+fn write_attr<W: fmt::Write>(attr: &CellAttr, w: &mut W, opts: &WriteOptions) -> fmt::Result {
+    if matches!(opts.flavor, BlifFlavor::Yosys) {
+        match attr {
+            CellAttr::CellName(n) => {
+                writeln!(w, ".cname {}", net(n, opts)?)?;
+            }
+            CellAttr::Attr { key, val } => {
+                writeln!(w, ".attr {key} {val}")?;
+            }
+            CellAttr::Param { key, val } => {
+                writeln!(w, ".param {key} {val}")?;
             }
         }
     }
@@ -182,68 +520,144 @@ fn write_attrs<W: fmt::Write>(cmd: &ModelCmd, w: &mut W, flavor: BlifFlavor) ->
 // 1.2  Logic Gate (.names / .cover / .table)
 // ---------------------------------------------------------------------------
 
-fn write_gate<W: fmt::Write>(
-    gate: &Gate,
-    cmd: &ModelCmd,
+fn write_gate<W: fmt::Write>(gate: &Gate, w: &mut W, opts: &WriteOptions) -> fmt::Result {
+    let align = write_gate_header(&gate.meta, gate.lut.0.len(), w, opts)?;
+
+    for (ins, out) in &gate.lut.0 {
+        write_lut_row(ins, *out, align, w)?;
+    }
+
+    Ok(())
+}
+
+/// The command a gate with `nterms` rows is written with: the one it was read
+/// from if the flavor supports it, `.names` otherwise.
+///
+/// `.cover` is only kept for covers of more than one term, a single term is
+/// just as short as `.names`. Gates have a single output, so the header of a
+/// `.cover` always has one output.
+fn gate_syntax(meta: &GateMeta, nterms: usize, flavor: BlifFlavor) -> LutSyntax {
+    match (flavor, meta.syntax) {
+        (BlifFlavor::Sis, LutSyntax::Cover) if nterms > 1 => LutSyntax::Cover,
+        (BlifFlavor::SisMV, LutSyntax::Table) => LutSyntax::Table,
+        _ => LutSyntax::Names,
+    }
+}
+
+/// Column alignment of the truth-table rows of a gate: the column where the
+/// input plane starts, and the one of the output value.
+#[derive(Clone, Copy)]
+struct RowAlign {
+    input_col: usize,
+    output_col: usize,
+}
+
+/// Returns the alignment that the rows of this gate should use.
+///
+/// `nterms` is the number of rows, which decides whether a `.cover` is kept.
+fn write_gate_header<W: fmt::Write>(
+    meta: &GateMeta,
+    nterms: usize,
     w: &mut W,
-    flavor: BlifFlavor,
-) -> fmt::Result {
-    if gate.meta.external_dc {
+    opts: &WriteOptions,
+) -> Result<Option<RowAlign>, fmt::Error> {
+    if meta.external_dc {
         writeln!(w, ".exdc")?;
     }
 
-    // Decide between .names, .cover or .table based on flavor.
-    //
-    // We always use .names here because that's what the AST stores.
-    // Alternative syntaxes (.cover, .table) are only consumed during
-    // parsing and normalised into the same Gate representation.
-    writeln!(w, ".names {}", format_gate_header(&gate.meta))?;
+    let inputs = nets(&meta.inputs, opts)?;
+    let output = net(&meta.output, opts)?;
+    let names = inputs.iter().chain(std::iter::once(&output));
+    let (wrapped, input_col, extra) = match gate_syntax(meta, nterms, opts.flavor) {
+        LutSyntax::Names => (write_stmt(".names", names, w, opts)?, ".names ".len(), 0),
+        LutSyntax::Table => {
+            let args = inputs.iter().map(|x| x.as_ref()).chain(["->", &output]);
+            (
+                write_stmt(".table", args, w, opts)?,
+                ".table ".len(),
+                "-> ".len(),
+            )
+        }
+        LutSyntax::Cover => {
+            writeln!(w, ".cover {} 1 {nterms}", meta.inputs.len())?;
+            let mut names = names;
+            let first = names.next().unwrap();
+            (write_stmt(first, names, w, opts)?, 0, 0)
+        }
+    };
 
-    if !gate.lut.0.is_empty() {
-        write!(w, "{}", gate.lut)?;
+    if !opts.align_lut_rows || meta.inputs.is_empty() {
+        return Ok(None);
     }
 
-    write_attrs(cmd, w, flavor)
+    Ok(Some(if wrapped {
+        RowAlign {
+            input_col: opts.indent,
+            output_col: opts.indent + meta.inputs.len() + 1,
+        }
+    } else {
+        let output_col = input_col + extra + inputs.iter().map(|x| x.len() + 1).sum::<usize>();
+        RowAlign {
+            input_col,
+            output_col: output_col.max(input_col + meta.inputs.len() + 1),
+        }
+    }))
 }
 
-fn format_gate_header(meta: &GateMeta) -> String {
-    let mut s = String::new();
-    for inp in &meta.inputs {
-        s.push_str(inp.as_str());
-        s.push(' ');
+fn write_lut_row<W: fmt::Write>(
+    ins: &[Tristate],
+    out: Option<bool>,
+    align: Option<RowAlign>,
+    w: &mut W,
+) -> fmt::Result {
+    let mut col = 0;
+    if let Some(align) = align {
+        write!(w, "{:1$}", "", align.input_col)?;
+        col = align.input_col;
+    }
+    for x in ins {
+        write!(w, "{x}")?;
+    }
+    col += ins.len();
+    let out = match out {
+        Some(true) => "1",
+        Some(false) => "0",
+        None => "x",
+    };
+    if ins.is_empty() {
+        writeln!(w, "{out}")
+    } else {
+        let pad = align.map_or(1, |align| align.output_col.saturating_sub(col).max(1));
+        writeln!(w, "{:pad$}{out}", "")
     }
-    s.push_str(meta.output.as_str());
-    s
 }
 
 // ---------------------------------------------------------------------------
 // 1.4  Generic Latch (.latch)  /  2.5  Extended Flip-Flop (.flop)
 // ---------------------------------------------------------------------------
 
-fn write_ff<W: fmt::Write>(
-    ff: &FlipFlop,
-    cmd: &ModelCmd,
-    w: &mut W,
-    flavor: BlifFlavor,
-) -> fmt::Result {
-    match flavor {
+fn write_ff<W: fmt::Write>(ff: &FlipFlop, w: &mut W, opts: &WriteOptions) -> fmt::Result {
+    match opts.flavor {
         BlifFlavor::ABC => {
             // ABC can write .flop for clocked flip-flops with edges
             // and .latch for everything else.
             if ff.clock.is_some() && ff.ty.is_some() {
-                write_flop(ff, cmd, w)?;
+                write_flop(ff, w, opts)
             } else {
-                write_latch(ff, cmd, w)?;
+                write_latch(ff, w, opts)
             }
         }
-        _ => write_latch(ff, cmd, w)?,
+        _ => write_latch(ff, w, opts),
     }
-
-    write_attrs(cmd, w, flavor)
 }
 
-fn write_latch<W: fmt::Write>(ff: &FlipFlop, _cmd: &ModelCmd, w: &mut W) -> fmt::Result {
-    write!(w, ".latch {} {}", ff.input, ff.output)?;
+fn write_latch<W: fmt::Write>(ff: &FlipFlop, w: &mut W, opts: &WriteOptions) -> fmt::Result {
+    write!(
+        w,
+        ".latch {} {}",
+        net(&ff.input, opts)?,
+        net(&ff.output, opts)?
+    )?;
 
     // type / init — the first positional value after in/out is ambiguous in BLIF:
     // it can be a type keyword or an init value. Write type if present.
@@ -260,7 +674,7 @@ fn write_latch<W: fmt::Write>(ff: &FlipFlop, _cmd: &ModelCmd, w: &mut W) -> fmt:
 
     // clock
     if let Some(clock) = &ff.clock {
-        write!(w, " {clock}")?;
+        write!(w, " {}", net(clock, opts)?)?;
     } else if ff.ty.is_some() {
         // If we wrote a type keyword but there's no clock, write NIL
         write!(w, " NIL")?;
@@ -285,12 +699,12 @@ fn write_latch<W: fmt::Write>(ff: &FlipFlop, _cmd: &ModelCmd, w: &mut W) -> fmt:
     Ok(())
 }
 
-fn write_flop<W: fmt::Write>(ff: &FlipFlop, _cmd: &ModelCmd, w: &mut W) -> fmt::Result {
-    write!(w, ".flop D={}", ff.input)?;
-    write!(w, " Q={}", ff.output)?;
+fn write_flop<W: fmt::Write>(ff: &FlipFlop, w: &mut W, opts: &WriteOptions) -> fmt::Result {
+    write!(w, ".flop D={}", net(&ff.input, opts)?)?;
+    write!(w, " Q={}", net(&ff.output, opts)?)?;
 
     if let Some(clock) = &ff.clock {
-        write!(w, " C={clock}")?;
+        write!(w, " C={}", net(clock, opts)?)?;
     }
 
     match &ff.ty {
@@ -314,48 +728,36 @@ fn write_flop<W: fmt::Write>(ff: &FlipFlop, _cmd: &ModelCmd, w: &mut W) -> fmt::
 // 1.5  Library Gate (.gate)
 // ---------------------------------------------------------------------------
 
-fn write_lib_gate<W: fmt::Write>(
-    lg: &LibGate,
-    cmd: &ModelCmd,
-    w: &mut W,
-    flavor: BlifFlavor,
-) -> fmt::Result {
-    write!(w, ".gate {}", lg.name)?;
+fn write_lib_gate<W: fmt::Write>(lg: &LibGate, w: &mut W, opts: &WriteOptions) -> fmt::Result {
+    let mut args = vec![net(&lg.name, opts)?.into_owned()];
     for (formal, actual) in &lg.maps {
-        write!(w, " {formal}={actual}")?;
+        args.push(format!("{}={}", net(formal, opts)?, net(actual, opts)?));
     }
-    writeln!(w)?;
-    write_attrs(cmd, w, flavor)
+    write_stmt(".gate", args, w, opts).map(|_| ())
 }
 
 // ---------------------------------------------------------------------------
 // 1.6  Library Latch (.mlatch)
 // ---------------------------------------------------------------------------
 
-fn write_lib_ff<W: fmt::Write>(
-    lf: &LibFlipFlop,
-    cmd: &ModelCmd,
-    w: &mut W,
-    flavor: BlifFlavor,
-) -> fmt::Result {
-    write!(w, ".mlatch {}", lf.name)?;
+fn write_lib_ff<W: fmt::Write>(lf: &LibFlipFlop, w: &mut W, opts: &WriteOptions) -> fmt::Result {
+    let mut args = vec![net(&lf.name, opts)?.into_owned()];
     for (formal, actual) in &lf.maps {
-        write!(w, " {formal}={actual}")?;
+        args.push(format!("{}={}", net(formal, opts)?, net(actual, opts)?));
     }
 
     if let Some(clock) = &lf.clock {
-        write!(w, " {clock}")?;
+        args.push(net(clock, opts)?.into_owned());
     }
 
     match &lf.init {
-        FlipFlopInit::Const(true) => write!(w, " 1")?,
-        FlipFlopInit::Const(false) => write!(w, " 0")?,
-        FlipFlopInit::DontCare => write!(w, " 2")?,
+        FlipFlopInit::Const(true) => args.push("1".into()),
+        FlipFlopInit::Const(false) => args.push("0".into()),
+        FlipFlopInit::DontCare => args.push("2".into()),
         FlipFlopInit::Unknown => {}
     }
 
-    writeln!(w)?;
-    write_attrs(cmd, w, flavor)
+    write_stmt(".mlatch", args, w, opts).map(|_| ())
 }
 
 // ---------------------------------------------------------------------------
@@ -366,27 +768,26 @@ fn write_submodel<W: fmt::Write>(
     name: &str,
     map: &[(Str<16>, Str<16>)],
     instance_name: Option<&str>,
-    cmd: &ModelCmd,
     w: &mut W,
-    flavor: BlifFlavor,
+    opts: &WriteOptions,
 ) -> fmt::Result {
-    let kw = if flavor == BlifFlavor::ABC {
+    let kw = if opts.flavor == BlifFlavor::ABC {
         ".subcircuit"
     } else {
         ".subckt"
     };
 
-    write!(w, "{kw}")?;
-    if let Some(inst) = instance_name {
-        write!(w, " {name}|{inst}")?;
+    let name = model_name(name, opts)?;
+    let name = if let Some(inst) = instance_name {
+        format!("{name}|{}", net(inst, opts)?)
     } else {
-        write!(w, " {name}")?;
-    }
+        name.into_owned()
+    };
+    let mut args = vec![name];
     for (formal, actual) in map {
-        write!(w, " {formal}={actual}")?;
+        args.push(format!("{}={}", net(formal, opts)?, net(actual, opts)?));
     }
-    writeln!(w)?;
-    write_attrs(cmd, w, flavor)
+    write_stmt(kw, args, w, opts).map(|_| ())
 }
 
 // ---------------------------------------------------------------------------
@@ -397,8 +798,10 @@ fn write_connect<W: fmt::Write>(
     from: &str,
     to: &str,
     w: &mut W,
-    flavor: BlifFlavor,
+    opts: &WriteOptions,
 ) -> fmt::Result {
+    let (from, to) = (net(from, opts)?, net(to, opts)?);
+    let flavor = opts.flavor;
     match flavor {
         BlifFlavor::SisMV => {
             writeln!(w, ".short {from} {to}")
@@ -421,12 +824,7 @@ fn write_connect<W: fmt::Write>(
 // 1.9  Finite State Machine (.start_kiss … .end_kiss)
 // ---------------------------------------------------------------------------
 
-fn write_fsm<W: fmt::Write>(
-    fsm: &FSM,
-    cmd: &ModelCmd,
-    w: &mut W,
-    flavor: BlifFlavor,
-) -> fmt::Result {
+fn write_fsm<W: fmt::Write>(fsm: &FSM, w: &mut W, opts: &WriteOptions) -> fmt::Result {
     writeln!(w, ".start_kiss")?;
     writeln!(w, ".i {}", fsm.inputs)?;
     writeln!(w, ".o {}", fsm.outputs)?;
@@ -455,7 +853,7 @@ fn write_fsm<W: fmt::Write>(
     if let Some(ref order) = fsm.physical_latch_order {
         write!(w, ".latch_order")?;
         for latch in order {
-            write!(w, " {latch}")?;
+            write!(w, " {}", net(latch, opts)?)?;
         }
         writeln!(w)?;
     }
@@ -470,7 +868,7 @@ fn write_fsm<W: fmt::Write>(
         }
     }
 
-    write_attrs(cmd, w, flavor)
+    Ok(())
 }
 
 // ---------------------------------------------------------------------------
@@ -480,7 +878,7 @@ fn write_fsm<W: fmt::Write>(
 fn write_clock_events<W: fmt::Write>(
     ev: &ClockEvents,
     w: &mut W,
-    _flavor: BlifFlavor,
+    opts: &WriteOptions,
 ) -> fmt::Result {
     write!(w, ".clock_event {}", ev.percent)?;
     for event in &ev.events {
@@ -488,10 +886,11 @@ fn write_clock_events<W: fmt::Write>(
             ClockEdgeKind::Rise => "r",
             ClockEdgeKind::Fall => "f",
         };
+        let clock = net(&event.clock_name, opts)?;
         if let Some((before, after)) = event.before_after {
-            write!(w, " ({edge}'{} {before} {after})", event.clock_name)?;
+            write!(w, " ({edge}'{clock} {before} {after})")?;
         } else {
-            write!(w, " {edge}'{}", event.clock_name)?;
+            write!(w, " {edge}'{clock}")?;
         }
     }
     writeln!(w)?;
@@ -505,8 +904,9 @@ fn write_clock_events<W: fmt::Write>(
 fn write_delay_constraint<W: fmt::Write>(
     dc: &ModelDelayConstraint,
     w: &mut W,
-    flavor: BlifFlavor,
+    opts: &WriteOptions,
 ) -> fmt::Result {
+    let flavor = opts.flavor;
     match dc {
         ModelDelayConstraint::Input(idc) => {
             // Full 8-argument .delay — core BLIF
@@ -518,7 +918,7 @@ fn write_delay_constraint<W: fmt::Write>(
             writeln!(
                 w,
                 ".delay {} {phase} {} {} {} {} {} {}",
-                idc.input,
+                net(&idc.input, opts)?,
                 idc.load,
                 idc.max_load,
                 idc.block_rise,
@@ -541,13 +941,19 @@ fn write_delay_constraint<W: fmt::Write>(
         }
 
         ModelDelayConstraint::InputArrivalTime(sat) => {
-            write!(w, ".input_arrival {} {} {}", sat.signal, sat.rise, sat.fall)?;
+            write!(
+                w,
+                ".input_arrival {} {} {}",
+                net(&sat.signal, opts)?,
+                sat.rise,
+                sat.fall
+            )?;
             if let Some(ref rel) = sat.event_relative {
                 let ba = match rel.ba {
                     BeforeAfter::Before => 'b',
                     BeforeAfter::After => 'a',
                 };
-                write!(w, " {ba} {}", rel.event)?;
+                write!(w, " {ba} {}", net(&rel.event, opts)?)?;
             }
             writeln!(w)
         }
@@ -556,14 +962,16 @@ fn write_delay_constraint<W: fmt::Write>(
             write!(
                 w,
                 ".output_required {} {} {}",
-                sat.signal, sat.rise, sat.fall
+                net(&sat.signal, opts)?,
+                sat.rise,
+                sat.fall
             )?;
             if let Some(ref rel) = sat.event_relative {
                 let ba = match rel.ba {
                     BeforeAfter::Before => 'b',
                     BeforeAfter::After => 'a',
                 };
-                write!(w, " {ba} {}", rel.event)?;
+                write!(w, " {ba} {}", net(&rel.event, opts)?)?;
             }
             writeln!(w)
         }
@@ -577,7 +985,13 @@ fn write_delay_constraint<W: fmt::Write>(
         }
 
         ModelDelayConstraint::InputDrive(sd) => {
-            writeln!(w, ".input_drive {} {} {}", sd.signal, sd.rise, sd.fall)
+            writeln!(
+                w,
+                ".input_drive {} {} {}",
+                net(&sd.signal, opts)?,
+                sd.rise,
+                sd.fall
+            )
         }
 
         ModelDelayConstraint::DefaultInputDrive((r, f)) => {
@@ -585,7 +999,7 @@ fn write_delay_constraint<W: fmt::Write>(
         }
 
         ModelDelayConstraint::MaxInputLoad(sl) => {
-            writeln!(w, ".max_input_load {} {}", sl.signal, sl.load)
+            writeln!(w, ".max_input_load {} {}", net(&sl.signal, opts)?, sl.load)
         }
 
         ModelDelayConstraint::DefaultMaxInputLoad(l) => {
@@ -593,7 +1007,7 @@ fn write_delay_constraint<W: fmt::Write>(
         }
 
         ModelDelayConstraint::OutputLoad(sl) => {
-            writeln!(w, ".output_load {} {}", sl.signal, sl.load)
+            writeln!(w, ".output_load {} {}", net(&sl.signal, opts)?, sl.load)
         }
 
         ModelDelayConstraint::DefaultOutputLoad(l) => {
@@ -611,21 +1025,22 @@ fn write_delay_constraint<W: fmt::Write>(
 
         ModelDelayConstraint::InputRequired(sl) => {
             if flavor == BlifFlavor::ABC {
-                writeln!(w, ".input_required {} {}", sl.signal, sl.load)
+                writeln!(w, ".input_required {} {}", net(&sl.signal, opts)?, sl.load)
             } else {
                 // Standard core BLIF can use .delay <signal> <float>
-                writeln!(w, ".delay {} {}", sl.signal, sl.load)
+                writeln!(w, ".delay {} {}", net(&sl.signal, opts)?, sl.load)
             }
         }
 
         ModelDelayConstraint::OutputArrival(sl) => {
             if flavor == BlifFlavor::ABC {
-                writeln!(w, ".output_arrival {} {}", sl.signal, sl.load)
+                writeln!(w, ".output_arrival {} {}", net(&sl.signal, opts)?, sl.load)
             } else {
                 writeln!(
                     w,
                     "# .output_arrival {} {}  (ABC extension)",
-                    sl.signal, sl.load
+                    net(&sl.signal, opts)?,
+                    sl.load
                 )
             }
         }
@@ -635,6 +1050,7 @@ fn write_delay_constraint<W: fmt::Write>(
             out_sig,
             delay,
         } => {
+            let (in_sig, out_sig) = (net(in_sig, opts)?, net(out_sig, opts)?);
             if flavor == BlifFlavor::ABC {
                 writeln!(w, ".delay {in_sig} {out_sig} {delay}")
             } else {
@@ -654,12 +1070,12 @@ fn write_delay_constraint<W: fmt::Write>(
 fn write_constraint<W: fmt::Write>(
     signals: &[Str<16>],
     w: &mut W,
-    flavor: BlifFlavor,
+    opts: &WriteOptions,
 ) -> fmt::Result {
-    if matches!(flavor, BlifFlavor::SisMV) {
+    if matches!(opts.flavor, BlifFlavor::SisMV) {
         write!(w, ".constraint")?;
         for sig in signals {
-            write!(w, " {sig}")?;
+            write!(w, " {}", net(sig, opts)?)?;
         }
         writeln!(w)
     } else {
@@ -675,11 +1091,11 @@ fn write_constraint<W: fmt::Write>(
 // 4.7  BLIF-MV: .onehot
 // ---------------------------------------------------------------------------
 
-fn write_onehot<W: fmt::Write>(signals: &[Str<16>], w: &mut W, flavor: BlifFlavor) -> fmt::Result {
-    if matches!(flavor, BlifFlavor::SisMV) {
+fn write_onehot<W: fmt::Write>(signals: &[Str<16>], w: &mut W, opts: &WriteOptions) -> fmt::Result {
+    if matches!(opts.flavor, BlifFlavor::SisMV) {
         write!(w, ".onehot")?;
         for sig in signals {
-            write!(w, " {sig}")?;
+            write!(w, " {}", net(sig, opts)?)?;
         }
         writeln!(w)
     } else {
@@ -699,9 +1115,10 @@ fn write_reset<W: fmt::Write>(
     signal: &str,
     value: &[Tristate],
     w: &mut W,
-    flavor: BlifFlavor,
+    opts: &WriteOptions,
 ) -> fmt::Result {
-    if matches!(flavor, BlifFlavor::SisMV) {
+    let signal = net(signal, opts)?;
+    if matches!(opts.flavor, BlifFlavor::SisMV) {
         writeln!(w, ".reset {signal}")?;
         let val_str: String = value.iter().map(|t| t.to_string()).collect();
         writeln!(w, "{val_str}")
@@ -743,9 +1160,10 @@ fn write_gateinit<W: fmt::Write>(
     signal: &str,
     value: &FlipFlopInit,
     w: &mut W,
-    flavor: BlifFlavor,
+    opts: &WriteOptions,
 ) -> fmt::Result {
-    if matches!(flavor, BlifFlavor::SisMV | BlifFlavor::Yosys) {
+    let signal = net(signal, opts)?;
+    if matches!(opts.flavor, BlifFlavor::SisMV | BlifFlavor::Yosys) {
         let val_str = match value {
             FlipFlopInit::Const(true) => "1",
             FlipFlopInit::Const(false) => "0",
@@ -776,12 +1194,12 @@ fn write_mv<W: fmt::Write>(
     nvalues: usize,
     value_names: &[String],
     w: &mut W,
-    flavor: BlifFlavor,
+    opts: &WriteOptions,
 ) -> fmt::Result {
-    if matches!(flavor, BlifFlavor::SisMV) {
+    if matches!(opts.flavor, BlifFlavor::SisMV) {
         write!(w, ".mv")?;
         for var in variables {
-            write!(w, " {var}")?;
+            write!(w, " {}", net(var, opts)?)?;
         }
         write!(w, " {nvalues}")?;
         for vn in value_names {
@@ -792,3 +1210,279 @@ fn write_mv<W: fmt::Write>(
         writeln!(w, "# .mv ...  (BLIF-MV extension)")
     }
 }
+
+// ---------------------------------------------------------------------------
+// Streaming writer
+// ---------------------------------------------------------------------------
+
+struct StreamState<W> {
+    out: W,
+    opts: WriteOptions,
+    result: fmt::Result,
+}
+
+/// Shared handle to the output of a [`StreamingWriter`].
+///
+/// The consumer traits hand out per-model and per-gate objects from `&self`
+/// methods, so all of them write through a shared, reference-counted handle.
+struct StreamHandle<W>(Rc<RefCell<StreamState<W>>>);
+
+impl<W> Clone for StreamHandle<W> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<W: fmt::Write> StreamHandle<W> {
+    /// Run `f` on the output, unless a previous write already failed.
+    fn emit(&self, f: impl FnOnce(&mut W, &WriteOptions) -> fmt::Result) {
+        let state = &mut *self.0.borrow_mut();
+        if state.result.is_ok() {
+            state.result = f(&mut state.out, &state.opts);
+        }
+    }
+}
+
+/// A BLIF writer that implements the consumer traits.
+///
+/// Every command is written as soon as the parser reports it, following the
+/// same [`BlifFlavor`] rules as [`write_blif`], so that a file can be
+/// converted between dialects without ever building an AST:
+///
+/// ```rust
+/// use turbo_blif::parse_blif;
+/// use turbo_blif::writer::{BlifFlavor, StreamingWriter};
+///
+/// let mut writer = StreamingWriter::new(String::new(), BlifFlavor::Yosys);
+/// parse_blif("top.blif", &mut writer, ".model top\n.inputs a\n.outputs b\n.barbuf a b\n.end\n".lines()).unwrap();
+/// assert!(writer.finish().unwrap().contains(".conn a b"));
+/// ```
+///
+/// The only construct that is buffered is a `.start_kiss` block, because its
+/// header contains the number of transitions and states. For the same
+/// reason, [`WriteOptions::canonical`] has no effect on streamed output.
+///
+/// Since the consumer callbacks can not return errors, the first error of the
+/// underlying writer is remembered, all further output is dropped, and the
+/// error is returned by [`finish`](Self::finish).
+pub struct StreamingWriter<W> {
+    out: StreamHandle<W>,
+}
+
+impl<W: fmt::Write> StreamingWriter<W> {
+    /// Create a streaming writer that emits into `out` using the given
+    /// flavor or [`WriteOptions`].
+    pub fn new(out: W, opts: impl Into<WriteOptions>) -> Self {
+        Self {
+            out: StreamHandle(Rc::new(RefCell::new(StreamState {
+                out,
+                opts: opts.into(),
+                result: Ok(()),
+            }))),
+        }
+    }
+
+    /// Get the underlying writer back.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`fmt::Error`] if any write to the underlying writer failed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a [`StreamingModel`] or [`StreamingGate`] created by this
+    /// writer is still alive.
+    pub fn finish(self) -> Result<W, fmt::Error> {
+        let state = Rc::try_unwrap(self.out.0)
+            .ok()
+            .expect("StreamingWriter::finish called while a model is still being written")
+            .into_inner();
+        state.result.map(|_| state.out)
+    }
+}
+
+impl<W: fmt::Write> ModelConsumer for StreamingWriter<W> {
+    type Inner = StreamingModel<W>;
+
+    fn model(&self, meta: ModelMeta) -> Self::Inner {
+        self.out.emit(|w, opts| write_model_header(&meta, w, opts));
+        StreamingModel {
+            out: self.out.clone(),
+        }
+    }
+
+    fn model_done(&mut self, _model: Self::Inner) {
+        self.out.emit(|w, _| writeln!(w, ".end"));
+    }
+
+    fn search(&mut self, path: &str) {
+        self.out.emit(|w, _| writeln!(w, ".search {path}"));
+    }
+}
+
+/// The per-model [`CommandConsumer`] of a [`StreamingWriter`].
+pub struct StreamingModel<W> {
+    out: StreamHandle<W>,
+}
+
+/// The per-gate [`GateLutConsumer`] of a [`StreamingWriter`].
+///
+/// Writes each truth-table row as soon as it is parsed.
+pub struct StreamingGate<W> {
+    out: StreamHandle<W>,
+    align: Option<RowAlign>,
+    pending: Option<Gate>,
+}
+
+impl<W: fmt::Write> GateLutConsumer for StreamingGate<W> {
+    fn entry(&mut self, ins: SmallVec<[Tristate; 8]>, out: Option<bool>) {
+        if let Some(gate) = &mut self.pending {
+            gate.lut.0.push((ins, out));
+            return;
+        }
+        let align = self.align;
+        self.out.emit(|w, _| write_lut_row(&ins, out, align, w));
+    }
+}
+
+impl<W: fmt::Write> CommandConsumer for StreamingModel<W> {
+    type Gate = StreamingGate<W>;
+    type FSM = FSM;
+
+    fn gate(&self, gate: GateMeta) -> Self::Gate {
+        // the `.cover` header contains the number of rows, so gates that may
+        // be written as `.cover` are buffered until they are complete
+        if gate_syntax(&gate, usize::MAX, self.out.0.borrow().opts.flavor) == LutSyntax::Cover {
+            return StreamingGate {
+                out: self.out.clone(),
+                align: None,
+                pending: Some(gate.into()),
+            };
+        }
+
+        let mut align = None;
+        self.out.emit(|w, opts| {
+            align = write_gate_header(&gate, 0, w, opts)?;
+            Ok(())
+        });
+        StreamingGate {
+            out: self.out.clone(),
+            align,
+            pending: None,
+        }
+    }
+
+    fn gate_done(&mut self, gate: Self::Gate) {
+        if let Some(gate) = &gate.pending {
+            self.out.emit(|w, opts| write_gate(gate, w, opts));
+        }
+    }
+
+    fn fsm(&self, inputs: usize, outputs: usize, reset_state: Option<&str>) -> Self::FSM {
+        FSM {
+            inputs,
+            outputs,
+            reset_state: reset_state.map(|x| x.to_string()),
+            states: vec![],
+            physical_latch_order: None,
+            state_assignments: None,
+        }
+    }
+
+    fn fsm_done(
+        &mut self,
+        fsm: Self::FSM,
+        physical_latch_order: Option<Vec<String>>,
+        state_assignments: Option<Vec<(String, SmallVec<[bool; 8]>)>>,
+    ) {
+        let mut fsm = fsm;
+        fsm.physical_latch_order = physical_latch_order;
+        fsm.state_assignments = state_assignments;
+        self.out.emit(|w, opts| write_fsm(&fsm, w, opts));
+    }
+
+    fn ff(&mut self, ff: FlipFlop) {
+        self.out.emit(|w, opts| write_ff(&ff, w, opts));
+    }
+
+    fn lib_gate(&mut self, gate: LibGate) {
+        self.out.emit(|w, opts| write_lib_gate(&gate, w, opts));
+    }
+
+    fn lib_ff(&mut self, ff: LibFlipFlop) {
+        self.out.emit(|w, opts| write_lib_ff(&ff, w, opts));
+    }
+
+    fn sub_model(
+        &mut self,
+        model: &str,
+        map: Vec<(Str<16>, Str<16>)>,
+        instance_name: Option<&str>,
+    ) {
+        self.out
+            .emit(|w, opts| write_submodel(model, &map, instance_name, w, opts));
+    }
+
+    fn attr(&mut self, attr: CellAttr) {
+        self.out.emit(|w, opts| write_attr(&attr, w, opts));
+    }
+
+    fn connect(&mut self, from: &str, to: &str) {
+        self.out.emit(|w, opts| write_connect(from, to, w, opts));
+    }
+
+    fn set_area(&mut self, area: f64) {
+        self.out.emit(|w, _| writeln!(w, ".area {area}"));
+    }
+
+    fn blackbox(&mut self) {
+        self.out.emit(|w, opts| write_blackbox(w, opts.flavor));
+    }
+
+    fn model_delay_constraint(&mut self, constraint: ModelDelayConstraint) {
+        self.out
+            .emit(|w, opts| write_delay_constraint(&constraint, w, opts));
+    }
+
+    fn set_cycle_time(&mut self, cycle_time: f32) {
+        self.out.emit(|w, _| writeln!(w, ".cycle {cycle_time}"));
+    }
+
+    fn clock_events(&mut self, events: ClockEvents) {
+        self.out
+            .emit(|w, opts| write_clock_events(&events, w, opts));
+    }
+
+    fn constraint(&mut self, signals: &[Str<16>]) {
+        self.out.emit(|w, opts| write_constraint(signals, w, opts));
+    }
+
+    fn onehot(&mut self, signals: &[Str<16>]) {
+        self.out.emit(|w, opts| write_onehot(signals, w, opts));
+    }
+
+    fn reset(&mut self, signal: Str<16>, value: SmallVec<[Tristate; 8]>) {
+        self.out
+            .emit(|w, opts| write_reset(&signal, &value, w, opts));
+    }
+
+    fn ltlformula(&mut self, formula: &str) {
+        self.out
+            .emit(|w, opts| write_ltlformula(formula, w, opts.flavor));
+    }
+
+    fn spec(&mut self, filename: &str) {
+        self.out
+            .emit(|w, opts| write_spec(filename, w, opts.flavor));
+    }
+
+    fn gateinit(&mut self, signal: Str<16>, value: FlipFlopInit) {
+        self.out
+            .emit(|w, opts| write_gateinit(&signal, &value, w, opts));
+    }
+
+    fn mv(&mut self, variables: Vec<Str<16>>, nvalues: usize, value_names: Vec<String>) {
+        self.out
+            .emit(|w, opts| write_mv(&variables, nvalues, &value_names, w, opts));
+    }
+}